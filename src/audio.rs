@@ -1,5 +1,10 @@
+#[cfg(not(feature = "gui"))]
+use super::headless::Sound;
+#[cfg(feature = "gui")]
 use super::GameState;
+#[cfg(feature = "gui")]
 use macroquad::audio::{set_sound_volume, Sound};
+#[cfg(feature = "gui")]
 use macroquad::{
     audio::{load_sound, play_sound, PlaySoundParams},
     prelude::*,
@@ -20,6 +25,7 @@ pub struct GameSoundDictEntry {
     pub filepath: String,
 }
 
+#[cfg(feature = "gui")]
 pub async fn load_assets(gs: &mut GameState) {
     let files = vec![
         GameSoundDictEntry {
@@ -54,6 +60,7 @@ pub async fn load_assets(gs: &mut GameState) {
     }
 }
 
+#[cfg(feature = "gui")]
 pub fn play_audio(sounds: &Vec<Option<Sound>>, sound: GameSound) {
     let s = sounds[sound as usize];
 
@@ -68,3 +75,7 @@ pub fn play_audio(sounds: &Vec<Option<Sound>>, sound: GameSound) {
         ),
     }
 }
+
+// the servers run the game without sound
+#[cfg(not(feature = "gui"))]
+pub fn play_audio(_sounds: &Vec<Option<Sound>>, _sound: GameSound) {}
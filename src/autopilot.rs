@@ -0,0 +1,151 @@
+use super::{
+    intercept, wrapped_offset, Action, GameState, RunState, ANGLE_STEP, AUTOPILOT_AIM,
+    AUTOPILOT_CRUISE, AUTOPILOT_HORIZON, AUTOPILOT_MARGIN, BULLET_LIVE_TIME, BULLET_VEL,
+    DRONE_RADIUS, TICK,
+};
+use glam::{vec2, Vec2};
+
+/*
+    A scripted pilot. It looks at the game and answers with the same Action
+    the keyboard gives, so anything that drives the game with actions can
+    hand a ship over to it: the demo on the title screen, the soak test, or
+    a seat nobody sits in. It keeps nothing between ticks, the same game
+    always gets the same answer.
+
+    Staying alive comes first. Whatever will pass closer than the margin
+    within the horizon makes it turn away and burn. Otherwise it turns to
+    where the nearest target will be when a bullet gets there and fires
+    once it points close enough, thrusting when the target is out of reach
+    and braking when it flies too fast.
+*/
+pub fn act(gs: &GameState, i: usize) -> Action {
+    let player = &gs.players[i];
+    let last = player.last_action;
+    // screens that wait for a key press, keys only count on the tick they go down
+    let press_fire = Action {
+        fire: !last.fire,
+        ..Action::default()
+    };
+    let press_confirm = Action {
+        confirm: !last.confirm,
+        ..Action::default()
+    };
+    match gs.run_state {
+        RunState::Death | RunState::TurnChange => return press_fire,
+        RunState::StageComplete | RunState::MatchSummary => return press_confirm,
+        RunState::Running if player.down => return press_fire,
+        RunState::Running => {}
+    }
+
+    let ship = &player.ship;
+    let unit = gs.scl;
+    let ship_vel = ship.vel / TICK; // the ship moves by its velocity every tick
+    let mut action = Action::default();
+
+    if let Some(away) = threat(gs, ship.pos, ship_vel) {
+        let off = steer(&mut action, ship.angle, away);
+        action.thrust = off.abs() < 60.0;
+        action.fire = true; // whatever is in front might as well be shot
+        return action;
+    }
+
+    let speed = ship_vel.length() / unit;
+    let too_fast = speed > AUTOPILOT_CRUISE;
+    let Some((aim, time)) = target(gs, ship.pos) else {
+        brake(&mut action, ship.angle, ship_vel, too_fast);
+        return action;
+    };
+    let off = steer(&mut action, ship.angle, aim);
+    let in_reach = time < BULLET_LIVE_TIME as f32 * 0.8;
+    action.fire = off.abs() < AUTOPILOT_AIM && in_reach;
+    if !in_reach && !too_fast && off.abs() < 20.0 {
+        action.thrust = true;
+    } else {
+        brake(&mut action, ship.angle, ship_vel, too_fast);
+    }
+    action
+}
+
+/*
+    The way out from the most urgent of everything on a collision course,
+    None if nothing is. Urgency is how soon it passes closest, and the way
+    out is directly away from where that happens.
+*/
+fn threat(gs: &GameState, pos: Vec2, vel: Vec2) -> Option<Vec2> {
+    let obstacles = gs
+        .asteroids
+        .iter()
+        .filter(|a| !a.collision)
+        .map(|a| (a.pos, a.vel, a.w / 2.0))
+        .chain(gs.comets.iter().map(|c| (c.pos, c.vel, c.r)))
+        .chain(
+            gs.drones
+                .iter()
+                .map(|d| (d.pos, d.vel, DRONE_RADIUS * gs.scl)),
+        )
+        .chain(gs.boss.iter().map(|b| (b.pos, b.vel, b.r)));
+
+    let margin = AUTOPILOT_MARGIN * gs.scl;
+    let mut urgent: Option<(f32, Vec2, Vec2)> = None;
+    for (other, other_vel, r) in obstacles {
+        let d = wrapped_offset(pos, other, gs.screen);
+        let v = other_vel - vel;
+        let t = (-d.dot(v) / v.length_squared().max(f32::EPSILON)).clamp(0.0, AUTOPILOT_HORIZON);
+        let closest = d + v * t;
+        if closest.length() < r + margin && urgent.is_none_or(|u| t < u.0) {
+            urgent = Some((t, closest, v));
+        }
+    }
+
+    let (_, closest, v) = urgent?;
+    if closest.length() > f32::EPSILON {
+        Some(-closest)
+    } else {
+        Some(vec2(-v.y, v.x)) // dead on, either side of its path will do
+    }
+}
+
+// where to aim at the nearest target and how long a bullet takes to get there
+fn target(gs: &GameState, pos: Vec2) -> Option<(Vec2, f32)> {
+    let targets = gs
+        .asteroids
+        .iter()
+        .filter(|a| !a.collision)
+        .map(|a| (a.pos, a.vel))
+        .chain(gs.drones.iter().map(|d| (d.pos, d.vel)))
+        .chain(gs.boss.iter().map(|b| (b.pos, b.vel)));
+    targets
+        .map(|(other, vel)| (wrapped_offset(pos, other, gs.screen), vel))
+        .min_by(|a, b| a.0.length().total_cmp(&b.0.length()))
+        .map(|(d, vel)| lead(d, vel, BULLET_VEL))
+}
+
+/*
+    Where something at offset `d` moving at `vel` meets a bullet fired now
+    at `speed`. Bullets don't take on the ship's velocity so only the
+    target's counts. Something outrunning the bullets is aimed at where it
+    is.
+*/
+fn lead(d: Vec2, vel: Vec2, speed: f32) -> (Vec2, f32) {
+    match intercept(d, vel, speed) {
+        Some(t) => (d + vel * t, t),
+        None => (d, d.length() / speed),
+    }
+}
+
+// turns towards `dir`, how many degrees off it the ship points
+fn steer(action: &mut Action, angle: f32, dir: Vec2) -> f32 {
+    let wanted = dir.x.atan2(-dir.y).to_degrees();
+    let off = (wanted - angle).rem_euclid(360.0);
+    let off = if off > 180.0 { off - 360.0 } else { off };
+    action.right = off > ANGLE_STEP / 2.0;
+    action.left = off < -ANGLE_STEP / 2.0;
+    off
+}
+
+// the reverse thrusters slow the ship down when it points where it's going
+fn brake(action: &mut Action, angle: f32, vel: Vec2, too_fast: bool) {
+    let heading = angle.to_radians();
+    let forward = vec2(heading.sin(), -heading.cos());
+    action.reverse = too_fast && forward.dot(vel) > 0.0;
+}
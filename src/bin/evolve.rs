@@ -0,0 +1,32 @@
+/*
+    Breeds bots for the campaign with no window or sound, see evolve.rs.
+    Prints how every generation did, the best genomes go to --out and play
+    back with `asteroids-rs --genome <file>`.
+*/
+use asteroids_rs::evolve::{Config, Evolution};
+use std::process::exit;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match Config::parse(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    };
+
+    let mut evolution = Evolution::new(config);
+    for _ in 0..evolution.config.generations {
+        match evolution.step() {
+            Ok(report) => println!(
+                "generation {:4}  best {:10.1}  mean {:10.1}",
+                report.generation, report.best, report.mean
+            ),
+            Err(e) => {
+                eprintln!("could not save the genomes: {}", e);
+                exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+/*
+    A stand-in for an online leaderboard, see leaderboard.rs. Scores are
+    only taken once their replay plays out to the same score.
+*/
+use asteroids_rs::leaderboard::{Server, ServerConfig};
+use std::process::exit;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match ServerConfig::parse(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    };
+
+    let port = config.port;
+    let server = match Server::bind(config) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("could not open port {}: {}", port, e);
+            exit(1);
+        }
+    };
+    if let Ok(addr) = server.local_addr() {
+        println!("listening on {}", addr);
+    }
+    server.run();
+}
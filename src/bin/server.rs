@@ -0,0 +1,31 @@
+/*
+    Dedicated server, runs the game with no window or sound for clients
+    connecting over TCP. See server.rs for how they talk to it. Build it
+    with --no-default-features to leave macroquad out altogether.
+*/
+use asteroids_rs::server::{Config, Server};
+use std::process::exit;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match Config::parse(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    };
+
+    let port = config.port;
+    let mut server = match Server::bind(config) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("could not open port {}: {}", port, e);
+            exit(1);
+        }
+    };
+    if let Ok(addr) = server.local_addr() {
+        println!("listening on {}", addr);
+    }
+    server.run();
+}
@@ -0,0 +1,311 @@
+use super::{
+    audio, contains, fracture, intersects, kill_player, nearest_ship, score_kill, spawner,
+    stage::BossSpec, touches_ship, wrap, Boss, BossKind, BossPart, Explosion, FlyingPoint,
+    GameState, Material, Rng, RunState, BOSS_CORE_RATIO, BOSS_LAUNCH_VEL, BOSS_PURSUIT_VEL,
+    BOSS_SCORE, BOSS_SPIN,
+};
+use glam::{vec2, Vec2};
+use std::f32::consts::PI;
+
+pub const BOSS_PHASES: usize = 3;
+
+fn ring(r: f32, n: usize) -> Vec<Vec2> {
+    (0..n)
+        .map(|i| {
+            let a = i as f32 * 2.0 * PI / n as f32;
+            vec2(r * a.cos(), r * a.sin())
+        })
+        .collect()
+}
+
+/*
+    Colossus: a ring of armor chunks around the core. Neighbouring chunks
+    share their edges so there is no gap to shoot through.
+    Station: an octagon core with launcher pods sticking out of it.
+*/
+pub fn spawn(spec: &BossSpec, pos: Vec2, scl: f32, seed: u64) -> Boss {
+    let mut rng = Rng::new(seed);
+    let r = spec.size * scl;
+    let n = spec.parts.max(1);
+    let (core, parts) = match spec.kind {
+        BossKind::Colossus => {
+            let r_in = r * BOSS_CORE_RATIO;
+            let step = 2.0 * PI / n as f32;
+            let edge: Vec<f32> = (0..n).map(|_| r * rng.gen_range(0.85, 1.0)).collect();
+            let at = |a: f32, r: f32| vec2(r * a.cos(), r * a.sin());
+            let parts = (0..n)
+                .map(|i| {
+                    let a0 = i as f32 * step;
+                    let a1 = a0 + step;
+                    let mid = a0 + step / 2.0;
+                    BossPart {
+                        points: vec![
+                            at(a0, r_in),
+                            at(a0, edge[i]),
+                            at(mid, r * rng.gen_range(0.8, 1.0)),
+                            at(a1, edge[(i + 1) % n]),
+                            at(a1, r_in),
+                            at(mid, r_in),
+                        ],
+                        hits: spec.part_hits,
+                        launcher: false,
+                    }
+                })
+                .collect();
+            (ring(r_in, n * 2), parts)
+        }
+        BossKind::Station => {
+            let r_core = r * 0.6;
+            let half = r * 0.18;
+            let parts = (0..n)
+                .map(|i| {
+                    let a = i as f32 * 2.0 * PI / n as f32;
+                    let out = vec2(a.cos(), a.sin());
+                    let side = vec2(-out.y, out.x);
+                    let center = out * (r_core + half);
+                    BossPart {
+                        points: vec![
+                            center - out * half - side * half,
+                            center + out * half - side * half,
+                            center + out * half + side * half,
+                            center - out * half + side * half,
+                        ],
+                        hits: spec.part_hits,
+                        launcher: true,
+                    }
+                })
+                .collect();
+            (ring(r_core, 8), parts)
+        }
+    };
+
+    Boss {
+        kind: spec.kind,
+        pos,
+        vel: Vec2::ZERO,
+        angle: 0.0,
+        r,
+        core,
+        parts,
+        health: spec.health,
+        max_health: spec.health,
+        phase: 0,
+        spawn_interval: spec.spawn_interval,
+        last_spawn: 0.0,
+        hit_at: -1.0,
+    }
+}
+
+/*
+    Colossus: spins up and starts chasing the player as health drops, and
+    spits out asteroids in its last phase.
+    Station: launches from its pods until they are shot off, then from the
+    exposed core, and chases the player once below half health.
+*/
+fn phase_for(boss: &Boss) -> usize {
+    let health = boss.health as f32 / boss.max_health as f32;
+    match boss.kind {
+        BossKind::Colossus if health > 0.66 => 0,
+        BossKind::Colossus if health > 0.33 => 1,
+        BossKind::Station if !boss.core_exposed() => 0,
+        BossKind::Station if health > 0.5 => 1,
+        _ => BOSS_PHASES - 1,
+    }
+}
+
+fn pursues(boss: &Boss) -> bool {
+    match boss.kind {
+        BossKind::Colossus => boss.phase >= 1,
+        BossKind::Station => boss.phase >= 2,
+    }
+}
+
+fn launches_from_core(boss: &Boss) -> bool {
+    match boss.kind {
+        BossKind::Colossus => boss.phase >= 2,
+        BossKind::Station => boss.phase >= 1,
+    }
+}
+
+fn hits(poly: &[Vec2], a: Vec2, b: Vec2) -> bool {
+    (0..poly.len()).any(|i| intersects(a, b, poly[i], poly[(i + 1) % poly.len()]))
+        || contains(poly, b)
+}
+
+fn launch(gs: &mut GameState, from: Vec2, dir: Vec2, boss_vel: Vec2) {
+    gs.asteroids.push(spawner::asteroid(
+        from,
+        boss_vel + dir * BOSS_LAUNCH_VEL,
+        1.0,
+        Material::Rocky,
+        0.0,
+        gs.scl,
+        &mut gs.rng,
+    ));
+}
+
+pub fn update(gs: &mut GameState, delta: f32, time: f64) {
+    let Some(mut boss) = gs.boss.take() else {
+        return;
+    };
+
+    if let (true, Some(target)) = (pursues(&boss), nearest_ship(gs, boss.pos)) {
+        let to_player = (target - boss.pos).normalize_or_zero();
+        boss.vel = boss.vel.lerp(to_player * BOSS_PURSUIT_VEL, delta);
+    }
+    boss.pos = wrap(boss.pos + boss.vel * delta, boss.r, boss.r, gs.screen);
+    boss.angle = (boss.angle + BOSS_SPIN * (boss.phase + 1) as f32) % 360.0;
+
+    // launch asteroids
+    let interval = boss.spawn_interval / (boss.phase + 1) as f64;
+    if boss.last_spawn == 0.0 {
+        boss.last_spawn = time;
+    }
+    if time - boss.last_spawn > interval {
+        boss.last_spawn = time;
+        if launches_from_core(&boss) {
+            let a = (time as f32 * 1.7) % (2.0 * PI);
+            let dir = vec2(a.cos(), a.sin());
+            launch(gs, boss.pos + dir * boss.r * 1.1, dir, boss.vel);
+        }
+        for part in boss.parts.iter().filter(|p| p.launcher) {
+            let c = fracture::centroid(&boss.to_world(&part.points));
+            let dir = (c - boss.pos).normalize_or_zero();
+            launch(gs, c + dir * boss.r * 0.3, dir, boss.vel);
+        }
+    }
+
+    // bullets hit the outer parts first, the core only if they got through
+    let mut broken = Vec::new();
+    let mut last_hit_by = 0;
+    for bullet in gs.bullets.iter_mut().filter(|b| !b.collision) {
+        let a = bullet.pos - bullet.vel * delta;
+        let b = bullet.pos;
+        if let Some(i) =
+            (0..boss.parts.len()).find(|i| hits(&boss.to_world(&boss.parts[*i].points), a, b))
+        {
+            bullet.collision = true;
+            boss.parts[i].hits -= 1;
+            if boss.parts[i].hits <= 0 && !broken.iter().any(|(b, _)| *b == i) {
+                broken.push((i, bullet.owner.player()));
+            }
+        } else if hits(&boss.core(), a, b) {
+            bullet.collision = true;
+            if boss.core_exposed() {
+                boss.health -= 1;
+                boss.hit_at = time;
+                last_hit_by = bullet.owner.player();
+            }
+        }
+    }
+
+    broken.sort_unstable_by_key(|b| std::cmp::Reverse(b.0));
+    for (i, owner) in broken {
+        let part = boss.parts.remove(i);
+        let points = boss.to_world(&part.points);
+        let c = fracture::centroid(&points);
+        let out = (c - boss.pos).normalize_or_zero();
+        gs.explosions.push(Explosion::new(
+            c.x - boss.r / 4.0,
+            c.y - boss.r / 4.0,
+            boss.r / 2.0,
+            2.0,
+            time,
+        ));
+        audio::play_audio(&gs.sounds, audio::GameSound::ExplosionMedium);
+        score_kill(gs, owner, c, out * BOSS_LAUNCH_VEL, 2.0, Material::Rocky);
+        match boss.kind {
+            // broken armor drifts off as a regular asteroid
+            BossKind::Colossus => {
+                let mut chunk = spawner::asteroid(
+                    c,
+                    boss.vel + out * BOSS_LAUNCH_VEL / 2.0,
+                    2.0,
+                    Material::Rocky,
+                    0.0,
+                    gs.scl,
+                    &mut gs.rng,
+                );
+                chunk.points = points.iter().map(|p| *p - c).collect();
+                chunk.craters.clear();
+                chunk.mass = fracture::area(&chunk.points) / (gs.scl * gs.scl);
+                chunk.w = 2.0 * chunk.points.iter().fold(0.0, |m: f32, p| m.max(p.length()));
+                gs.asteroids.push(chunk);
+            }
+            BossKind::Station => {
+                fracture::shatter(
+                    &points,
+                    boss.pos,
+                    boss.vel,
+                    time,
+                    &mut gs.rng,
+                    &mut gs.debris,
+                );
+            }
+        }
+    }
+
+    let phase = phase_for(&boss);
+    if phase > boss.phase {
+        boss.phase = phase;
+        gs.explosions.push(Explosion::new(
+            boss.pos.x - boss.r,
+            boss.pos.y - boss.r,
+            boss.r * 2.0,
+            3.0,
+            time,
+        ));
+        audio::play_audio(&gs.sounds, audio::GameSound::ExplosionLarge);
+    }
+
+    for i in 0..gs.players.len() {
+        if gs.run_state != RunState::Running || gs.players[i].down {
+            continue;
+        }
+        let ship = gs.players[i].ship.points(gs.scl);
+        let touched = touches_ship(&boss.core(), &ship)
+            || boss
+                .parts
+                .iter()
+                .any(|p| touches_ship(&boss.to_world(&p.points), &ship));
+        if touched {
+            kill_player(gs, i);
+        }
+    }
+
+    if boss.health > 0 {
+        gs.boss = Some(boss);
+        return;
+    }
+
+    // defeated
+    let core = boss.core();
+    fracture::shatter(&core, boss.pos, boss.vel, time, &mut gs.rng, &mut gs.debris);
+    for part in boss.parts.iter() {
+        fracture::shatter(
+            &boss.to_world(&part.points),
+            boss.pos,
+            boss.vel,
+            time,
+            &mut gs.rng,
+            &mut gs.debris,
+        );
+    }
+    gs.explosions.push(Explosion::new(
+        boss.pos.x - boss.r * 1.5,
+        boss.pos.y - boss.r * 1.5,
+        boss.r * 3.0,
+        3.0,
+        time,
+    ));
+    audio::play_audio(&gs.sounds, audio::GameSound::ExplosionLarge);
+    let player = &mut gs.players[last_hit_by];
+    let val = BOSS_SCORE * player.score_multiplier;
+    player.score += val;
+    gs.flying_points.push(FlyingPoint {
+        created_at: time,
+        pos: boss.pos,
+        vel: vec2(0.0, -BOSS_LAUNCH_VEL),
+        val,
+    });
+}
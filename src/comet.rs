@@ -0,0 +1,133 @@
+use super::{
+    audio, contains, kill_player, segment_distance, Comet, Exhaust, Explosion, FlyingPoint,
+    GameState, Rng, RunState, COMET_RADIUS, COMET_SCORE, COMET_TAIL_COOLDOWN, COMET_TAIL_VEL,
+};
+use glam::{vec2, Vec2};
+use std::f32::consts::PI;
+
+const RANDOM_COMET_SPEED: f32 = 12.0; // units per second
+
+pub fn spawn(pos: Vec2, vel: Vec2, scl: f32) -> Comet {
+    Comet {
+        pos,
+        vel,
+        r: COMET_RADIUS * scl,
+        last_tail_frame: 0.0,
+        collision: false,
+    }
+}
+
+// enters just outside the screen and heads somewhere near the center
+fn random(rng: &mut Rng, screen: Vec2, scl: f32) -> Comet {
+    let center = screen / 2.0;
+    let a = rng.gen_range(0.0, 2.0 * PI);
+    let from = center + vec2(a.cos(), a.sin()) * (center.length() + scl);
+    let to = center
+        + vec2(
+            rng.gen_range(-0.25, 0.25) * screen.x,
+            rng.gen_range(-0.25, 0.25) * screen.y,
+        );
+    spawn(
+        from,
+        (to - from).normalize_or_zero() * RANDOM_COMET_SPEED * scl,
+        scl,
+    )
+}
+
+fn gone(c: &Comet, screen: Vec2) -> bool {
+    let center = screen / 2.0;
+    let outside = c.pos.x < -c.r * 4.0
+        || c.pos.y < -c.r * 4.0
+        || c.pos.x > screen.x + c.r * 4.0
+        || c.pos.y > screen.y + c.r * 4.0;
+    outside && (c.pos - center).dot(c.vel) > 0.0
+}
+
+/*
+    Comets fly straight and don't wrap, they are dropped once they leave the
+    screen. Their tails are exhaust particles blown away from the sun.
+*/
+pub fn update(gs: &mut GameState, delta: f32, time: f64) {
+    if gs.run_state == RunState::Running
+        && gs.comet_interval > 0.0
+        && gs.play_time - gs.last_comet > gs.comet_interval
+    {
+        gs.last_comet = gs.play_time;
+        let comet = random(&mut gs.rng, gs.screen, gs.scl);
+        gs.comets.push(comet);
+    }
+    if gs.comets.is_empty() {
+        return;
+    }
+
+    let ships: Vec<(usize, Vec<Vec2>)> = (0..gs.players.len())
+        .filter(|i| !gs.players[*i].down)
+        .map(|i| (i, gs.players[i].ship.points(gs.scl)))
+        .collect();
+    let mut player_collisions = Vec::new();
+    let mut shot = Vec::new();
+    for c in gs.comets.iter_mut() {
+        c.pos += c.vel * delta;
+
+        if time - c.last_tail_frame > COMET_TAIL_COOLDOWN {
+            c.last_tail_frame = time;
+            let side = vec2(-gs.sun.y, gs.sun.x);
+            for _ in 0..2 {
+                let spread = gs.rng.gen_range(-1.0, 1.0);
+                gs.exhaust.push(Exhaust {
+                    created_at: time,
+                    pos: c.pos + side * spread * c.r,
+                    size: gs.rng.gen_range(0.2, 0.6),
+                    vel: -gs.sun * COMET_TAIL_VEL * gs.rng.gen_range(0.5, 1.0)
+                        + side * spread * COMET_TAIL_VEL * 0.2,
+                });
+            }
+        }
+
+        for bullet in gs.bullets.iter_mut().filter(|b| !b.collision) {
+            if segment_distance(c.pos, bullet.pos - bullet.vel * delta, bullet.pos) < c.r {
+                bullet.collision = true;
+                if !c.collision {
+                    shot.push((c.pos, bullet.owner.player()));
+                }
+                c.collision = true;
+            }
+        }
+
+        for (i, ship) in ships.iter() {
+            if gs.run_state == RunState::Running
+                && (contains(ship, c.pos)
+                    || (0..3).any(|k| segment_distance(c.pos, ship[k], ship[(k + 1) % 3]) < c.r))
+                && !player_collisions.contains(i)
+            {
+                player_collisions.push(*i);
+            }
+        }
+    }
+
+    let screen = gs.screen;
+    gs.comets.retain(|c| !c.collision && !gone(c, screen));
+    for (pos, owner) in shot {
+        gs.explosions.push(Explosion::new(
+            pos.x - gs.scl,
+            pos.y - gs.scl,
+            gs.scl * 2.0,
+            2.0,
+            time,
+        ));
+        audio::play_audio(&gs.sounds, audio::GameSound::ExplosionMedium);
+        let player = &mut gs.players[owner];
+        let val = COMET_SCORE * player.score_multiplier;
+        player.score += val;
+        gs.flying_points.push(FlyingPoint {
+            created_at: time,
+            pos,
+            vel: vec2(0.0, -COMET_TAIL_VEL),
+            val,
+        });
+    }
+
+    for i in player_collisions {
+        kill_player(gs, i);
+    }
+}
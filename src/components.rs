@@ -1,9 +1,20 @@
-use super::spawner;
+#[cfg(not(feature = "gui"))]
+use super::headless::{color_u8, Color, KeyCode, Sound};
+use super::{
+    hotseat,
+    quicksave::{Craters, Points, Rgba, Unsaved, Xy},
+    spawner, stage,
+    stage::{AsteroidGroup, CometSpec, Stage, SwarmSpec},
+    Rng,
+};
+use glam::{const_vec2, vec2, Vec2};
+#[cfg(feature = "gui")]
 use macroquad::{
     audio::Sound,
     color_u8,
-    prelude::{const_vec2, get_time, rand, screen_height, screen_width, vec2, Color, Vec2},
+    prelude::{Color, KeyCode},
 };
+use nanoserde::{DeBin, DeJson, SerBin, SerJson};
 
 // vertical scale units. Screen height is 1:16
 pub const UNITS: f32 = 16.0;
@@ -11,8 +22,17 @@ pub const UNITS: f32 = 16.0;
 //colors
 pub const DARK: Color = color_u8!(49, 47, 40, 255);
 pub const LIGHT: Color = color_u8!(218, 216, 209, 255);
+pub const PLAYER_COLORS: [Color; 4] = [
+    LIGHT,
+    color_u8!(224, 178, 110, 255),
+    color_u8!(128, 188, 196, 255),
+    color_u8!(206, 132, 148, 255),
+];
+pub const SPLIT_GOLD: Color = color_u8!(224, 178, 110, 255); // faster than the best segment ever
+pub const SPLIT_BEHIND: Color = color_u8!(206, 132, 148, 255);
 
 //dimensions
+pub const GUI_BAR_HEIGHT: f32 = 50.0;
 pub const POINT_FONT_SIZE: f32 = 40.0;
 pub const FONT_SIZE: f32 = 20.0;
 pub const SCREEN_WIDTH: f32 = 400.0;
@@ -35,70 +55,348 @@ pub const ANGLE_STEP: f32 = 4.0;
 //time in seconds
 pub const BULLET_LIVE_TIME: f64 = 0.75;
 pub const TURRET_COOLDOWN: f64 = 0.5;
+pub const RAPID_COOLDOWN: f64 = 0.166;
 pub const EXHAUST_COOLDOWN: f64 = 0.175;
 pub const EXHAUST_LIVE_TIME: f64 = 2.0;
 pub const EXPLOSION_LIVE_TIME: f64 = 0.333;
 pub const FLYING_POINT_LIVE_TIME: f64 = 0.666;
+pub const DEBRIS_LIVE_TIME: f64 = 0.75;
 pub const GAME_TIME: f32 = 100.0;
+pub const TICK: f32 = 1.0 / 60.0; // fixed simulation step
+pub const MAX_TICKS_PER_FRAME: u32 = 4; // catching up after a stall
 pub const COMBO_TIMER: f32 = 3.0;
 
 pub const MAX_PLAYER_LIVES: i32 = 3;
 pub const SCORE_BASE: i32 = 16;
 
-#[derive(PartialEq)]
+// procedural asteroid outlines, see spawner::shape
+pub struct ShapeConfig {
+    pub vertices: usize,
+    pub octaves: u32,
+    pub base_freq: usize, // noise lattice points around the outline in the first octave
+    pub roughness: f32,
+    pub notches: usize,
+    pub notch_depth: f32,
+    pub craters: usize,
+}
+
+pub const ASTEROID_SHAPE: ShapeConfig = ShapeConfig {
+    vertices: 14,
+    octaves: 3,
+    base_freq: 3,
+    roughness: 0.3,
+    notches: 2,
+    notch_depth: 0.35,
+    craters: 2,
+};
+
+//materials
+pub const EXPLOSIVE_BLAST_RADIUS: f32 = 1.25; // times the asteroid width
+pub const CRYSTAL_SCORE_BONUS: i32 = 3;
+
+//bosses
+pub const BOSS_SCORE: i32 = 2000;
+pub const BOSS_CORE_RATIO: f32 = 0.45; // core radius relative to the boss radius
+pub const BOSS_SPIN: f32 = 0.2; // degrees per frame, multiplied by phase
+pub const BOSS_PURSUIT_VEL: f32 = 20.0;
+pub const BOSS_LAUNCH_VEL: f32 = 60.0;
+
+//comets
+pub const COMET_SCORE: i32 = 500;
+pub const COMET_RADIUS: f32 = 0.3; // units
+pub const COMET_TAIL_COOLDOWN: f64 = 0.02;
+pub const COMET_TAIL_VEL: f32 = 90.0;
+pub const DEFAULT_SUN: Vec2 = const_vec2!([-1.0, -1.0]);
+
+//swarms, distances in units
+pub const DRONE_RADIUS: f32 = 0.2;
+pub const DRONE_MAX_VEL: f32 = 6.0; // units per second
+pub const DRONE_VIEW: f32 = 2.0; // how far a drone sees its flockmates
+pub const DRONE_SEPARATION: f32 = 0.8;
+pub const SEPARATION_WEIGHT: f32 = 8.0;
+pub const ALIGNMENT_WEIGHT: f32 = 1.0;
+pub const COHESION_WEIGHT: f32 = 0.8;
+pub const SEEK_WEIGHT: f32 = 1.5;
+
+//weapons
+pub const SPREAD_ANGLE: f32 = 12.0; // degrees between the spread shot's bullets
+
+//co-op
+pub const REVIVE_RADIUS: f32 = 1.0; // units
+
+//versus
+pub const VERSUS_ROUNDS: usize = 3; // each lasts GAME_TIME
+pub const MAX_VERSUS_PLAYERS: usize = 4;
+pub const RESPAWN_INVULNERABILITY: f64 = 2.0;
+pub const ARENA_REFILL_DELAY: f32 = 3.0;
+
+//hot-seat
+pub const HOTSEAT_PLAYERS: usize = 2;
+
+//netcode, in ticks
+pub const INPUT_DELAY: u32 = 2;
+pub const MAX_PREDICTION: u32 = 8; // how far to run ahead of the remote inputs
+pub const HASH_INTERVAL: u32 = 30;
+pub const MAX_INPUTS_PER_PACKET: usize = 64;
+
+//dedicated server
+pub const SERVER_PORT: u16 = 7300;
+pub const SERVER_RATE: u32 = 30; // state broadcasts per second
+pub const MAX_MESSAGE_LEN: usize = 1 << 20; // bytes
+
+//autopilot
+pub const AUTOPILOT_HORIZON: f32 = 1.5; // seconds ahead it looks for collisions
+pub const AUTOPILOT_MARGIN: f32 = 1.5; // units kept clear of anything on a collision course
+pub const AUTOPILOT_CRUISE: f32 = 4.0; // units per second it flies at most when not evading
+pub const AUTOPILOT_AIM: f32 = 5.0; // degrees off target it still fires at
+pub const ATTRACT_IDLE: f32 = 15.0; // seconds on the title screen before the demo
+pub const ATTRACT_TIME: f32 = 60.0; // seconds the demo plays at most
+
+//high scores
+pub const HIGH_SCORES_FILE: &str = "scores.json";
+pub const HIGH_SCORES: usize = 10; // kept for each seed
+pub const GHOST_ALPHA: f32 = 0.3;
+
+//speedrun
+pub const SPLITS_FILE: &str = "splits.json";
+pub const SPLITS_SHOWN: usize = 3; // the last few splits on screen
+
+//daily challenge
+pub const DAILY_FILE: &str = "daily.json";
+pub const DAILY_ASTEROIDS: (f32, f32) = (0.75, 1.75); // range of the asteroid count multiplier
+pub const DAILY_SPEED: (f32, f32) = (0.8, 1.6); // range of the asteroid speed multiplier
+pub const DAILY_SHOWN: usize = 5; // best days on the title screen
+
+//online leaderboard
+pub const LEADERBOARD_PORT: u16 = 7500;
+pub const LEADERBOARD_TOP: usize = 5; // fetched for the title screen
+pub const LEADERBOARD_KEEP: usize = 100; // kept for each seed on the server
+pub const LEADERBOARD_TIMEOUT: f32 = 5.0; // seconds
+pub const LEADERBOARD_OUTBOX: &str = "outbox.json";
+pub const LEADERBOARD_MAX_TICKS: u32 = 60 * 60 * 60; // an hour, longer runs aren't replayed
+pub const LEADERBOARD_DEADLINE: f32 = 120.0; // seconds for a whole request, replay and all
+pub const LEADERBOARD_REQUESTS: usize = 8; // answered at once, more connections are turned away
+pub const LEADERBOARD_DAILY_DAYS: u64 = 7; // how old a daily challenge can be and still be ranked
+
+//quicksave
+pub const QUICKSAVE_FILE: &str = "quicksave.bin";
+pub const QUICKSAVE_NOTICE: f32 = 2.0; // seconds a save or load is announced for
+
+//learning environment
+pub const GYM_NEAREST: usize = 8; // asteroids in an observation
+pub const GYM_RAY_LENGTH: f32 = 8.0; // units
+pub const GYM_SCORE_WEIGHT: f32 = 0.01; // reward per point
+pub const GYM_DEATH_PENALTY: f32 = 1.0;
+
+//neuroevolution
+pub const EVOLVE_POPULATION: usize = 64;
+pub const EVOLVE_GENERATIONS: usize = 50;
+pub const EVOLVE_ELITE: usize = 4; // the best genomes carried over unchanged
+pub const EVOLVE_MUTATION_RATE: f32 = 0.1; // chance of each weight changing
+pub const EVOLVE_MUTATION_STRENGTH: f32 = 0.5; // largest change to a weight
+pub const EVOLVE_HIDDEN: usize = 16; // neurons in the hidden layer
+pub const EVOLVE_RAYS: usize = 8;
+pub const EVOLVE_RUNS: usize = 4; // seeded runs each genome plays per generation
+pub const EVOLVE_MAX_TICKS: u32 = 60 * 60; // a run is a minute at most
+
+//wingman, distances in units
+pub const WINGMAN_ORBIT: f32 = 2.0;
+pub const WINGMAN_ORBIT_SPEED: f32 = 1.5; // radians per second
+pub const WINGMAN_RADIUS: f32 = 0.3;
+pub const WINGMAN_RANGE: f32 = 7.0;
+pub const WINGMAN_COOLDOWN: f64 = 1.25;
+
+//gravity wells
+pub const WELL_SOFTENING: f32 = 0.5; // units
+pub const WELL_FIELD_LINES: i32 = 12;
+pub const TRAJECTORY_STEPS: usize = 240;
+pub const TRAJECTORY_STEP_TIME: f32 = 1.0 / 30.0;
+
+//fragmentation
+pub const FRAGMENT_IMPULSE: f32 = 40.0;
+pub const FRAGMENT_SPREAD: f32 = 25.0;
+pub const FRACTURE_MIN_AREA: f32 = 0.15; // in units squared
+pub const FRACTURE_SPIN: f32 = 1.5; // degrees per frame
+
+// what an asteroid of a given size breaks into in stages without their
+// own fragment rules, as material, size and the sizes of the pieces
+pub const FRAGMENT_RULES: [(Option<Material>, f32, &[f32]); 4] = [
+    (Some(Material::Ice), 3.0, &[1.0, 1.0, 1.0, 1.0, 1.0, 1.0]),
+    (Some(Material::Ice), 2.0, &[1.0, 1.0, 1.0, 1.0]),
+    (None, 3.0, &[2.0, 2.0, 2.0]),
+    (None, 2.0, &[1.0, 1.0]),
+];
+
+#[derive(SerBin, DeBin, Clone, Copy, PartialEq)]
 pub enum RunState {
     Running,
     Death,
-    StageComplete,
+    StageComplete, // or round over in versus
+    MatchSummary,
+    TurnChange, // hot-seat interstitial before the next player's turn
 }
 
+impl RunState {
+    // in declaration order, `ALL[state as usize]` is the state itself
+    pub const ALL: [RunState; 5] = [
+        RunState::Running,
+        RunState::Death,
+        RunState::StageComplete,
+        RunState::MatchSummary,
+        RunState::TurnChange,
+    ];
+}
+
+#[derive(SerBin, DeBin, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Campaign, // alone or in co-op
+    Versus,
+    HotSeat, // alternating turns, each player on their own field
+}
+
+impl Mode {
+    pub const ALL: [Mode; 3] = [Mode::Campaign, Mode::Versus, Mode::HotSeat];
+}
+
+#[derive(SerJson, DeJson, SerBin, DeBin, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Weapon {
+    #[default]
+    Blaster, // one bullet at a time
+    Spread, // three bullets fanned out
+    Rapid,  // fires three times as often
+}
+
+impl Weapon {
+    pub const ALL: [Weapon; 3] = [Weapon::Blaster, Weapon::Spread, Weapon::Rapid];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weapon::Blaster => "Blaster",
+            Weapon::Spread => "Spread shot",
+            Weapon::Rapid => "Rapid fire",
+        }
+    }
+}
+
+// changes to the normal rules, see daily.rs
+#[derive(SerJson, DeJson, SerBin, DeBin, Clone, Copy, PartialEq, Debug)]
+pub struct Modifiers {
+    pub weapon: Weapon,
+    pub asteroids: f32, // multiplier on the number of asteroids in each group
+    pub speed: f32,     // multiplier on the speed asteroids start out with
+}
+
+impl Default for Modifiers {
+    fn default() -> Self {
+        Modifiers {
+            weapon: Weapon::Blaster,
+            asteroids: 1.0,
+            speed: 1.0,
+        }
+    }
+}
+
+#[derive(SerBin, DeBin, Clone)]
 pub struct FlyingPoint {
+    #[nserde(proxy = "Xy")]
     pub pos: Vec2,
+    #[nserde(proxy = "Xy")]
     pub vel: Vec2,
     pub val: i32,
     pub created_at: f64,
 }
+#[derive(SerBin, DeBin, Clone)]
 pub struct Explosion {
+    #[nserde(proxy = "Xy")]
     pub pos: Vec2,
     pub width: f32,
     pub size: f32,
     pub created_at: f64,
 }
 
+#[derive(SerBin, DeBin, Clone)]
 pub struct Star {
+    #[nserde(proxy = "Xy")]
     pub pos: Vec2,
     pub size: f32,
 }
 
 impl Explosion {
-    pub fn new(x: f32, y: f32, width: f32, size: f32) -> Self {
+    pub fn new(x: f32, y: f32, width: f32, size: f32, time: f64) -> Self {
         Explosion {
             pos: vec2(x, y),
             width,
             size,
-            created_at: get_time(),
+            created_at: time,
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(DeJson, SerBin, DeBin, Clone, Copy, PartialEq, Debug)]
+pub enum Material {
+    Rocky,
+    Metallic,  // takes several hits, bullets ricochet off
+    Explosive, // blast destroys whatever is close
+    Ice,       // shatters into many small shards
+    Crystal,   // worth bonus score
+}
+
+impl Material {
+    pub const ALL: [Material; 5] = [
+        Material::Rocky,
+        Material::Metallic,
+        Material::Explosive,
+        Material::Ice,
+        Material::Crystal,
+    ];
+
+    pub fn hits(&self, size: f32) -> i32 {
+        match self {
+            Material::Metallic => 1 + size as i32,
+            _ => 1,
+        }
+    }
+}
+
+// where and in which direction a bullet struck
+#[derive(SerBin, DeBin, Clone, Copy)]
+pub struct Impact {
+    #[nserde(proxy = "Xy")]
+    pub pos: Vec2,
+    #[nserde(proxy = "Xy")]
+    pub dir: Vec2,
+    pub owner: usize, // player credited for what the impact destroys
+}
+
+#[derive(SerBin, DeBin, Clone)]
 pub struct Asteroid {
+    #[nserde(proxy = "Xy")]
     pub pos: Vec2,
+    #[nserde(proxy = "Xy")]
     pub vel: Vec2,
     pub angle: f32,
+    pub spin: f32,
     pub size: f32,
+    pub mass: f32,
+    #[nserde(proxy = "Points")]
     pub points: Vec<Vec2>,
+    #[nserde(proxy = "Craters")]
+    pub craters: Vec<Vec<Vec2>>,
+    pub seed: u64,
+    pub material: Material,
+    pub hits: i32,
     pub w: f32,
     pub collision: bool,
+    pub impact: Option<Impact>,
 }
 
 impl Asteroid {
-    pub fn points(&self) -> Vec<Vec2> {
+    fn to_world(&self, local: &[Vec2]) -> Vec<Vec2> {
         let rot = self.angle.to_radians();
         let c = rot.cos();
         let s = rot.sin();
         let mut points = Vec::new();
-        self.points.iter().for_each(|p| {
+        local.iter().for_each(|p| {
             points.push(vec2(
                 self.pos.x + p.x * c - p.y * s,
                 self.pos.y + p.x * s + p.y * c,
@@ -107,29 +405,223 @@ impl Asteroid {
 
         points
     }
+
+    pub fn points(&self) -> Vec<Vec2> {
+        self.to_world(&self.points)
+    }
+
+    pub fn craters(&self) -> Vec<Vec<Vec2>> {
+        self.craters.iter().map(|c| self.to_world(c)).collect()
+    }
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone, Copy, PartialEq, Debug)]
+pub enum BossKind {
+    Colossus, // armored giant, the core is only reachable through broken armor
+    Station,  // launches asteroids from its pods, the core is shielded until they are gone
 }
 
+#[derive(SerBin, DeBin, Clone)]
+pub struct BossPart {
+    #[nserde(proxy = "Points")]
+    pub points: Vec<Vec2>,
+    pub hits: i32,
+    pub launcher: bool,
+}
+
+#[derive(SerBin, DeBin, Clone)]
+pub struct Boss {
+    pub kind: BossKind,
+    #[nserde(proxy = "Xy")]
+    pub pos: Vec2,
+    #[nserde(proxy = "Xy")]
+    pub vel: Vec2,
+    pub angle: f32,
+    pub r: f32,
+    #[nserde(proxy = "Points")]
+    pub core: Vec<Vec2>,
+    pub parts: Vec<BossPart>,
+    pub health: i32,
+    pub max_health: i32,
+    pub phase: usize,
+    pub spawn_interval: f64,
+    pub last_spawn: f64,
+    pub hit_at: f64,
+}
+
+impl Boss {
+    pub fn to_world(&self, local: &[Vec2]) -> Vec<Vec2> {
+        let rot = self.angle.to_radians();
+        let c = rot.cos();
+        let s = rot.sin();
+        local
+            .iter()
+            .map(|p| {
+                vec2(
+                    self.pos.x + p.x * c - p.y * s,
+                    self.pos.y + p.x * s + p.y * c,
+                )
+            })
+            .collect()
+    }
+
+    pub fn core(&self) -> Vec<Vec2> {
+        self.to_world(&self.core)
+    }
+
+    // the station core is shielded while any launcher pod is left
+    pub fn core_exposed(&self) -> bool {
+        match self.kind {
+            BossKind::Colossus => true,
+            BossKind::Station => !self.parts.iter().any(|p| p.launcher),
+        }
+    }
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone, Copy, PartialEq, Default)]
+pub enum WellKind {
+    #[default]
+    Well, // only pulls
+    BlackHole, // destroys whatever crosses the event horizon
+    Planet,    // solid surface that asteroids and ships crash into
+}
+
+#[derive(SerBin, DeBin, Clone)]
+pub struct GravityWell {
+    pub kind: WellKind,
+    #[nserde(proxy = "Xy")]
+    pub pos: Vec2,
+    pub strength: f32,
+    pub radius: f32, // pixels, event horizon or planet surface
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone, Copy, PartialEq, Default)]
+pub enum ZoneKind {
+    #[default]
+    Nebula, // thick friction, hides asteroids away from the ship
+    IonStorm,    // knocks out the thrusters in short bursts
+    DebrisField, // wears down the hull and pushes the ship along
+}
+
+#[derive(SerBin, DeBin, Clone)]
+pub struct Zone {
+    pub kind: ZoneKind,
+    #[nserde(proxy = "Xy")]
+    pub pos: Vec2,
+    pub radius: f32,
+    pub friction: f32,    // multiplier on FRICT
+    pub visibility: f32,  // pixels
+    pub outage_rate: f32, // outages per second
+    pub outage_time: f64,
+    pub damage: f32, // hull per second
+    #[nserde(proxy = "Xy")]
+    pub drift: Vec2, // pixels per second squared
+}
+
+impl Zone {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        self.pos.distance(pos) < self.radius
+    }
+}
+
+#[derive(SerBin, DeBin, Clone)]
 pub struct Exhaust {
+    #[nserde(proxy = "Xy")]
     pub pos: Vec2,
     pub created_at: f64,
+    #[nserde(proxy = "Xy")]
     pub vel: Vec2,
     pub size: f32,
 }
 
+#[derive(SerBin, DeBin, Clone)]
+pub struct Comet {
+    #[nserde(proxy = "Xy")]
+    pub pos: Vec2,
+    #[nserde(proxy = "Xy")]
+    pub vel: Vec2, // pixels per second
+    pub r: f32,
+    pub last_tail_frame: f64,
+    pub collision: bool,
+}
+
+#[derive(SerBin, DeBin, Clone)]
+pub struct Drone {
+    #[nserde(proxy = "Xy")]
+    pub pos: Vec2,
+    #[nserde(proxy = "Xy")]
+    pub vel: Vec2, // pixels per second
+    pub swarm: usize,
+    pub collision: bool,
+}
+
+#[derive(SerBin, DeBin, Clone)]
+pub struct Wingman {
+    #[nserde(proxy = "Xy")]
+    pub pos: Vec2,
+    pub orbit: f32, // radians around the ship
+    #[nserde(proxy = "Xy")]
+    pub aim: Vec2,
+    pub last_turret_frame: f64,
+}
+
+// line segment left over from a fractured asteroid
+#[derive(SerBin, DeBin, Clone)]
+pub struct Debris {
+    #[nserde(proxy = "Xy")]
+    pub pos: Vec2,
+    #[nserde(proxy = "Xy")]
+    pub vel: Vec2,
+    pub angle: f32,
+    pub spin: f32,
+    pub len: f32,
+    pub created_at: f64,
+}
+
+/*
+    Who fired a bullet. The wingman's kills and combo go to the first
+    player it flies with, but its shots aren't theirs: they count for
+    neither their hits nor their accuracy.
+*/
+#[derive(SerBin, DeBin, Clone, Copy, PartialEq, Debug)]
+pub enum Owner {
+    Player(usize),
+    Wingman,
+}
+
+impl Owner {
+    // index of the player who gets the score
+    pub fn player(self) -> usize {
+        match self {
+            Owner::Player(i) => i,
+            Owner::Wingman => 0,
+        }
+    }
+}
+
+#[derive(SerBin, DeBin, Clone)]
 pub struct Bullet {
+    #[nserde(proxy = "Xy")]
     pub pos: Vec2,
     pub created_at: f64,
+    #[nserde(proxy = "Xy")]
     pub vel: Vec2,
     pub collision: bool,
+    pub owner: Owner,
 }
 
+#[derive(SerBin, DeBin, Clone)]
 pub struct Spaceship {
     pub w: f32,
     pub h: f32,
+    #[nserde(proxy = "Xy")]
     pub pos: Vec2,
     pub angle: f32,
+    #[nserde(proxy = "Xy")]
     pub vel: Vec2,
     pub strafing: (bool, bool),
+    pub hull: f32,
+    pub thrusters_offline_until: f64,
     pub last_turret_frame: f64,
     pub last_exhaust_frame: f64,
 }
@@ -143,6 +635,8 @@ impl Spaceship {
             angle: 0.0,
             vel: vec2(0.0, 0.0),
             strafing: (false, false),
+            hull: 1.0,
+            thrusters_offline_until: 0.0,
             last_turret_frame: 0.0,
             last_exhaust_frame: 0.0,
         }
@@ -150,7 +644,8 @@ impl Spaceship {
     pub fn reset(&mut self) {
         self.vel = vec2(0.0, 0.0);
         self.angle = 0.0;
-        self.pos = vec2(screen_width() / 2.0, screen_height() / 2.0);
+        self.hull = 1.0;
+        self.thrusters_offline_until = 0.0;
     }
 
     pub fn points(&self, scale: f32) -> Vec<Vec2> {
@@ -183,55 +678,276 @@ impl Spaceship {
     }
 }
 
+/*
+    What one player does during one tick. The simulation only ever sees
+    these, whether they were read from the keyboard or arrived over the
+    network, which keeps it deterministic.
+*/
+#[derive(SerBin, DeBin, Clone, Copy, Default, PartialEq, Debug)]
+pub struct Action {
+    pub left: bool,
+    pub right: bool,
+    pub thrust: bool,
+    pub reverse: bool,
+    pub strafe_left: bool,
+    pub strafe_right: bool,
+    pub fire: bool,
+    pub confirm: bool, // continue past the stage and round screens
+}
+
+impl Action {
+    pub fn to_bits(self) -> u8 {
+        [
+            self.left,
+            self.right,
+            self.thrust,
+            self.reverse,
+            self.strafe_left,
+            self.strafe_right,
+            self.fire,
+            self.confirm,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, b)| bits | (*b as u8) << i)
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        let bit = |i: u8| bits & (1 << i) != 0;
+        Action {
+            left: bit(0),
+            right: bit(1),
+            thrust: bit(2),
+            reverse: bit(3),
+            strafe_left: bit(4),
+            strafe_right: bit(5),
+            fire: bit(6),
+            confirm: bit(7),
+        }
+    }
+}
+
+// any of the keys listed for an action triggers it
+#[derive(Clone, Copy)]
+pub struct Bindings {
+    pub left: &'static [KeyCode],
+    pub right: &'static [KeyCode],
+    pub thrust: &'static [KeyCode],
+    pub reverse: &'static [KeyCode],
+    pub strafe_left: &'static [KeyCode],
+    pub strafe_right: &'static [KeyCode],
+    pub fire: &'static [KeyCode],
+}
+
+pub const SOLO_BINDINGS: Bindings = Bindings {
+    left: &[KeyCode::Left, KeyCode::A],
+    right: &[KeyCode::Right, KeyCode::D],
+    thrust: &[KeyCode::Up, KeyCode::W],
+    reverse: &[KeyCode::Down, KeyCode::S],
+    strafe_left: &[KeyCode::Q],
+    strafe_right: &[KeyCode::E],
+    fire: &[KeyCode::Space],
+};
+
+// player one keeps the left side of the keyboard, player two the right
+pub const COOP_BINDINGS: [Bindings; 2] = [
+    Bindings {
+        left: &[KeyCode::A],
+        right: &[KeyCode::D],
+        thrust: &[KeyCode::W],
+        reverse: &[KeyCode::S],
+        strafe_left: &[KeyCode::Q],
+        strafe_right: &[KeyCode::E],
+        fire: &[KeyCode::Space],
+    },
+    Bindings {
+        left: &[KeyCode::Left],
+        right: &[KeyCode::Right],
+        thrust: &[KeyCode::Up],
+        reverse: &[KeyCode::Down],
+        strafe_left: &[KeyCode::Comma],
+        strafe_right: &[KeyCode::Period],
+        fire: &[KeyCode::RightControl, KeyCode::Slash],
+    },
+];
+
+// co-op layouts plus the middle of the keyboard and the numpad
+pub const VERSUS_BINDINGS: [Bindings; MAX_VERSUS_PLAYERS] = [
+    COOP_BINDINGS[0],
+    COOP_BINDINGS[1],
+    Bindings {
+        left: &[KeyCode::J],
+        right: &[KeyCode::L],
+        thrust: &[KeyCode::I],
+        reverse: &[KeyCode::K],
+        strafe_left: &[KeyCode::U],
+        strafe_right: &[KeyCode::O],
+        fire: &[KeyCode::M],
+    },
+    Bindings {
+        left: &[KeyCode::Kp4],
+        right: &[KeyCode::Kp6],
+        thrust: &[KeyCode::Kp8],
+        reverse: &[KeyCode::Kp5],
+        strafe_left: &[KeyCode::Kp7],
+        strafe_right: &[KeyCode::Kp9],
+        fire: &[KeyCode::Kp0, KeyCode::KpEnter],
+    },
+];
+
+#[derive(SerBin, DeBin, Clone)]
+pub struct Player {
+    pub ship: Spaceship,
+    #[nserde(proxy = "Rgba")]
+    pub color: Color,
+    #[nserde(proxy = "Unsaved")]
+    pub bindings: Bindings,
+    pub lives: i32,
+    pub score: i32,
+    pub combo: i32,
+    pub combo_time: f32,
+    pub score_multiplier: i32,
+    pub down: bool, // shot down, the ship stays behind as a marker to revive at
+    pub kills: i32,
+    pub deaths: i32,
+    pub shots: i32,
+    pub hits: i32, // bullets that struck something
+    pub invulnerable_until: f64,
+    pub last_action: Action, // the previous tick's, to tell presses from holds
+}
+
+impl Player {
+    pub fn new(color: Color, bindings: Bindings) -> Self {
+        Player {
+            ship: Spaceship::new(0.0, 0.0, PLAYER_WIDTH, PLAYER_HEIGHT),
+            color,
+            bindings,
+            lives: MAX_PLAYER_LIVES,
+            score: 0,
+            combo: 0,
+            combo_time: 0.0,
+            score_multiplier: 1,
+            down: false,
+            kills: 0,
+            deaths: 0,
+            shots: 0,
+            hits: 0,
+            invulnerable_until: 0.0,
+            last_action: Action::default(),
+        }
+    }
+}
+
+#[derive(SerBin, DeBin, Clone)]
 pub struct GameState {
     pub scl: f32, // scale
-    pub player: Spaceship,
+    #[nserde(proxy = "Xy")]
+    pub screen: Vec2,
+    pub players: Vec<Player>,
+    pub mode: Mode,
+    pub round: usize,
+    pub seat: usize,           // whose turn it is in hot-seat
+    pub bench: Vec<GameState>, // the other hot-seat players' games, waiting their turn
+    pub friendly_fire: bool,
+    pub wingman: Option<Wingman>,
+    pub wingman_enabled: bool,
     pub flying_points: Vec<FlyingPoint>,
     pub background: Vec<Star>,
     pub exhaust: Vec<Exhaust>,
+    pub debris: Vec<Debris>,
     pub explosions: Vec<Explosion>,
     pub bullets: Vec<Bullet>,
     pub asteroids: Vec<Asteroid>,
-    pub lives: i32,
+    pub boss: Option<Boss>,
+    pub comets: Vec<Comet>,
+    pub drones: Vec<Drone>,
+    #[nserde(proxy = "Xy")]
+    pub sun: Vec2, // direction towards the sun, comet tails point away from it
+    pub wells: Vec<GravityWell>,
+    pub zones: Vec<Zone>,
     pub run_state: RunState,
     pub play_time: f32,
-    pub combo_time: f32,
-    pub combo: i32,
-    pub score_multiplier: i32,
-    pub score: i32,
+    pub time: f64, // simulation clock, advances by TICK
+    pub tick: u32,
+    pub rng: Rng, // reseeded by every stage, everything random in the simulation draws from it
+    pub seed: u64, // mixed into every stage's seed, 0 plays the stages as designed
+    pub modifiers: Modifiers,
+    pub daily: bool, // today's daily challenge, there's one attempt at it
     pub debug: bool,
+    #[nserde(proxy = "Unsaved")]
     pub sounds: Vec<Option<Sound>>,
+    pub stages: Vec<Stage>,
+    pub stage: usize,
+    pub pending_groups: Vec<AsteroidGroup>,
+    pub pending_comets: Vec<CometSpec>,
+    pub pending_swarms: Vec<SwarmSpec>,
+    pub comet_interval: f32,
+    pub last_comet: f32,
 }
 
-pub fn get_new_game_state() -> GameState {
-    let scale = screen_height() / UNITS;
-    let center_pos = vec2(screen_width() / 2.0, screen_height() / 2.0);
+pub fn get_new_game_state(players: usize, mode: Mode) -> GameState {
+    if mode == Mode::HotSeat {
+        return hotseat::new_game(HOTSEAT_PLAYERS);
+    }
+    // the window catches up through stage::resize, this works without one
+    let screen = vec2(SCREEN_WIDTH, SCREEN_HEIGHT);
+    let players = match (mode, players) {
+        (Mode::Versus, n) => (0..n.clamp(2, MAX_VERSUS_PLAYERS))
+            .map(|i| Player::new(PLAYER_COLORS[i], VERSUS_BINDINGS[i]))
+            .collect(),
+        (_, 1) => vec![Player::new(PLAYER_COLORS[0], SOLO_BINDINGS)],
+        (_, _) => (0..2)
+            .map(|i| Player::new(PLAYER_COLORS[i], COOP_BINDINGS[i]))
+            .collect(),
+    };
+    let stages = match mode {
+        Mode::Versus => stage::load_arenas(),
+        _ => stage::load(),
+    };
 
-    let gs = GameState {
-        asteroids: spawner::asteroids(
-            center_pos,
-            screen_width() / ASTEROID_MAX_SIZE,
-            3,
-            ASTEROID_MAX_SIZE,
-            scale,
-        ),
-        background: spawner::stars(50, screen_width(), screen_height()),
+    let mut gs = GameState {
+        asteroids: Vec::new(),
+        boss: None,
+        comets: Vec::new(),
+        drones: Vec::new(),
+        sun: DEFAULT_SUN,
+        wells: Vec::new(),
+        zones: Vec::new(),
+        background: spawner::stars(50, screen.x, screen.y),
         bullets: Vec::new(),
-        combo: 0,
-        combo_time: 0.0,
+        debris: Vec::new(),
         debug: false,
         exhaust: Vec::new(),
         explosions: Vec::new(),
         flying_points: Vec::new(),
-        lives: MAX_PLAYER_LIVES,
         play_time: 0.0,
-        player: Spaceship::new(center_pos.x, center_pos.y, PLAYER_WIDTH, PLAYER_HEIGHT),
+        time: 0.0,
+        tick: 0,
+        rng: Rng::new(0),
+        seed: 0,
+        modifiers: Modifiers::default(),
+        daily: false,
+        players,
+        mode,
+        round: 0,
+        seat: 0,
+        bench: Vec::new(),
+        friendly_fire: false,
+        wingman: None,
+        wingman_enabled: false,
         run_state: RunState::Running,
-        scl: scale,
-        score: 0,
-        score_multiplier: 1,
+        scl: screen.y / UNITS,
+        screen,
         sounds: vec![None; 10],
+        stages,
+        stage: 0,
+        pending_groups: Vec::new(),
+        pending_comets: Vec::new(),
+        pending_swarms: Vec::new(),
+        comet_interval: 0.0,
+        last_comet: 0.0,
     };
+    stage::start(&mut gs, 0);
 
     gs
 }
@@ -0,0 +1,64 @@
+use super::{contains, intersects, kill_player, versus, GameState, Mode, RunState, REVIVE_RADIUS};
+use glam::Vec2;
+
+/*
+    A downed ship with lives left is revived where it went down once a
+    teammate flies over it, that's the only way back. With friendly fire
+    on, and always in versus, bullets hit the other ships too.
+*/
+pub fn update(gs: &mut GameState, delta: f32) {
+    if gs.players.len() < 2 || gs.run_state != RunState::Running {
+        return;
+    }
+    let pvp = gs.mode == Mode::Versus;
+    let time = gs.time;
+
+    let r = REVIVE_RADIUS * gs.scl;
+    for i in 0..gs.players.len() {
+        let marker = gs.players[i].ship.pos;
+        let rescued = gs.players[i].down
+            && gs.players[i].lives > 0
+            && gs
+                .players
+                .iter()
+                .enumerate()
+                .any(|(j, p)| j != i && !p.down && p.ship.pos.distance(marker) < r);
+        if rescued {
+            let player = &mut gs.players[i];
+            player.down = false;
+            player.ship.hull = 1.0;
+            player.ship.vel = Vec2::ZERO;
+        }
+    }
+
+    if !gs.friendly_fire && !pvp {
+        return;
+    }
+    let mut hit = Vec::new();
+    for bullet in gs.bullets.iter_mut().filter(|b| !b.collision) {
+        let a = bullet.pos - bullet.vel * delta;
+        for (i, p) in gs.players.iter().enumerate() {
+            if i == bullet.owner.player()
+                || p.down
+                || versus::is_invulnerable(p, time)
+                || hit.iter().any(|(h, _)| *h == i)
+            {
+                continue;
+            }
+            let ship = &p.ship.points(gs.scl)[..3];
+            if contains(ship, bullet.pos)
+                || (0..3).any(|k| intersects(a, bullet.pos, ship[k], ship[(k + 1) % 3]))
+            {
+                bullet.collision = true;
+                hit.push((i, bullet.owner.player()));
+                break;
+            }
+        }
+    }
+    for (i, owner) in hit {
+        if pvp {
+            gs.players[owner].kills += 1;
+        }
+        kill_player(gs, i);
+    }
+}
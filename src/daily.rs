@@ -0,0 +1,239 @@
+use super::{
+    get_new_game_state, replay::Replay, scores::Entry, stage, Action, GameState, Mode, Modifiers,
+    Rng, Weapon, DAILY_ASTEROIDS, DAILY_SPEED, UNITS,
+};
+use glam::Vec2;
+use nanoserde::{DeJson, SerJson};
+use std::{
+    cmp::Reverse,
+    fs, io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/*
+    One day's challenge. Everything about it comes from the day alone, so
+    everyone who plays it that day flies the same field with the same
+    rules. Days start at midnight UTC wherever the game is played.
+*/
+#[derive(Clone, PartialEq, Debug)]
+pub struct Challenge {
+    pub date: String, // year-month-day
+    pub seed: u64,
+    pub modifiers: Modifiers,
+}
+
+impl Challenge {
+    // `day` counts the days since 1970-01-01
+    pub fn for_day(day: u64) -> Challenge {
+        let mut rng = Rng::new(day);
+        let seed = rng.next_u64();
+        let weapon = Weapon::ALL[(rng.next_u64() % Weapon::ALL.len() as u64) as usize];
+        // rounded so they read well on the title screen
+        let asteroids = (rng.gen_range(DAILY_ASTEROIDS.0, DAILY_ASTEROIDS.1) * 4.0).round() / 4.0;
+        let speed = (rng.gen_range(DAILY_SPEED.0, DAILY_SPEED.1) * 10.0).round() / 10.0;
+        Challenge {
+            date: date(day),
+            seed,
+            modifiers: Modifiers {
+                weapon,
+                asteroids,
+                speed,
+            },
+        }
+    }
+
+    pub fn today() -> Challenge {
+        Challenge::for_day(today())
+    }
+
+    // a solo game of the challenge at its first tick
+    pub fn game(&self, screen: Vec2) -> GameState {
+        let mut gs = get_new_game_state(1, Mode::Campaign);
+        gs.seed = self.seed;
+        gs.modifiers = self.modifiers;
+        gs.daily = true;
+        gs.screen = screen;
+        gs.scl = screen.y / UNITS;
+        stage::start(&mut gs, 0);
+        gs
+    }
+
+    pub fn describe(&self) -> String {
+        let m = &self.modifiers;
+        format!(
+            "{}, {:.0}% asteroids, {:.1}x speed",
+            m.weapon.name(),
+            m.asteroids * 100.0,
+            m.speed
+        )
+    }
+}
+
+// the days since 1970-01-01 up to now
+pub fn today() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH);
+    now.map_or(0, |d| d.as_secs() / SECONDS_PER_DAY)
+}
+
+// year-month-day of the days since 1970-01-01 in the Gregorian calendar
+fn date(day: u64) -> String {
+    // shifted to start in March so the leap day comes last, 146097 days every 400 years
+    let z = day + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + (m <= 2) as u64;
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+// one day's attempt, saved as soon as it starts so it can't be tried again
+#[derive(SerJson, DeJson, Clone, PartialEq, Debug)]
+pub struct Attempt {
+    pub date: String,
+    pub score: i32,
+    pub stage: usize, // the one it ended on
+    pub ticks: u32,
+    pub finished: bool, // false when it was left before the end
+    pub replay: Replay, // empty when it can't be played again
+}
+
+impl Attempt {
+    // as a high score, to send to an online leaderboard
+    pub fn entry(&self) -> Entry {
+        Entry {
+            name: String::new(),
+            score: self.score,
+            stage: self.stage,
+            ticks: self.ticks,
+            splits: Vec::new(),
+            replay: self.replay.clone(),
+        }
+    }
+}
+
+// every daily challenge played on this machine, in the order they were
+#[derive(SerJson, DeJson, Default, Debug)]
+pub struct Board {
+    pub attempts: Vec<Attempt>,
+}
+
+impl Board {
+    // an empty board when there's no file yet or it can't be read
+    pub fn load(path: &str) -> Board {
+        let Ok(json) = fs::read_to_string(path) else {
+            return Board::default();
+        };
+        DeJson::deserialize_json(&json).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            Board::default()
+        })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.serialize_json())
+    }
+
+    pub fn played(&self, date: &str) -> Option<&Attempt> {
+        self.attempts.iter().find(|a| a.date == date)
+    }
+
+    // highest score first, the earlier day first on a tie
+    pub fn best(&self) -> Vec<&Attempt> {
+        let mut best: Vec<&Attempt> = self.attempts.iter().collect();
+        best.sort_by_key(|a| Reverse(a.score));
+        best
+    }
+}
+
+/*
+    Today's challenge and the board it goes on. start() hands out the day's
+    one game, after() follows it every tick until it ends or is left for
+    another mode. The run is recorded like a high score run is.
+*/
+pub struct Daily {
+    path: String,
+    pub board: Board,
+    pub challenge: Challenge,
+    live: bool,
+    replay: Option<Replay>,
+}
+
+impl Daily {
+    pub fn new(path: &str) -> Daily {
+        Daily {
+            path: path.to_string(),
+            board: Board::load(path),
+            challenge: Challenge::today(),
+            live: false,
+            replay: None,
+        }
+    }
+
+    // today's game, None when today's attempt was used already
+    pub fn start(&mut self, screen: Vec2) -> Option<GameState> {
+        // the day may have turned since the last one
+        self.challenge = Challenge::today();
+        if self.board.played(&self.challenge.date).is_some() {
+            return None;
+        }
+        let gs = self.challenge.game(screen);
+        self.board.attempts.push(Attempt {
+            date: self.challenge.date.clone(),
+            score: 0,
+            stage: 0,
+            ticks: 0,
+            finished: false,
+            replay: Replay::default(),
+        });
+        self.live = true;
+        self.replay = Some(Replay::new(&gs));
+        self.save();
+        Some(gs)
+    }
+
+    // the attempt once the run has ended
+    pub fn after(&mut self, gs: &GameState, action: Action) -> Option<Attempt> {
+        if !self.live {
+            return None;
+        }
+        // switched to another mode, what was scored so far stands
+        if !gs.daily {
+            self.live = false;
+            self.replay = None;
+            self.save();
+            return None;
+        }
+        if let Some(replay) = &mut self.replay {
+            replay.push(action);
+        }
+        // a resized window can't be replayed
+        if self.replay.as_ref().is_some_and(|r| !r.matches(gs)) {
+            self.replay = None;
+        }
+        let attempt = self.board.attempts.last_mut().unwrap();
+        attempt.score = gs.players[0].score;
+        attempt.stage = gs.stage;
+        attempt.ticks = gs.tick;
+        if stage::is_run_over(gs) {
+            attempt.finished = true;
+            attempt.replay = self.replay.take().unwrap_or_default();
+            let attempt = attempt.clone();
+            self.live = false;
+            self.save();
+            return Some(attempt);
+        }
+        None
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.board.save(&self.path) {
+            eprintln!("could not save {}: {}", self.path, e);
+        }
+    }
+}
@@ -1,14 +1,23 @@
 use super::{
-    gui, GameState, RunState, Spaceship, ASTEROID_MAX_SIZE, BULLET_WIDTH, DARK,
-    EXPLOSION_LIVE_TIME, FONT_SIZE, GAME_TIME, LIGHT, PLAYER_HEIGHT, POINT_FONT_SIZE,
+    gravity, gui, versus, zone, Asteroid, Boss, GameState, GravityWell, Material, Owner, Player,
+    Rng, RunState, Spaceship, WellKind, Wingman, ZoneKind, ASTEROID_MAX_SIZE, BULLET_WIDTH, DARK,
+    DRONE_RADIUS, EXPLOSION_LIVE_TIME, FONT_SIZE, GAME_TIME, GHOST_ALPHA, LIGHT, PLAYER_HEIGHT,
+    POINT_FONT_SIZE, REVIVE_RADIUS, WELL_FIELD_LINES, WELL_SOFTENING, WINGMAN_RADIUS,
 };
 use macroquad::prelude::{
-    clear_background, draw_circle, draw_line, draw_rectangle_lines, draw_text, draw_triangle,
-    get_fps, get_time, measure_text, rand, screen_height, screen_width, Color, BLACK, GRAY, GREEN,
-    LIGHTGRAY, RED,
+    clear_background, draw_circle, draw_circle_lines, draw_line, draw_rectangle_lines, draw_text,
+    draw_triangle, draw_triangle_lines, get_fps, get_frame_time, get_time, measure_text, rand,
+    screen_height, screen_width, vec2, Color, Vec2, BLACK, GRAY, GREEN, LIGHTGRAY, RED,
 };
+use std::f32::consts::PI;
 
-pub fn draw_spaceship(ship: &Spaceship, scl: f32, debug: bool) {
+pub fn draw_spaceship(
+    ship: &Spaceship,
+    color: Color,
+    scl: f32,
+    debug: bool,
+    wells: &[GravityWell],
+) {
     let Spaceship {
         angle,
         pos,
@@ -19,9 +28,9 @@ pub fn draw_spaceship(ship: &Spaceship, scl: f32, debug: bool) {
 
     let p = ship.points(scl);
 
-    draw_triangle(p[0], p[1], p[2], LIGHT);
-    draw_line(p[1].x, p[1].y, p[3].x, p[3].y, 2.0, LIGHT);
-    draw_line(p[2].x, p[2].y, p[4].x, p[4].y, 2.0, LIGHT);
+    draw_triangle(p[0], p[1], p[2], color);
+    draw_line(p[1].x, p[1].y, p[3].x, p[3].y, 2.0, color);
+    draw_line(p[2].x, p[2].y, p[4].x, p[4].y, 2.0, color);
 
     let (left_strafe, right_strafe) = strafing;
     let rot = angle.to_radians();
@@ -50,6 +59,17 @@ pub fn draw_spaceship(ship: &Spaceship, scl: f32, debug: bool) {
         );
     }
 
+    // predicted free fall path, dotted
+    let frame_time = get_frame_time();
+    if !wells.is_empty() && frame_time > 0.0 {
+        let path = gravity::predict(wells, *pos, *vel / frame_time, scl);
+        for (i, seg) in path.windows(2).enumerate() {
+            if i % 2 == 0 {
+                draw_line(seg[0].x, seg[0].y, seg[1].x, seg[1].y, 1.0, GRAY);
+            }
+        }
+    }
+
     if debug {
         draw_line(
             pos.x,
@@ -71,6 +91,272 @@ pub fn draw_spaceship(ship: &Spaceship, scl: f32, debug: bool) {
     }
 }
 
+// the ghost of the best run, see ghost.rs, drawn see-through over the game
+pub fn draw_ghost(ship: &Spaceship, scl: f32) {
+    let color = Color {
+        a: GHOST_ALPHA,
+        ..LIGHT
+    };
+    draw_spaceship(ship, color, scl, false, &[]);
+}
+
+fn draw_asteroid(asteroid: &Asteroid) {
+    let p = asteroid.points();
+    let center = asteroid.pos;
+    for i in 0..p.len() {
+        let p1 = p[i];
+        let p2 = p[(i + 1) % p.len()];
+        // bug: not drawing over star background..
+        draw_triangle(p1, p2, center, DARK);
+
+        match asteroid.material {
+            Material::Rocky => draw_line(p1.x, p1.y, p2.x, p2.y, 2.0, LIGHT),
+            Material::Metallic => {
+                // heavy plating with an inner rim
+                draw_line(p1.x, p1.y, p2.x, p2.y, 3.0, LIGHT);
+                let i1 = center + (p1 - center) * 0.75;
+                let i2 = center + (p2 - center) * 0.75;
+                draw_line(i1.x, i1.y, i2.x, i2.y, 1.0, LIGHT);
+            }
+            Material::Explosive => {
+                // dashed, like a fuse
+                for d in (0..4).step_by(2) {
+                    let d1 = p1 + (p2 - p1) * (d as f32 / 4.0);
+                    let d2 = p1 + (p2 - p1) * ((d + 1) as f32 / 4.0);
+                    draw_line(d1.x, d1.y, d2.x, d2.y, 2.0, LIGHT);
+                }
+            }
+            Material::Ice => {
+                // thin with frost spikes at each vertex
+                draw_line(p1.x, p1.y, p2.x, p2.y, 1.0, LIGHT);
+                let spike = p1 + (p1 - center).normalize_or_zero() * asteroid.w * 0.08;
+                draw_line(p1.x, p1.y, spike.x, spike.y, 1.0, LIGHT);
+            }
+            Material::Crystal => {
+                // facets running to the center
+                draw_line(p1.x, p1.y, p2.x, p2.y, 2.0, LIGHT);
+                if i % 2 == 0 {
+                    draw_line(p1.x, p1.y, center.x, center.y, 1.0, LIGHT);
+                }
+            }
+        }
+    }
+
+    for crater in asteroid.craters().iter() {
+        for i in 0..crater.len() {
+            let c1 = crater[i];
+            let c2 = crater[(i + 1) % crater.len()];
+            draw_line(c1.x, c1.y, c2.x, c2.y, 1.0, LIGHT);
+        }
+    }
+}
+
+fn draw_outline(p: &[Vec2], thickness: f32) {
+    for i in 0..p.len() {
+        let p1 = p[i];
+        let p2 = p[(i + 1) % p.len()];
+        draw_line(p1.x, p1.y, p2.x, p2.y, thickness, LIGHT);
+    }
+}
+
+fn draw_boss(boss: &Boss, time: f64) {
+    let core = boss.core();
+    for i in 0..core.len() {
+        draw_triangle(core[i], core[(i + 1) % core.len()], boss.pos, DARK);
+    }
+    if boss.core_exposed() {
+        // pulsing weak point, lit up briefly when hit
+        let pulse = (time * 6.0).sin() as f32 * 0.1 + 0.5;
+        let hit = time - boss.hit_at < 0.1;
+        let inner: Vec<Vec2> = core
+            .iter()
+            .map(|p| boss.pos + (*p - boss.pos) * pulse)
+            .collect();
+        if hit {
+            for i in 0..inner.len() {
+                draw_triangle(inner[i], inner[(i + 1) % inner.len()], boss.pos, LIGHT);
+            }
+        }
+        draw_outline(&inner, 1.0);
+        draw_outline(&core, 2.0);
+    } else {
+        // shield plating
+        draw_outline(&core, 3.0);
+        let inner: Vec<Vec2> = core
+            .iter()
+            .map(|p| boss.pos + (*p - boss.pos) * 0.8)
+            .collect();
+        draw_outline(&inner, 1.0);
+    }
+
+    for part in boss.parts.iter() {
+        let p = boss.to_world(&part.points);
+        let c = p.iter().fold(Vec2::ZERO, |acc, v| acc + *v) / p.len() as f32;
+        for i in 0..p.len() {
+            draw_triangle(p[i], p[(i + 1) % p.len()], c, DARK);
+        }
+        draw_outline(&p, if part.launcher { 3.0 } else { 2.0 });
+    }
+}
+
+// outline of a downed ship inside a dashed ring to fly over
+fn draw_marker(player: &Player, scl: f32) {
+    let p = player.ship.points(scl);
+    draw_triangle_lines(p[0], p[1], p[2], 1.0, player.color);
+    let r = REVIVE_RADIUS * scl;
+    let spin = get_time() as f32;
+    for i in 0..12 {
+        let a = spin + i as f32 * PI / 6.0;
+        let b = a + PI / 12.0;
+        draw_line(
+            player.ship.pos.x + r * a.cos(),
+            player.ship.pos.y + r * a.sin(),
+            player.ship.pos.x + r * b.cos(),
+            player.ship.pos.y + r * b.sin(),
+            1.0,
+            player.color,
+        );
+    }
+}
+
+// a diamond pointing where it aims
+fn draw_wingman(w: &Wingman, scl: f32) {
+    let r = WINGMAN_RADIUS * scl;
+    let side = vec2(-w.aim.y, w.aim.x) * r * 0.6;
+    let points = [
+        w.pos + w.aim * r * 1.4,
+        w.pos + side,
+        w.pos - w.aim * r * 0.6,
+        w.pos - side,
+    ];
+    draw_outline(&points, 1.5);
+}
+
+// small darts pointing where they fly
+fn draw_drones(gs: &GameState) {
+    let r = DRONE_RADIUS * gs.scl;
+    for d in gs.drones.iter() {
+        let dir = d.vel.normalize_or_zero();
+        let side = vec2(-dir.y, dir.x) * r * 0.7;
+        draw_triangle_lines(
+            d.pos + dir * r * 1.5,
+            d.pos - dir * r + side,
+            d.pos - dir * r - side,
+            1.0,
+            LIGHT,
+        );
+    }
+}
+
+fn draw_comets(gs: &GameState) {
+    for c in gs.comets.iter() {
+        draw_circle(c.pos.x, c.pos.y, c.r * 1.6, faded(0.15));
+        draw_circle(c.pos.x, c.pos.y, c.r, LIGHT);
+    }
+}
+
+fn draw_planet(well: &GravityWell) {
+    let GravityWell { pos, radius, .. } = well;
+    draw_circle(pos.x, pos.y, *radius, DARK);
+    // latitude bands, squashed to look like a sphere
+    for i in 1..4 {
+        let y = pos.y - radius + radius * 2.0 * i as f32 / 4.0;
+        let half = (radius * radius - (y - pos.y).powi(2)).sqrt();
+        draw_line(pos.x - half, y, pos.x + half, y, 1.0, GRAY);
+    }
+    draw_circle_lines(pos.x, pos.y, *radius, 2.0, LIGHT);
+    draw_circle_lines(pos.x, pos.y, radius * 1.15, 1.0, GRAY);
+}
+
+// radial field lines with dashes flowing inwards, and the horizon of black holes
+fn draw_wells(gs: &GameState) {
+    let time = get_time() as f32;
+    for well in gs.wells.iter() {
+        if well.kind == WellKind::Planet {
+            draw_planet(well);
+            continue;
+        }
+
+        let r0 = f32::max(well.radius, WELL_SOFTENING * gs.scl);
+        let r1 = well.strength.sqrt() * gs.scl; // where the pull drops below one unit/s²
+        for i in 0..WELL_FIELD_LINES {
+            let a = i as f32 * 2.0 * PI / WELL_FIELD_LINES as f32;
+            let dir = vec2(a.cos(), a.sin());
+            let from = well.pos + dir * r0;
+            let to = well.pos + dir * r1;
+            draw_line(from.x, from.y, to.x, to.y, 1.0, GRAY);
+
+            for k in 0..3 {
+                let t = (time * 0.5 + k as f32 / 3.0) % 1.0;
+                let r = r1 - (r1 - r0) * t * t;
+                let d1 = well.pos + dir * r;
+                let d2 = well.pos + dir * f32::max(r - 4.0, r0);
+                draw_line(d1.x, d1.y, d2.x, d2.y, 2.0, LIGHT);
+            }
+        }
+
+        if well.kind == WellKind::BlackHole {
+            draw_circle(well.pos.x, well.pos.y, well.radius, BLACK);
+            draw_circle_lines(well.pos.x, well.pos.y, well.radius, 2.0, LIGHT);
+        } else {
+            draw_circle_lines(well.pos.x, well.pos.y, r0, 1.0, GRAY);
+        }
+    }
+}
+
+fn faded(alpha: f32) -> Color {
+    Color::new(LIGHT.r, LIGHT.g, LIGHT.b, alpha)
+}
+
+/*
+    Nebulae are stacked translucent discs that fade out towards the edge,
+    ion storms flicker with lightning and debris fields are specks drifting
+    along with the zone's push.
+*/
+fn draw_zones(gs: &GameState) {
+    let time = get_time() as f32;
+    for (i, z) in gs.zones.iter().enumerate() {
+        match z.kind {
+            ZoneKind::Nebula => {
+                for k in 1..=5 {
+                    draw_circle(z.pos.x, z.pos.y, z.radius * k as f32 / 5.0, faded(0.03));
+                }
+            }
+            ZoneKind::IonStorm => {
+                draw_circle(z.pos.x, z.pos.y, z.radius, faded(0.03));
+                draw_circle_lines(z.pos.x, z.pos.y, z.radius, 1.0, GRAY);
+                if rand::gen_range(0.0, 1.0) < 0.1 {
+                    let a = rand::gen_range(0.0, 2.0 * PI);
+                    let mut from = z.pos + vec2(a.cos(), a.sin()) * z.radius * 0.8;
+                    for _ in 0..6 {
+                        let to = from
+                            + (z.pos - from).normalize_or_zero() * z.radius * 0.2
+                            + vec2(rand::gen_range(-1.0, 1.0), rand::gen_range(-1.0, 1.0))
+                                * z.radius
+                                * 0.1;
+                        draw_line(from.x, from.y, to.x, to.y, 1.0, LIGHT);
+                        from = to;
+                    }
+                }
+            }
+            ZoneKind::DebrisField => {
+                draw_circle_lines(z.pos.x, z.pos.y, z.radius, 1.0, GRAY);
+                let mut rng = Rng::new(i as u64 + 1);
+                let shift = z.drift.normalize_or_zero() * time * gs.scl;
+                let span = z.radius * 2.0;
+                for _ in 0..(z.radius / 4.0) as usize {
+                    let p = vec2(rng.gen_range(0.0, span), rng.gen_range(0.0, span)) + shift;
+                    let p = z.pos - vec2(z.radius, z.radius)
+                        + vec2(p.x.rem_euclid(span), p.y.rem_euclid(span));
+                    if z.contains(p) {
+                        draw_circle(p.x, p.y, 1.0, GRAY);
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn draw_background(gs: &GameState) {
     for star in gs.background.iter() {
         draw_circle(star.pos.x, star.pos.y, star.size, LIGHTGRAY);
@@ -86,14 +372,14 @@ fn draw_debug(gs: &GameState) {
         LIGHT,
     );
     draw_text(
-        &format!("Vel: {}", gs.player.vel.to_string()),
+        &format!("Vel: {}", gs.players[0].ship.vel.to_string()),
         10.0,
         60.0,
         FONT_SIZE - 5.0,
         LIGHT,
     );
     draw_text(
-        &format!("Angle: {}", gs.player.angle.to_string()),
+        &format!("Angle: {}", gs.players[0].ship.angle.to_string()),
         10.0,
         70.0,
         FONT_SIZE - 5.0,
@@ -107,7 +393,7 @@ fn draw_debug(gs: &GameState) {
         LIGHT,
     );
     draw_text(
-        &format!("Player lives: {}", gs.lives),
+        &format!("Player lives: {}", gs.players[0].lives),
         10.0,
         90.0,
         FONT_SIZE - 5.0,
@@ -135,14 +421,31 @@ pub fn draw(gs: &GameState) {
 
     match gs.run_state {
         RunState::Running | RunState::Death | RunState::StageComplete => {
-            draw_spaceship(&gs.player, gs.scl, gs.debug);
+            draw_zones(gs);
+            draw_wells(gs);
+            let time = gs.time;
+            for player in gs.players.iter() {
+                // blink while the spawn shield is up
+                let blink = versus::is_invulnerable(player, time) && (time * 10.0) as i64 % 2 == 0;
+                if player.down {
+                    draw_marker(player, gs.scl);
+                } else if !blink {
+                    draw_spaceship(&player.ship, player.color, gs.scl, gs.debug, &gs.wells);
+                }
+            }
+            if let Some(w) = &gs.wingman {
+                draw_wingman(w, gs.scl);
+            }
 
             for bullet in gs.bullets.iter() {
                 draw_circle(
                     bullet.pos.x,
                     bullet.pos.y,
                     BULLET_WIDTH / 2.0 * gs.scl,
-                    LIGHT,
+                    match bullet.owner {
+                        Owner::Player(i) => gs.players.get(i).map_or(LIGHT, |p| p.color),
+                        Owner::Wingman => LIGHT,
+                    },
                 )
             }
 
@@ -165,15 +468,27 @@ pub fn draw(gs: &GameState) {
                 );
             }
 
-            for asteroid in gs.asteroids.iter() {
-                let p = asteroid.points();
-                for i in 0..=(p.len() - 1) {
-                    let p1 = p[i];
-                    let p2 = p[(i + 1) % p.len()];
-                    // bug: not drawing over star background..
-                    draw_triangle(p1, p2, asteroid.pos, DARK);
-                    draw_line(p1.x, p1.y, p2.x, p2.y, 2.0, LIGHT);
-                }
+            for asteroid in gs.asteroids.iter().filter(|a| zone::visible(gs, a.pos)) {
+                draw_asteroid(asteroid);
+            }
+
+            if let Some(boss) = &gs.boss {
+                draw_boss(boss, gs.time);
+            }
+            draw_comets(gs);
+            draw_drones(gs);
+
+            for d in gs.debris.iter() {
+                let rot = d.angle.to_radians();
+                let half = vec2(rot.cos(), rot.sin()) * d.len / 2.0;
+                draw_line(
+                    d.pos.x - half.x,
+                    d.pos.y - half.y,
+                    d.pos.x + half.x,
+                    d.pos.y + half.y,
+                    2.0,
+                    LIGHT,
+                );
             }
 
             for e in gs.explosions.iter() {
@@ -183,8 +498,7 @@ pub fn draw(gs: &GameState) {
                     e.pos.y,
                     e.width,
                     e.width,
-                    thickness
-                        - thickness * ((get_time() - e.created_at) / EXPLOSION_LIVE_TIME) as f32,
+                    thickness - thickness * ((gs.time - e.created_at) / EXPLOSION_LIVE_TIME) as f32,
                     LIGHT,
                 );
             }
@@ -265,6 +579,7 @@ pub fn draw(gs: &GameState) {
             //     LIGHT,
             // )
         }
-        _ => {}
+        RunState::MatchSummary => gui::draw_summary(gs),
+        RunState::TurnChange => gui::draw_turn_change(gs),
     }
 }
@@ -0,0 +1,351 @@
+use super::{
+    gym::{self, Env},
+    Action, Rng, EVOLVE_ELITE, EVOLVE_GENERATIONS, EVOLVE_HIDDEN, EVOLVE_MAX_TICKS,
+    EVOLVE_MUTATION_RATE, EVOLVE_MUTATION_STRENGTH, EVOLVE_POPULATION, EVOLVE_RAYS, EVOLVE_RUNS,
+    GYM_NEAREST, TICK,
+};
+use nanoserde::{DeJson, SerJson};
+use std::{fs, path::Path, thread};
+
+// left, right, thrust, reverse, strafe left, strafe right and fire
+const OUTPUTS: usize = 7;
+const TOURNAMENT: usize = 3;
+
+/*
+    A bot's brain, the weights of a small network with one hidden layer.
+    It sees the learning environment's observation, see gym.rs, and holds
+    each key whose output comes out positive. The observation it was bred
+    on goes with it so it can be played back with the same one.
+*/
+#[derive(SerJson, DeJson, Clone, PartialEq, Debug)]
+pub struct Genome {
+    pub nearest: usize,
+    pub rays: usize,
+    pub hidden: usize,
+    pub weights: Vec<f32>,
+    pub fitness: f32,
+    pub generation: usize,
+}
+
+impl Genome {
+    pub fn random(nearest: usize, rays: usize, hidden: usize, rng: &mut Rng) -> Genome {
+        let mut genome = Genome {
+            nearest,
+            rays,
+            hidden,
+            weights: Vec::new(),
+            fitness: 0.0,
+            generation: 0,
+        };
+        genome.weights = (0..genome.weight_count())
+            .map(|_| rng.gen_range(-1.0, 1.0))
+            .collect();
+        genome
+    }
+
+    pub fn load(path: &str) -> Result<Genome, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let genome: Genome =
+            DeJson::deserialize_json(&json).map_err(|e| format!("{}: {}", path, e))?;
+        if genome.weights.len() != genome.weight_count() {
+            return Err(format!("{}: wrong number of weights", path));
+        }
+        Ok(genome)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        fs::write(path, self.serialize_json())
+    }
+
+    // the environment settings giving the observation this genome expects
+    pub fn env_config(&self, max_ticks: u32) -> gym::Config {
+        gym::Config {
+            nearest: self.nearest,
+            rays: self.rays,
+            max_steps: max_ticks,
+            ..gym::Config::default()
+        }
+    }
+
+    fn weight_count(&self) -> usize {
+        let inputs = self.env_config(0).observation_len();
+        self.hidden * (inputs + 1) + OUTPUTS * (self.hidden + 1)
+    }
+
+    pub fn act(&self, obs: &[f32]) -> Action {
+        // each neuron's weights are followed by its bias
+        let mut w = self.weights.iter();
+        let mut neuron = |input: &[f32]| {
+            let sum: f32 = input.iter().zip(w.by_ref()).map(|(x, w)| x * w).sum();
+            sum + w.next().unwrap_or(&0.0)
+        };
+        let hidden: Vec<f32> = (0..self.hidden).map(|_| neuron(obs).tanh()).collect();
+        let out: Vec<bool> = (0..OUTPUTS).map(|_| neuron(&hidden) > 0.0).collect();
+        Action {
+            left: out[0],
+            right: out[1],
+            thrust: out[2],
+            reverse: out[3],
+            strafe_left: out[4],
+            strafe_right: out[5],
+            fire: out[6],
+            confirm: false,
+        }
+    }
+
+    // half the weights from each parent
+    fn cross(&self, other: &Genome, rng: &mut Rng) -> Genome {
+        let mut child = self.clone();
+        for (w, o) in child.weights.iter_mut().zip(other.weights.iter()) {
+            if rng.gen_range(0.0, 1.0) < 0.5 {
+                *w = *o;
+            }
+        }
+        child
+    }
+
+    fn mutate(&mut self, rate: f32, strength: f32, rng: &mut Rng) {
+        for w in self.weights.iter_mut() {
+            if rng.gen_range(0.0, 1.0) < rate {
+                *w += rng.gen_range(-strength, strength);
+            }
+        }
+    }
+}
+
+pub struct Config {
+    pub population: usize,
+    pub generations: usize,
+    pub elite: usize,
+    pub mutation_rate: f32,
+    pub mutation_strength: f32,
+    pub hidden: usize,
+    pub rays: usize,
+    pub runs: usize,    // each genome plays the seeds `seed` to `seed + runs - 1`
+    pub max_ticks: u32, // per run
+    pub seed: u64,
+    pub threads: usize,
+    // fitness is the sum of these times the average of each over the runs
+    pub score: f32,          // per point
+    pub survival: f32,       // per second alive
+    pub accuracy: f32,       // per bullet that hit over every bullet fired
+    pub out: Option<String>, // directory to checkpoint the best genomes in
+}
+
+const USAGE: &str = "usage: evolve [--population n] [--generations n] [--elite n] \
+    [--mutation-rate x] [--mutation-strength x] [--hidden n] [--rays n] [--runs n] \
+    [--ticks n] [--seed n] [--threads n] [--score x] [--survival x] [--accuracy x] [--out dir]";
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            population: EVOLVE_POPULATION,
+            generations: EVOLVE_GENERATIONS,
+            elite: EVOLVE_ELITE,
+            mutation_rate: EVOLVE_MUTATION_RATE,
+            mutation_strength: EVOLVE_MUTATION_STRENGTH,
+            hidden: EVOLVE_HIDDEN,
+            rays: EVOLVE_RAYS,
+            runs: EVOLVE_RUNS,
+            max_ticks: EVOLVE_MAX_TICKS,
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            score: 1.0,
+            survival: 0.0,
+            accuracy: 0.0,
+            out: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(args: &[String]) -> Result<Config, String> {
+        let mut config = Config::default();
+
+        let mut i = 0;
+        while i < args.len() {
+            let value = args.get(i + 1).ok_or_else(|| USAGE.to_string())?;
+            let number = || -> Result<usize, String> {
+                value
+                    .parse()
+                    .map_err(|_| format!("{} needs a number", args[i]))
+            };
+            let float = || -> Result<f32, String> {
+                value
+                    .parse()
+                    .map_err(|_| format!("{} needs a number", args[i]))
+            };
+            match args[i].as_str() {
+                "--population" => config.population = number()?,
+                "--generations" => config.generations = number()?,
+                "--elite" => config.elite = number()?,
+                "--mutation-rate" => config.mutation_rate = float()?,
+                "--mutation-strength" => config.mutation_strength = float()?,
+                "--hidden" => config.hidden = number()?,
+                "--rays" => config.rays = number()?,
+                "--runs" => config.runs = number()?,
+                "--ticks" => config.max_ticks = number()? as u32,
+                "--seed" => config.seed = number()? as u64,
+                "--threads" => config.threads = number()?,
+                "--score" => config.score = float()?,
+                "--survival" => config.survival = float()?,
+                "--accuracy" => config.accuracy = float()?,
+                "--out" => config.out = Some(value.clone()),
+                _ => return Err(USAGE.to_string()),
+            }
+            i += 2;
+        }
+
+        if config.population < 2 || config.elite >= config.population {
+            return Err("--population needs at least 2 and more than --elite".to_string());
+        }
+        // no limit on the ticks would leave a genome that never dies playing forever
+        if config.runs == 0 || config.threads == 0 || config.hidden == 0 || config.max_ticks == 0 {
+            return Err("--runs, --threads, --hidden and --ticks can't be 0".to_string());
+        }
+        Ok(config)
+    }
+}
+
+// how one generation did
+pub struct Report {
+    pub generation: usize,
+    pub best: f32,
+    pub mean: f32,
+}
+
+/*
+    A population of genomes bred one generation at a time. Every genome
+    plays the same seeded runs so their fitness compares fairly, spread
+    over the threads. Everything random happens on the calling thread so
+    a config gives the same genomes however many threads there are.
+*/
+pub struct Evolution {
+    pub config: Config,
+    pub population: Vec<Genome>,
+    pub generation: usize,
+    pub best: Option<Genome>, // of every generation so far
+    rng: Rng,
+}
+
+impl Evolution {
+    pub fn new(config: Config) -> Evolution {
+        let mut rng = Rng::new(config.seed);
+        let population = (0..config.population)
+            .map(|_| Genome::random(GYM_NEAREST, config.rays, config.hidden, &mut rng))
+            .collect();
+        Evolution {
+            config,
+            population,
+            generation: 0,
+            best: None,
+            rng,
+        }
+    }
+
+    // rates the current generation, saves its best and breeds the next
+    pub fn step(&mut self) -> std::io::Result<Report> {
+        self.evaluate();
+        self.population
+            .sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        let top = self.population[0].clone();
+        let mean =
+            self.population.iter().map(|g| g.fitness).sum::<f32>() / self.population.len() as f32;
+        let improved = self.best.as_ref().is_none_or(|b| top.fitness > b.fitness);
+        if improved {
+            self.best = Some(top.clone());
+        }
+        if let Some(out) = &self.config.out {
+            let dir = Path::new(out);
+            fs::create_dir_all(dir)?;
+            top.save(&dir.join(format!("generation-{:04}.json", self.generation)))?;
+            if improved {
+                top.save(&dir.join("best.json"))?;
+            }
+        }
+
+        let report = Report {
+            generation: self.generation,
+            best: top.fitness,
+            mean,
+        };
+        self.breed();
+        self.generation += 1;
+        Ok(report)
+    }
+
+    fn evaluate(&mut self) {
+        let config = &self.config;
+        let chunk = self.population.len().div_ceil(config.threads);
+        thread::scope(|s| {
+            for genomes in self.population.chunks_mut(chunk) {
+                s.spawn(move || {
+                    for genome in genomes {
+                        genome.fitness = fitness(genome, config);
+                    }
+                });
+            }
+        });
+    }
+
+    // the elite carry over, the rest are children of tournament winners
+    fn breed(&mut self) {
+        let config = &self.config;
+        let mut next: Vec<Genome> = self.population[..config.elite].to_vec();
+        while next.len() < config.population {
+            let a = tournament(&self.population, &mut self.rng);
+            let b = tournament(&self.population, &mut self.rng);
+            let mut child = a.cross(b, &mut self.rng);
+            child.mutate(
+                config.mutation_rate,
+                config.mutation_strength,
+                &mut self.rng,
+            );
+            next.push(child);
+        }
+        for genome in next.iter_mut() {
+            genome.generation = self.generation + 1;
+        }
+        self.population = next;
+    }
+}
+
+fn tournament<'a>(population: &'a [Genome], rng: &mut Rng) -> &'a Genome {
+    (0..TOURNAMENT)
+        .map(|_| &population[rng.next_u64() as usize % population.len()])
+        .max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+        .unwrap()
+}
+
+pub fn fitness(genome: &Genome, config: &Config) -> f32 {
+    let mut env = Env::new(genome.env_config(config.max_ticks));
+    let mut total = 0.0;
+    for run in 0..config.runs {
+        let mut obs = env.reset(config.seed + run as u64);
+        let mut ticks = 0;
+        loop {
+            let (next, _, done) = env.step(genome.act(&obs));
+            obs = next;
+            ticks += 1;
+            if done {
+                break;
+            }
+        }
+        let player = &env.game().players[0];
+        let accuracy = player.hits as f32 / player.shots.max(1) as f32;
+        total += player.score as f32 * config.score
+            + ticks as f32 * TICK * config.survival
+            + accuracy * config.accuracy;
+    }
+    total / config.runs as f32
+}
+
+// the genome to play back when started with --genome, None otherwise
+pub fn genome_from_args() -> Option<Result<Genome, String>> {
+    let args: Vec<String> = std::env::args().collect();
+    let i = args.iter().position(|a| a == "--genome")?;
+    Some(match args.get(i + 1) {
+        Some(path) => Genome::load(path),
+        None => Err("usage: asteroids-rs --genome <genome.json>".to_string()),
+    })
+}
@@ -0,0 +1,477 @@
+use super::{
+    contains, stage::Stage, Asteroid, Debris, Impact, Rng, FRACTURE_MIN_AREA, FRACTURE_SPIN,
+    FRAGMENT_IMPULSE, FRAGMENT_SPREAD,
+};
+use glam::{vec2, Vec2};
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+// shoelace formula, independent of winding
+pub fn area(points: &[Vec2]) -> f32 {
+    let mut sum = 0.0;
+    for i in 0..points.len() {
+        sum += cross(points[i], points[(i + 1) % points.len()]);
+    }
+    (sum / 2.0).abs()
+}
+
+pub fn centroid(points: &[Vec2]) -> Vec2 {
+    let mut sum = Vec2::ZERO;
+    let mut a = 0.0;
+    for i in 0..points.len() {
+        let p1 = points[i];
+        let p2 = points[(i + 1) % points.len()];
+        let c = cross(p1, p2);
+        sum += (p1 + p2) * c;
+        a += c;
+    }
+    if a == 0.0 {
+        return points.iter().fold(Vec2::ZERO, |acc, p| acc + *p) / points.len() as f32;
+    }
+    sum / (3.0 * a)
+}
+
+/*
+    Which side of the line through `origin` along `dir` each point is on,
+    true for the left, with its distance from the line. Points on the line
+    are moved a hair off it, to the side the polygon's inside is on right
+    there: a cut along an edge or through a notch then runs outside the
+    polygon instead of leaving pieces joined by a strip without width.
+*/
+fn sides(points: &[Vec2], origin: Vec2, dir: Vec2) -> Vec<(f32, bool)> {
+    let n = points.len();
+    let reach = points
+        .iter()
+        .fold(0.0, |m: f32, p| m.max((*p - origin).length()));
+    let dist: Vec<f32> = points.iter().map(|p| cross(dir, *p - origin)).collect();
+    let on = |i: usize| dist[i].abs() <= reach * 1e-6;
+    let mut sides: Vec<(f32, bool)> = dist.iter().map(|d| (*d, *d > 0.0)).collect();
+    let Some(off) = (0..n).find(|i| !on(*i)) else {
+        return sides;
+    };
+    let ccw = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| cross(*a, *b))
+        .sum::<f32>()
+        > 0.0;
+
+    // runs of points on the line, walked from a point off it so none wraps
+    let mut k = 1;
+    while k < n {
+        let first = (off + k) % n;
+        if !on(first) {
+            k += 1;
+            continue;
+        }
+        let mut len = 1;
+        while on((first + len) % n) {
+            len += 1;
+        }
+        let last = (first + len - 1) % n;
+        let before = sides[(first + n - 1) % n].1;
+        let after = sides[(last + 1) % n].1;
+        let left = if len > 1 {
+            // the inside is on the left of the outline going ccw
+            ((points[last] - points[first]).dot(dir) > 0.0) == ccw
+        } else if before == after {
+            // a corner touching the line, the inside is between its edges
+            let p = points[first];
+            let turn = cross(p - points[(first + n - 1) % n], points[(first + 1) % n] - p);
+            (turn > 0.0) == ccw && before || (turn > 0.0) != ccw && !before
+        } else {
+            true
+        };
+        for m in 0..len {
+            sides[(first + m) % n] = (0.0, left);
+        }
+        k += len;
+    }
+    sides
+}
+
+/*
+    Splits a simple (possibly concave) polygon along the infinite line
+    through `origin` with direction `dir`. The cut points are sorted along
+    the line and paired up into chords; walking the outline and jumping
+    across a chord whenever one is reached traces out each piece. A concave
+    polygon can therefore fall apart into more than two pieces.
+
+    Cuts next to a point moved off the line, see sides(), land on that
+    point. They are ordered the way they would be if it had really moved,
+    and the repeated points and pieces without area this leaves behind are
+    cleaned up at the end.
+*/
+pub fn split(points: &[Vec2], origin: Vec2, dir: Vec2) -> Vec<Vec<Vec2>> {
+    let dir = dir.normalize_or_zero();
+    let sides = sides(points, origin, dir);
+
+    // outline with the cut points inserted, and the cut points' place along
+    // the line, how it would move with the point, and index into the ring
+    let mut ring: Vec<(Vec2, bool)> = Vec::new();
+    let mut cuts: Vec<(f32, f32, usize)> = Vec::new();
+    for i in 0..points.len() {
+        let j = (i + 1) % points.len();
+        let (a, b) = (points[i], points[j]);
+        let ((sa, left_a), (sb, left_b)) = (sides[i], sides[j]);
+        ring.push((a, false));
+        if left_a != left_b {
+            // exactly on the point, rounding would reorder cuts that tie
+            let (cut, drift) = match (sa == 0.0, sb == 0.0) {
+                (true, _) => (a, (b - a).dot(dir) / sb.abs()),
+                (_, true) => (b, (a - b).dot(dir) / sa.abs()),
+                _ => (a + (b - a) * (sa / (sa - sb)), 0.0),
+            };
+            cuts.push(((cut - origin).dot(dir), drift, ring.len()));
+            ring.push((cut, true));
+        }
+    }
+    if cuts.len() < 2 {
+        return vec![points.to_vec()];
+    }
+
+    cuts.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    let mut chord = vec![0; ring.len()];
+    for pair in cuts.chunks(2) {
+        if let [(_, _, a), (_, _, b)] = pair {
+            chord[*a] = *b;
+            chord[*b] = *a;
+        }
+    }
+
+    let mut visited = vec![false; ring.len()];
+    let mut pieces = Vec::new();
+    for start in 0..ring.len() {
+        if visited[start] || ring[start].1 {
+            continue;
+        }
+        let mut piece: Vec<Vec2> = Vec::new();
+        let mut i = start;
+        while piece.len() <= ring.len() {
+            visited[i] = true;
+            piece.push(ring[i].0);
+            if ring[i].1 {
+                i = chord[i];
+                piece.push(ring[i].0);
+            }
+            i = (i + 1) % ring.len();
+            if i == start {
+                break;
+            }
+        }
+        piece.dedup();
+        while piece.len() > 1 && piece.first() == piece.last() {
+            piece.pop();
+        }
+        pieces.push(piece);
+    }
+
+    let whole = area(points);
+    pieces.retain(|p| p.len() >= 3 && area(p) > whole * 1e-6);
+    if pieces.is_empty() {
+        return vec![points.to_vec()];
+    }
+    pieces
+}
+
+fn rotate(v: Vec2, deg: f32) -> Vec2 {
+    let r = deg.to_radians();
+    vec2(v.x * r.cos() - v.y * r.sin(), v.x * r.sin() + v.y * r.cos())
+}
+
+// breaks a polygon up into its edges, flying away from `from`
+pub fn shatter(
+    points: &[Vec2],
+    from: Vec2,
+    vel: Vec2,
+    time: f64,
+    rng: &mut Rng,
+    debris: &mut Vec<Debris>,
+) {
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let pos = (a + b) / 2.0;
+        let edge = b - a;
+        debris.push(Debris {
+            pos,
+            vel: vel + (pos - from).normalize_or_zero() * FRAGMENT_SPREAD,
+            angle: edge.y.atan2(edge.x).to_degrees(),
+            spin: rng.gen_range(-FRACTURE_SPIN, FRACTURE_SPIN),
+            len: edge.length(),
+            created_at: time,
+        });
+    }
+}
+
+/*
+    Fractures a destroyed asteroid along a line through the bullet impact.
+    The piece count comes from the stage's fragment rules; if more pieces
+    are needed the largest one is cut again through its centroid. Pieces
+    smaller than FRACTURE_MIN_AREA are shattered into debris instead of
+    becoming asteroids.
+
+    Every fragment inherits the parent velocity and the bullet impulse, and
+    is pushed away from the impact point. The push is re-centered so that the
+    total momentum of the fragments equals parent momentum plus impulse.
+*/
+pub fn fracture(
+    parent: &Asteroid,
+    stage: &Stage,
+    scl: f32,
+    time: f64,
+    rng: &mut Rng,
+) -> (Vec<Asteroid>, Vec<Debris>) {
+    let mut debris = Vec::new();
+    let impact = parent.impact.unwrap_or(Impact {
+        pos: parent.pos,
+        dir: vec2(0.0, -1.0),
+        owner: 0,
+    });
+    let dir = if impact.dir == Vec2::ZERO {
+        vec2(0.0, -1.0)
+    } else {
+        impact.dir
+    };
+    let sizes = stage.fragment_sizes(parent.material, parent.size);
+    if sizes.is_empty() {
+        shatter(
+            &parent.points(),
+            impact.pos,
+            parent.vel,
+            time,
+            rng,
+            &mut debris,
+        );
+        return (Vec::new(), debris);
+    }
+
+    let mut pieces = split(&parent.points(), impact.pos, dir);
+    let mut cuts = 0;
+    while pieces.len() < sizes.len() && cuts < sizes.len() {
+        let (i, largest) = pieces
+            .iter()
+            .enumerate()
+            .max_by(|a, b| area(a.1).total_cmp(&area(b.1)))
+            .unwrap();
+        let cut_dir = rotate(dir, rng.gen_range(45.0, 135.0));
+        let mut halves = split(largest, centroid(largest), cut_dir);
+        pieces.remove(i);
+        pieces.append(&mut halves);
+        cuts += 1;
+    }
+
+    // slivers turn into debris, the rest become asteroids
+    let min_area = FRACTURE_MIN_AREA * scl * scl;
+    let (mut pieces, slivers): (Vec<_>, Vec<_>) = pieces
+        .into_iter()
+        .partition(|p| p.len() >= 3 && area(p) >= min_area);
+    for sliver in slivers.iter().filter(|s| s.len() >= 2) {
+        shatter(sliver, impact.pos, parent.vel, time, rng, &mut debris);
+    }
+    pieces.sort_by(|a, b| area(b).total_cmp(&area(a)));
+    for extra in pieces.iter().skip(sizes.len()) {
+        shatter(extra, impact.pos, parent.vel, time, rng, &mut debris);
+    }
+    if pieces.is_empty() {
+        return (Vec::new(), debris);
+    }
+
+    let craters = parent.craters();
+    let mut spread_sum = Vec2::ZERO;
+    let mut children = Vec::new();
+    for (i, (piece, &size)) in pieces.iter().zip(sizes.iter()).enumerate() {
+        let c = centroid(piece);
+        let mass = area(piece) / (scl * scl);
+        let spread = (c - impact.pos).normalize_or_zero() * FRAGMENT_SPREAD * mass;
+        spread_sum += spread;
+
+        let points: Vec<Vec2> = piece.iter().map(|p| *p - c).collect();
+        let craters = craters
+            .iter()
+            .filter(|crater| crater.iter().all(|p| contains(piece, *p)))
+            .map(|crater| crater.iter().map(|p| *p - c).collect())
+            .collect();
+        let w = 2.0 * points.iter().fold(0.0, |m: f32, p| m.max(p.length()));
+        let torque = cross((c - impact.pos) / scl, dir);
+        children.push(Asteroid {
+            pos: c,
+            vel: spread / mass,
+            angle: 0.0,
+            spin: parent.spin + torque * FRACTURE_SPIN / mass,
+            size,
+            mass,
+            points,
+            craters,
+            seed: parent.seed.wrapping_mul(31).wrapping_add(i as u64 + 1),
+            material: parent.material,
+            hits: parent.material.hits(size),
+            w,
+            collision: false,
+            impact: None,
+        });
+    }
+
+    let total_mass: f32 = children.iter().map(|c| c.mass).sum();
+    let impulse = dir * FRAGMENT_IMPULSE * parent.mass;
+    let shared = (parent.vel * parent.mass + impulse - spread_sum) / total_mass;
+    for child in children.iter_mut() {
+        child.vel += shared;
+    }
+
+    (children, debris)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_simple;
+
+    fn square() -> Vec<Vec2> {
+        vec![
+            vec2(0.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(2.0, 2.0),
+            vec2(0.0, 2.0),
+        ]
+    }
+
+    // a U standing on its base, the notch is cut out of the top
+    fn u_shape() -> Vec<Vec2> {
+        vec![
+            vec2(0.0, 0.0),
+            vec2(3.0, 0.0),
+            vec2(3.0, 3.0),
+            vec2(2.0, 3.0),
+            vec2(2.0, 1.0),
+            vec2(1.0, 1.0),
+            vec2(1.0, 3.0),
+            vec2(0.0, 3.0),
+        ]
+    }
+
+    // the pieces cover the outline exactly and none of them crosses itself
+    fn assert_partition(points: &[Vec2], pieces: &[Vec<Vec2>]) {
+        let total: f32 = pieces.iter().map(|p| area(p)).sum();
+        assert!(
+            (total - area(points)).abs() < 1e-3 * area(points),
+            "{} pieces add up to {}, not {}",
+            pieces.len(),
+            total,
+            area(points)
+        );
+        for piece in pieces {
+            assert!(piece.len() >= 3, "{:?}", piece);
+            assert!(is_simple(piece), "{:?}", piece);
+        }
+    }
+
+    #[test]
+    fn area_and_centroid() {
+        let mut points = square();
+        assert_eq!(area(&points), 4.0);
+        assert_eq!(centroid(&points), vec2(1.0, 1.0));
+        points.reverse();
+        assert_eq!(area(&points), 4.0);
+        assert_eq!(centroid(&points), vec2(1.0, 1.0));
+        assert_eq!(area(&u_shape()), 7.0);
+
+        // no area to weigh by, the centroid falls back to the mean
+        let flat = [vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)];
+        assert_eq!(area(&flat), 0.0);
+        assert_eq!(centroid(&flat), vec2(1.0, 0.0));
+    }
+
+    #[test]
+    fn straight_cut_makes_two_halves() {
+        let pieces = split(&square(), vec2(1.0, 1.0), vec2(0.0, 1.0));
+        assert_eq!(pieces.len(), 2);
+        assert_partition(&square(), &pieces);
+        for piece in pieces.iter() {
+            assert_eq!(area(piece), 2.0);
+        }
+    }
+
+    #[test]
+    fn cut_through_vertices() {
+        let pieces = split(&square(), vec2(0.0, 0.0), vec2(1.0, 1.0));
+        assert_eq!(pieces.len(), 2);
+        assert_partition(&square(), &pieces);
+
+        // through one corner and the middle of the opposite edge
+        let pieces = split(&square(), vec2(0.0, 0.0), vec2(2.0, 1.0));
+        assert_eq!(pieces.len(), 2);
+        assert_partition(&square(), &pieces);
+    }
+
+    #[test]
+    fn cut_along_an_edge_leaves_it_whole() {
+        for (origin, dir) in [
+            (vec2(0.0, 0.0), vec2(1.0, 0.0)),
+            (vec2(2.0, 1.0), vec2(0.0, -1.0)),
+            (vec2(5.0, 5.0), vec2(1.0, 0.0)), // misses it altogether
+        ] {
+            let pieces = split(&square(), origin, dir);
+            assert_eq!(pieces.len(), 1);
+            assert_partition(&square(), &pieces);
+        }
+
+        // the cut runs along the bottom of the notch
+        let pieces = split(&u_shape(), vec2(0.0, 1.0), vec2(1.0, 0.0));
+        assert_partition(&u_shape(), &pieces);
+    }
+
+    #[test]
+    fn concave_outline_falls_into_more_pieces() {
+        let pieces = split(&u_shape(), vec2(0.0, 2.0), vec2(1.0, 0.0));
+        assert_eq!(pieces.len(), 3);
+        assert_partition(&u_shape(), &pieces);
+        let mut areas: Vec<f32> = pieces.iter().map(|p| area(p)).collect();
+        areas.sort_by(f32::total_cmp);
+        assert_eq!(areas, [1.0, 1.0, 5.0]);
+    }
+
+    #[test]
+    fn slivers_still_add_up() {
+        let pieces = split(&square(), vec2(0.0, 1e-4), vec2(1.0, 0.0));
+        assert_eq!(pieces.len(), 2);
+        assert_partition(&square(), &pieces);
+        assert!(pieces.iter().any(|p| area(p) < 1e-3));
+    }
+
+    fn star() -> Vec<Vec2> {
+        (0..10)
+            .map(|i| {
+                let a = i as f32 * std::f32::consts::TAU / 10.0;
+                let r = if i % 2 == 0 { 3.0 } else { 1.0 };
+                vec2(a.cos(), a.sin()) * r
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cuts_through_every_two_points_of_a_star() {
+        let star = star();
+        for a in star.iter() {
+            for b in star.iter().filter(|b| *b != a) {
+                assert_partition(&star, &split(&star, *a, *b - *a));
+            }
+        }
+        // through the inner points on both sides, four tips come off
+        let pieces = split(&star, star[1], star[1] - star[7]);
+        assert_partition(&star, &pieces);
+    }
+
+    #[test]
+    fn random_cuts_through_a_star() {
+        let star = star();
+        let mut rng = Rng::new(7);
+        for _ in 0..500 {
+            let origin = vec2(rng.gen_range(-3.0, 3.0), rng.gen_range(-3.0, 3.0));
+            let dir = rotate(vec2(1.0, 0.0), rng.gen_range(0.0, 360.0));
+            assert_partition(&star, &split(&star, origin, dir));
+        }
+    }
+}
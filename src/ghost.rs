@@ -0,0 +1,151 @@
+use super::{
+    replay::{self, Replay},
+    rollback::checksum,
+    scores::{Entry, Table},
+    stage, step, Action, GameState, Spaceship, TICK,
+};
+
+// the best run so far for the same start, played again tick by tick
+struct Ghost {
+    game: GameState,
+    entry: Entry,
+}
+
+impl Ghost {
+    fn new(entry: &Entry) -> Ghost {
+        Ghost {
+            game: entry.replay.start(),
+            entry: entry.clone(),
+        }
+    }
+
+    fn step(&mut self) {
+        if let Some(action) = self.entry.replay.action(self.game.tick as usize) {
+            step(&mut self.game, &[action]);
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.game.tick as usize >= self.entry.replay.len()
+    }
+}
+
+struct Recording {
+    replay: Replay,
+    splits: Vec<u32>,
+}
+
+/*
+    Records every solo campaign run from its first tick and races it
+    against the best one recorded for the same seed. The ghost is a second
+    game stepped with the recorded actions alongside the live one, so it
+    always is where that run was on the same tick. Finished runs that make
+    the high score table are saved to it with their replay.
+
+    Call before() and after() around every tick of the live game.
+*/
+pub struct Race {
+    path: String,
+    pub table: Table,
+    recording: Option<Recording>,
+    ghost: Option<Ghost>,
+}
+
+impl Race {
+    pub fn new(path: &str) -> Race {
+        Race {
+            path: path.to_string(),
+            table: Table::load(path),
+            recording: None,
+            ghost: None,
+        }
+    }
+
+    pub fn before(&mut self, gs: &GameState) {
+        if gs.tick == 0 {
+            self.recording = None;
+            self.ghost = None;
+            if !replay::is_recordable(gs) {
+                return;
+            }
+            // a run that doesn't start out the way its replay would can't be played again
+            let replay = Replay::new(gs);
+            if checksum(&replay.start()) != checksum(gs) {
+                return;
+            }
+            self.ghost = self.table.best(&replay).map(Ghost::new);
+            self.recording = Some(Recording {
+                replay,
+                splits: Vec::new(),
+            });
+        }
+        // a resized window or the wingman switched mid-run can't be replayed
+        if self
+            .recording
+            .as_ref()
+            .is_some_and(|r| !r.replay.matches(gs))
+        {
+            self.recording = None;
+            self.ghost = None;
+        }
+    }
+
+    // stops recording and racing the run, a quickload took it somewhere it never went
+    pub fn abandon(&mut self) {
+        self.recording = None;
+        self.ghost = None;
+    }
+
+    // the run once it has ended, as it went into the table
+    pub fn after(&mut self, gs: &GameState, action: Action) -> Option<Entry> {
+        if let Some(ghost) = &mut self.ghost {
+            ghost.step();
+        }
+        let recording = self.recording.as_mut()?;
+        recording.replay.push(action);
+        if let Some(tick) = stage::split(gs, recording.splits.len()) {
+            recording.splits.push(tick);
+        }
+        if stage::is_run_over(gs) {
+            let recording = self.recording.take().unwrap();
+            let entry = Entry {
+                name: String::new(),
+                score: gs.players[0].score,
+                stage: gs.stage,
+                ticks: gs.tick,
+                splits: recording.splits,
+                replay: recording.replay,
+            };
+            if self.table.insert(entry.clone()).is_some() {
+                if let Err(e) = self.table.save(&self.path) {
+                    eprintln!("could not save {}: {}", self.path, e);
+                }
+            }
+            return Some(entry);
+        }
+        None
+    }
+
+    // the ghost's ship while it flies the same stage as the live one
+    pub fn ghost(&self, gs: &GameState) -> Option<&Spaceship> {
+        let ghost = self.ghost.as_ref()?;
+        let same_stage = ghost.game.stage == gs.stage;
+        (same_stage && !ghost.is_done()).then(|| &ghost.game.players[0].ship)
+    }
+
+    /*
+        How far ahead of the ghost the live run is: the points right now
+        and the seconds at the last stage both have cleared, negative for
+        faster. None when there is no ghost.
+    */
+    pub fn deltas(&self, gs: &GameState) -> Option<(i32, Option<f32>)> {
+        let ghost = self.ghost.as_ref()?;
+        let score = gs.players[0].score - ghost.game.players[0].score;
+        let splits = &self.recording.as_ref()?.splits;
+        let cleared = splits.len().min(ghost.entry.splits.len());
+        let time = cleared
+            .checked_sub(1)
+            .map(|i| (splits[i] as f32 - ghost.entry.splits[i] as f32) * TICK);
+        Some((score, time))
+    }
+}
@@ -0,0 +1,110 @@
+use super::{
+    kill_player, GameState, GravityWell, Impact, RunState, WellKind, TRAJECTORY_STEPS,
+    TRAJECTORY_STEP_TIME, WELL_SOFTENING,
+};
+use glam::{vec2, Vec2};
+
+/*
+    Inverse-square pull of all wells at `pos`, in pixels per second squared.
+    A well's strength is its pull in units per second squared at a distance
+    of one unit. Distances below WELL_SOFTENING are clamped so the pull
+    stays finite at the center.
+*/
+pub fn accel(wells: &[GravityWell], pos: Vec2, scl: f32) -> Vec2 {
+    wells.iter().fold(Vec2::ZERO, |acc, well| {
+        let d = (well.pos - pos) / scl;
+        let r2 = d.length_squared().max(WELL_SOFTENING * WELL_SOFTENING);
+        acc + d.normalize_or_zero() * well.strength * scl / r2
+    })
+}
+
+// the black hole or planet `pos` is inside of, if any
+pub fn inside(wells: &[GravityWell], pos: Vec2) -> Option<WellKind> {
+    wells
+        .iter()
+        .find(|w| w.kind != WellKind::Well && w.pos.distance(pos) < w.radius)
+        .map(|w| w.kind)
+}
+
+/*
+    Velocity for a circular orbit around `well` at `pos`, counter clockwise
+    on screen, in pixels per second. With the pull above, mu is
+    strength * scl³ in pixels.
+*/
+pub fn orbital_velocity(well: &GravityWell, pos: Vec2, scl: f32) -> Vec2 {
+    let d = pos - well.pos;
+    let speed = (well.strength * scl.powi(3) / d.length().max(1.0)).sqrt();
+    vec2(d.y, -d.x).normalize_or_zero() * speed
+}
+
+// path of a free falling object, cut short where it hits a body
+pub fn predict(wells: &[GravityWell], pos: Vec2, vel: Vec2, scl: f32) -> Vec<Vec2> {
+    let mut path = vec![pos];
+    let mut pos = pos;
+    let mut vel = vel;
+    for _ in 0..TRAJECTORY_STEPS {
+        vel += accel(wells, pos, scl) * TRAJECTORY_STEP_TIME;
+        pos += vel * TRAJECTORY_STEP_TIME;
+        path.push(pos);
+        if inside(wells, pos).is_some() {
+            break;
+        }
+    }
+    path
+}
+
+/*
+    Velocities are updated before positions are integrated in update, which
+    makes this semi-implicit Euler and keeps orbits from spiralling out.
+*/
+pub fn update(gs: &mut GameState, delta: f32) {
+    if gs.wells.is_empty() {
+        return;
+    }
+    let wells = &gs.wells;
+    let scl = gs.scl;
+
+    // ship velocity is in pixels per frame, everything else per second
+    for p in gs.players.iter_mut().filter(|p| !p.down) {
+        p.ship.vel += accel(wells, p.ship.pos, scl) * delta * delta;
+    }
+    for a in gs.asteroids.iter_mut() {
+        a.vel += accel(wells, a.pos, scl) * delta;
+        // asteroids vanish into black holes but crash on planets
+        if inside(wells, a.pos) == Some(WellKind::Planet) && !a.collision {
+            a.collision = true;
+            a.impact = Some(Impact {
+                pos: a.pos,
+                dir: a.vel.normalize_or_zero(),
+                owner: 0,
+            });
+        }
+    }
+    for b in gs.bullets.iter_mut() {
+        b.vel += accel(wells, b.pos, scl) * delta;
+        if inside(wells, b.pos).is_some() {
+            b.collision = true;
+        }
+    }
+    for e in gs.exhaust.iter_mut() {
+        e.vel += accel(wells, e.pos, scl) * delta;
+    }
+    for d in gs.debris.iter_mut() {
+        d.vel += accel(wells, d.pos, scl) * delta;
+    }
+
+    gs.asteroids
+        .retain(|a| inside(wells, a.pos) != Some(WellKind::BlackHole));
+    gs.exhaust.retain(|e| inside(wells, e.pos).is_none());
+    gs.debris.retain(|d| inside(wells, d.pos).is_none());
+
+    for i in 0..gs.players.len() {
+        let player = &gs.players[i];
+        if gs.run_state == RunState::Running
+            && !player.down
+            && inside(&gs.wells, player.ship.pos).is_some()
+        {
+            kill_player(gs, i);
+        }
+    }
+}
@@ -1,14 +1,18 @@
 use crate::components::{RunState, COMBO_TIMER};
 
 use super::{
-    draw_spaceship, GameState, Spaceship, DARK, GAME_TIME, LIGHT, MAX_PLAYER_LIVES, PLAYER_HEIGHT,
-    PLAYER_WIDTH,
+    boss::BOSS_PHASES,
+    daily::Attempt,
+    draw_spaceship, hotseat,
+    speedrun::{Mark, Split},
+    stage, versus, zone, GameState, Mode, Player, Spaceship, DAILY_SHOWN, DARK, FONT_SIZE,
+    GAME_TIME, GUI_BAR_HEIGHT, LIGHT, MAX_PLAYER_LIVES, PLAYER_HEIGHT, PLAYER_WIDTH, SPLITS_SHOWN,
+    SPLIT_BEHIND, SPLIT_GOLD, TICK,
 };
 use macroquad::prelude::{
     draw_rectangle, draw_text, draw_triangle, measure_text, screen_height, screen_width, vec2, GRAY,
 };
 
-pub const GUI_BAR_HEIGHT: f32 = 50.0;
 pub const GUI_NUMBER_FONT_SIZE: f32 = 50.0;
 
 pub fn draw(gs: &GameState) {
@@ -21,22 +25,133 @@ pub fn draw(gs: &GameState) {
     );
 
     if gs.run_state == RunState::StageComplete {
-        // skriv stage complete
+        let text = if gs.mode == Mode::Versus {
+            format!(
+                "Round {} over. Press Enter for round {}.",
+                gs.round + 1,
+                gs.round + 2
+            )
+        } else if !stage::is_game_over(gs) && stage::is_cleared(gs) && stage::has_next(gs) {
+            format!(
+                "{} clear. Press Enter to continue.",
+                gs.stages[gs.stage].name
+            )
+        } else {
+            "Game over. Press Enter to restart.".to_string()
+        };
+        let text_size = measure_text(&text, None, FONT_SIZE as _, 1.0);
+        draw_text(
+            &text,
+            screen_width() / 2.0 - text_size.width / 2.0,
+            screen_height() / 2.0 - PLAYER_HEIGHT * 2.0 * gs.scl,
+            FONT_SIZE,
+            LIGHT,
+        );
+    }
+    // draw boss health
+    if let Some(boss) = &gs.boss {
+        let bw = screen_width() * 0.6;
+        let bx = screen_width() / 2.0 - bw / 2.0;
+        let by = 10.0;
+        let name = format!("{:?}  {}/{}", boss.kind, boss.phase + 1, BOSS_PHASES);
+        let name_size = measure_text(&name, None, FONT_SIZE as _, 1.0);
+        draw_text(
+            &name,
+            screen_width() / 2.0 - name_size.width / 2.0,
+            by + name_size.height,
+            FONT_SIZE,
+            LIGHT,
+        );
+        let bar_y = by + name_size.height + 5.0;
+        draw_rectangle(bx, bar_y, bw, 6.0, GRAY);
+        draw_rectangle(
+            bx,
+            bar_y,
+            bw * (boss.health.max(0) as f32 / boss.max_health as f32),
+            6.0,
+            LIGHT,
+        );
+    }
+
+    // one panel per player, side by side in co-op
+    let w = screen_width() / gs.players.len() as f32;
+    for (i, player) in gs.players.iter().enumerate() {
+        draw_player(gs, player, w * i as f32, w);
+    }
+
+    let time = ((GAME_TIME - gs.play_time) as i8).to_string();
+    let time_size = measure_text(&time, None, GUI_NUMBER_FONT_SIZE as _, 1.0);
+    let ty = if gs.players.len() > 1 {
+        time_size.height + 10.0
+    } else {
+        screen_height() - GUI_BAR_HEIGHT / 2.0 + time_size.height / 2.0
+    };
+    draw_text(
+        &time,
+        screen_width() / 2.0 - time_size.width / 2.0,
+        ty,
+        GUI_NUMBER_FONT_SIZE,
+        GRAY,
+    );
+
+    if gs.mode == Mode::HotSeat {
+        draw_seats(gs);
+    }
+
+    if gs.players.len() > 1 && gs.friendly_fire {
+        let text = "FRIENDLY FIRE";
+        let text_size = measure_text(text, None, FONT_SIZE as _, 1.0);
+        draw_text(
+            text,
+            screen_width() / 2.0 - text_size.width / 2.0,
+            ty + text_size.height + 5.0,
+            FONT_SIZE,
+            GRAY,
+        );
+    }
+}
+
+// the gui bar for one player, laid out within x..x + w
+fn draw_player(gs: &GameState, player: &Player, x: f32, w: f32) {
+    // draw hull integrity once it has taken damage
+    if player.ship.hull < 1.0 {
+        let hw = w / 5.0;
+        let hx = x + w / 2.0 - hw / 2.0;
+        let hy = screen_height() - GUI_BAR_HEIGHT - 10.0;
+        draw_rectangle(hx, hy, hw, 4.0, GRAY);
+        draw_rectangle(hx, hy, hw * player.ship.hull.max(0.0), 4.0, player.color);
     }
-    // draw score
-    let bg_score_string = &"00000".to_string();
+    if !player.down && !zone::thrusters_online(&player.ship, gs.time) {
+        let text = "THRUSTERS OFFLINE";
+        let text_size = measure_text(text, None, FONT_SIZE as _, 1.0);
+        draw_text(
+            text,
+            x + w / 2.0 - text_size.width / 2.0,
+            screen_height() - GUI_BAR_HEIGHT - 20.0,
+            FONT_SIZE,
+            player.color,
+        );
+    }
+
+    // draw score, in versus it's kills and deaths
+    let versus = gs.mode == Mode::Versus;
+    let bg_score_string = &if versus { "00/00" } else { "00000" }.to_string();
     let bg_text_size = measure_text(bg_score_string, None, GUI_NUMBER_FONT_SIZE as _, 1.0);
-    let score_string = &gs.score.to_string();
+    let score_string = &if versus {
+        format!("{}/{}", player.kills, player.deaths)
+    } else {
+        player.score.to_string()
+    };
     let text_size = measure_text(score_string, None, GUI_NUMBER_FONT_SIZE as _, 1.0);
     draw_text(
         bg_score_string,
-        screen_width() - bg_text_size.width - 10.0,
+        x + w - bg_text_size.width - 10.0,
         screen_height() - GUI_BAR_HEIGHT / 2.0 + bg_text_size.height / 2.0,
         GUI_NUMBER_FONT_SIZE,
         GRAY,
     );
     draw_rectangle(
-        screen_width() - text_size.width - 10.0,
+        x + w - text_size.width - 10.0,
         screen_height() - GUI_BAR_HEIGHT,
         text_size.width,
         GUI_BAR_HEIGHT,
@@ -44,20 +159,26 @@ pub fn draw(gs: &GameState) {
     );
     draw_text(
         &score_string,
-        screen_width() - text_size.width - 10.0,
+        x + w - text_size.width - 10.0,
         screen_height() - GUI_BAR_HEIGHT / 2.0 + text_size.height / 2.0,
         GUI_NUMBER_FONT_SIZE,
-        LIGHT,
+        player.color,
     );
 
     //draw combo timer
     let th = GUI_BAR_HEIGHT * 0.5;
-    let tw = screen_width() / 5.0;
-    let tx = GUI_BAR_HEIGHT * 1.5;
+    let tw = w / 5.0;
+    let tx = x + GUI_BAR_HEIGHT * 1.5;
     let ty = screen_height() - GUI_BAR_HEIGHT / 2.0 - th / 2.0;
     draw_rectangle(tx, ty, tw, th, GRAY); //bg
-    if gs.combo_time > 0.0 {
-        draw_rectangle(tx, ty, tw * (gs.combo_time / COMBO_TIMER), th, LIGHT); //actual timer
+    if player.combo_time > 0.0 {
+        draw_rectangle(
+            tx,
+            ty,
+            tw * (player.combo_time / COMBO_TIMER),
+            th,
+            player.color,
+        ); //actual timer
     }
     draw_triangle(vec2(tx, ty), vec2(tx + 20.0, ty), vec2(tx, ty + th), DARK);
     draw_triangle(
@@ -70,9 +191,9 @@ pub fn draw(gs: &GameState) {
     //draw combo
     let bg_combo_string = &"00".to_string();
     let bg_combo_size = measure_text(bg_combo_string, None, GUI_NUMBER_FONT_SIZE as _, 1.0);
-    let combo_string = &gs.combo.to_string();
+    let combo_string = &player.combo.to_string();
     let combo_size = measure_text(combo_string, None, GUI_NUMBER_FONT_SIZE as _, 1.0);
-    let cx = 85.0;
+    let cx = x + 85.0;
     let cy = screen_height() - GUI_BAR_HEIGHT / 2.0 - 5.0;
     draw_text(
         bg_combo_string,
@@ -81,7 +202,7 @@ pub fn draw(gs: &GameState) {
         GUI_NUMBER_FONT_SIZE,
         GRAY,
     );
-    if gs.combo > 0 {
+    if player.combo > 0 {
         draw_rectangle(
             cx - combo_size.width - 10.0,
             cy - combo_size.height / 2.0,
@@ -94,13 +215,13 @@ pub fn draw(gs: &GameState) {
             cx - combo_size.width - 10.0,
             cy + combo_size.height / 2.0,
             GUI_NUMBER_FONT_SIZE,
-            LIGHT,
+            player.color,
         );
     }
 
     //draw multiplier
-    if gs.score_multiplier > 1 {
-        let multiplier = &format!("{}x", gs.score_multiplier).to_string();
+    if player.score_multiplier > 1 {
+        let multiplier = &format!("{}x", player.score_multiplier).to_string();
         let multiplier_size =
             measure_text(multiplier, None, (GUI_NUMBER_FONT_SIZE - 15.0) as _, 1.0);
         draw_text(
@@ -108,26 +229,254 @@ pub fn draw(gs: &GameState) {
             tx + tw,
             screen_height() - GUI_BAR_HEIGHT / 2.0 + multiplier_size.height / 2.0,
             GUI_NUMBER_FONT_SIZE - 15.0,
-            LIGHT,
+            player.color,
         );
     }
 
-    draw_text(
-        &((GAME_TIME - gs.play_time) as i8).to_string(),
-        screen_width() / 2.0 - 10.0,
-        screen_height() - GUI_BAR_HEIGHT / 2.0 + text_size.height / 2.0,
-        GUI_NUMBER_FONT_SIZE,
-        GRAY,
-    );
-
+    if versus {
+        return;
+    }
     let mut mock = Spaceship::new(0.0, 0.0, PLAYER_WIDTH / 2., PLAYER_HEIGHT / 2.);
-    for i in 0..gs.lives {
+    for i in 0..player.lives {
         mock.pos = vec2(
-            ((screen_width() / 1.25) + PLAYER_WIDTH * gs.scl)
+            ((x + w / 1.25) + PLAYER_WIDTH * gs.scl)
                 - PLAYER_WIDTH * gs.scl * MAX_PLAYER_LIVES as f32
                 + (PLAYER_WIDTH * gs.scl * i as f32),
             screen_height() - PLAYER_WIDTH * gs.scl / 1.25,
         );
-        draw_spaceship(&mock, gs.scl, gs.debug)
+        draw_spaceship(&mock, player.color, gs.scl, gs.debug, &[])
+    }
+}
+
+// final standings after the last versus round
+pub fn draw_summary(gs: &GameState) {
+    let title = "Match over";
+    let title_size = measure_text(title, None, GUI_NUMBER_FONT_SIZE as _, 1.0);
+    let mut y = screen_height() / 3.0;
+    draw_text(
+        title,
+        screen_width() / 2.0 - title_size.width / 2.0,
+        y,
+        GUI_NUMBER_FONT_SIZE,
+        LIGHT,
+    );
+
+    for (place, i) in versus::standings(gs).into_iter().enumerate() {
+        let player = &gs.players[i];
+        let row = format!(
+            "{}.  Player {}    {} kills    {} deaths",
+            place + 1,
+            i + 1,
+            player.kills,
+            player.deaths
+        );
+        let row_size = measure_text(&row, None, FONT_SIZE as _, 1.0);
+        y += row_size.height * 2.0;
+        draw_text(
+            &row,
+            screen_width() / 2.0 - row_size.width / 2.0,
+            y,
+            FONT_SIZE,
+            player.color,
+        );
+    }
+
+    let text = "Press Enter for a rematch.";
+    let text_size = measure_text(text, None, FONT_SIZE as _, 1.0);
+    draw_text(
+        text,
+        screen_width() / 2.0 - text_size.width / 2.0,
+        y + text_size.height * 4.0,
+        FONT_SIZE,
+        GRAY,
+    );
+}
+
+// every hot-seat player's score in the corner, whoever is up in their color
+fn draw_seats(gs: &GameState) {
+    let mut y = 10.0;
+    for game in hotseat::games(gs) {
+        let text = format!("P{}  {}", game.seat + 1, game.players[0].score);
+        let text_size = measure_text(&text, None, FONT_SIZE as _, 1.0);
+        y += text_size.height + 5.0;
+        let color = if game.seat == gs.seat {
+            game.players[0].color
+        } else {
+            GRAY
+        };
+        draw_text(&text, 10.0, y, FONT_SIZE, color);
+    }
+}
+
+// shown between hot-seat turns until the next player is ready
+pub fn draw_turn_change(gs: &GameState) {
+    let title = format!("PLAYER {} UP", gs.seat + 1);
+    let title_size = measure_text(&title, None, GUI_NUMBER_FONT_SIZE as _, 1.0);
+    draw_text(
+        &title,
+        screen_width() / 2.0 - title_size.width / 2.0,
+        screen_height() / 2.0,
+        GUI_NUMBER_FONT_SIZE,
+        gs.players[0].color,
+    );
+
+    let text = "Press Space to start.";
+    let text_size = measure_text(text, None, FONT_SIZE as _, 1.0);
+    draw_text(
+        text,
+        screen_width() / 2.0 - text_size.width / 2.0,
+        screen_height() / 2.0 + PLAYER_HEIGHT * 2.0 * gs.scl,
+        FONT_SIZE,
+        LIGHT,
+    );
+    draw_seats(gs);
+}
+
+/*
+    Ahead or behind the ghost just above the gui bar: points first, then
+    seconds at the last stage both runs cleared. Ahead is in the light
+    color, behind in gray.
+*/
+pub fn draw_race(score: i32, time: Option<f32>) {
+    let mut x = 10.0;
+    let y = screen_height() - GUI_BAR_HEIGHT - 10.0;
+    let score_text = format!("GHOST {:+}", score);
+    let time_text = time.map(|t| format!("{:+.2}s", t));
+    let parts = [
+        (Some(score_text), score >= 0),
+        (time_text, time.is_some_and(|t| t <= 0.0)),
+    ];
+    for (text, ahead) in parts {
+        let Some(text) = text else {
+            continue;
+        };
+        draw_text(&text, x, y, FONT_SIZE, if ahead { LIGHT } else { GRAY });
+        x += measure_text(&text, None, FONT_SIZE as _, 1.0).width + 15.0;
+    }
+}
+
+/*
+    The speedrun timer in the top left corner, the run's time over its
+    last few splits. Each split shows the time against the personal best,
+    in gold when the stage went faster than it ever has.
+*/
+pub fn draw_speedrun(time: f32, splits: &[Split]) {
+    let mut y = 10.0;
+    for split in splits
+        .iter()
+        .skip(splits.len().saturating_sub(SPLITS_SHOWN))
+    {
+        let delta = match split.delta {
+            Some(d) => format!("{:+.2}", d as f32 * TICK),
+            None => format!("{:.2}", split.time as f32 * TICK),
+        };
+        let color = match split.mark {
+            Mark::Gold => SPLIT_GOLD,
+            Mark::Ahead => LIGHT,
+            Mark::Behind => SPLIT_BEHIND,
+            Mark::New => GRAY,
+        };
+        let row = format!("{}  {}", split.stage, delta);
+        let row_size = measure_text(&row, None, FONT_SIZE as _, 1.0);
+        y += row_size.height + 5.0;
+        draw_text(&row, 10.0, y, FONT_SIZE, color);
+    }
+
+    let clock = format!("{}:{:05.2}", (time / 60.0) as u32, time % 60.0);
+    let clock_size = measure_text(&clock, None, FONT_SIZE as _, 1.0);
+    draw_text(&clock, 10.0, y + clock_size.height + 5.0, FONT_SIZE, LIGHT);
+}
+
+/*
+    The title screen, `demo` while the autopilot plays behind it. `daily`
+    are the lines about today's challenge under the prompt. Scores from an
+    online leaderboard come with the name of who set them.
+*/
+pub fn draw_title(demo: bool, scores: &[(String, i32)], daily: &[String]) {
+    let title = "ASTEROIDS";
+    let title_size = measure_text(title, None, GUI_NUMBER_FONT_SIZE as _, 1.0);
+    draw_text(
+        title,
+        screen_width() / 2.0 - title_size.width / 2.0,
+        screen_height() / 3.0,
+        GUI_NUMBER_FONT_SIZE,
+        LIGHT,
+    );
+
+    let text = if demo {
+        "DEMO    Press any key to play."
+    } else {
+        "Press any key to play."
+    };
+    let text_size = measure_text(text, None, FONT_SIZE as _, 1.0);
+    draw_text(
+        text,
+        screen_width() / 2.0 - text_size.width / 2.0,
+        screen_height() / 3.0 + title_size.height * 2.0,
+        FONT_SIZE,
+        if demo { GRAY } else { LIGHT },
+    );
+
+    let mut y = screen_height() / 3.0 + title_size.height * 2.0 + text_size.height * 2.0;
+    for line in daily {
+        let line_size = measure_text(line, None, FONT_SIZE as _, 1.0);
+        draw_text(
+            line,
+            screen_width() / 2.0 - line_size.width / 2.0,
+            y,
+            FONT_SIZE,
+            GRAY,
+        );
+        y += line_size.height * 1.5;
+    }
+
+    y += text_size.height;
+    for (place, (name, score)) in scores.iter().enumerate() {
+        let row = match name.as_str() {
+            "" => format!("{:2}.  {:>6}", place + 1, score),
+            name => format!("{:2}.  {}  {:>6}", place + 1, name, score),
+        };
+        let row_size = measure_text(&row, None, FONT_SIZE as _, 1.0);
+        draw_text(
+            &row,
+            screen_width() / 2.0 - row_size.width / 2.0,
+            y,
+            FONT_SIZE,
+            GRAY,
+        );
+        y += row_size.height * 1.5;
     }
 }
+
+/*
+    The daily challenge board under the game over text, the best days
+    with `today` in the light color.
+*/
+pub fn draw_daily(board: &[&Attempt], today: &str) {
+    let mut y = screen_height() / 2.0;
+    for (place, attempt) in board.iter().take(DAILY_SHOWN).enumerate() {
+        let row = format!("{:2}.  {}  {:>6}", place + 1, attempt.date, attempt.score);
+        let row_size = measure_text(&row, None, FONT_SIZE as _, 1.0);
+        let color = if attempt.date == today { LIGHT } else { GRAY };
+        draw_text(
+            &row,
+            screen_width() / 2.0 - row_size.width / 2.0,
+            y,
+            FONT_SIZE,
+            color,
+        );
+        y += row_size.height * 1.5;
+    }
+}
+
+// network status in the top right corner, `alert` for trouble like a desync
+pub fn draw_net_status(text: &str, alert: bool) {
+    let text_size = measure_text(text, None, FONT_SIZE as _, 1.0);
+    draw_text(
+        text,
+        screen_width() - text_size.width - 10.0,
+        text_size.height + 10.0,
+        FONT_SIZE,
+        if alert { LIGHT } else { GRAY },
+    );
+}
@@ -0,0 +1,192 @@
+use super::{
+    get_new_game_state, intersection, stage, wrapped_offset, Action, GameState, Mode, RunState,
+    GYM_DEATH_PENALTY, GYM_NEAREST, GYM_RAY_LENGTH, GYM_SCORE_WEIGHT, MAX_PLAYER_LIVES, TICK,
+};
+use glam::{vec2, Vec2};
+use std::f32::consts::PI;
+
+const SHIP_VALUES: usize = 7;
+const ASTEROID_VALUES: usize = 5;
+
+pub struct Config {
+    pub nearest: usize,      // asteroids in each observation, closest first
+    pub rays: usize,         // sensors spread evenly around the ship, 0 for none
+    pub ray_length: f32,     // units
+    pub ticks_per_step: u32, // the action is held for this many ticks
+    pub max_steps: u32,      // episodes end after this many steps, 0 for never
+    pub score_weight: f32,   // reward per point scored
+    pub death_penalty: f32,  // reward taken per life lost
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            nearest: GYM_NEAREST,
+            rays: 0,
+            ray_length: GYM_RAY_LENGTH,
+            ticks_per_step: 1,
+            max_steps: 0,
+            score_weight: GYM_SCORE_WEIGHT,
+            death_penalty: GYM_DEATH_PENALTY,
+        }
+    }
+}
+
+impl Config {
+    pub fn observation_len(&self) -> usize {
+        SHIP_VALUES + self.nearest * ASTEROID_VALUES + self.rays
+    }
+}
+
+/*
+    The single player campaign as a reinforcement learning environment,
+    reset(seed) and step(action) like a Gym environment. It never opens a
+    window or plays a sound so it runs as fast as the simulation does.
+    Whatever the game would wait for a key press for, a respawn or the
+    next stage, happens right away. An episode is over when the last life
+    is lost, the last stage is cleared or max_steps have been taken.
+
+    An action is the game's Action, Action::from_bits turns the numbers
+    0 to 255 into every combination for a discrete action space.
+*/
+pub struct Env {
+    pub config: Config,
+    gs: GameState,
+    steps: u32,
+}
+
+impl Env {
+    pub fn new(config: Config) -> Env {
+        Env {
+            config,
+            gs: get_new_game_state(1, Mode::Campaign),
+            steps: 0,
+        }
+    }
+
+    // starts a new episode, the seed changes the asteroids of every stage
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.gs = get_new_game_state(1, Mode::Campaign);
+        self.gs.seed = seed;
+        stage::start(&mut self.gs, 0);
+        self.steps = 0;
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: Action) -> (Vec<f32>, f32, bool) {
+        let before = (self.gs.players[0].score, self.gs.players[0].lives);
+        for _ in 0..self.config.ticks_per_step.max(1) {
+            super::step(&mut self.gs, &[action]);
+            self.carry_on();
+            if self.is_over() {
+                break;
+            }
+        }
+        self.steps += 1;
+
+        let player = &self.gs.players[0];
+        let reward = (player.score - before.0) as f32 * self.config.score_weight
+            - (before.1 - player.lives) as f32 * self.config.death_penalty;
+        let out_of_steps = self.config.max_steps > 0 && self.steps >= self.config.max_steps;
+        (self.observe(), reward, self.is_over() || out_of_steps)
+    }
+
+    // the game behind the environment, to draw it or look closer
+    pub fn game(&self) -> &GameState {
+        &self.gs
+    }
+
+    pub fn observation_len(&self) -> usize {
+        self.config.observation_len()
+    }
+
+    fn carry_on(&mut self) {
+        let gs = &mut self.gs;
+        match gs.run_state {
+            RunState::Death => gs.run_state = RunState::Running,
+            RunState::StageComplete
+                if !stage::is_game_over(gs) && stage::is_cleared(gs) && stage::has_next(gs) =>
+            {
+                stage::start(gs, gs.stage + 1)
+            }
+            _ => {}
+        }
+    }
+
+    // game over or all stages cleared, carry_on has moved on from the rest
+    fn is_over(&self) -> bool {
+        self.gs.run_state == RunState::StageComplete
+    }
+
+    /*
+        The ship's seven values first: its position across the screen in
+        0..1, velocity, the sine and cosine of its heading and the lives it
+        has left in 0..1. Then five for each of the nearest asteroids: the
+        offset from the ship the short way round the wrapping screen, the
+        velocity relative to the ship and the radius, zeros where there are
+        fewer asteroids. Last the rays, each the distance to the first
+        asteroid along it over ray_length, or 1 when it hits nothing.
+    */
+    pub fn observe(&self) -> Vec<f32> {
+        let gs = &self.gs;
+        let ship = &gs.players[0].ship;
+        let unit = gs.scl;
+        let ship_vel = ship.vel / TICK; // the ship moves by its velocity every tick
+        let heading = ship.angle.to_radians();
+
+        let mut obs = Vec::with_capacity(self.observation_len());
+        obs.extend([
+            ship.pos.x / gs.screen.x,
+            ship.pos.y / gs.screen.y,
+            ship_vel.x / unit,
+            ship_vel.y / unit,
+            heading.sin(),
+            heading.cos(),
+            gs.players[0].lives as f32 / MAX_PLAYER_LIVES as f32,
+        ]);
+
+        let mut near: Vec<(Vec2, usize)> = gs
+            .asteroids
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| !a.collision)
+            .map(|(i, a)| (wrapped_offset(ship.pos, a.pos, gs.screen), i))
+            .collect();
+        near.sort_by(|a, b| a.0.length().total_cmp(&b.0.length()));
+        for k in 0..self.config.nearest {
+            match near.get(k) {
+                Some((offset, i)) => {
+                    let a = &gs.asteroids[*i];
+                    let vel = (a.vel - ship_vel) / unit;
+                    obs.extend([offset.x / unit, offset.y / unit, vel.x, vel.y]);
+                    obs.push(a.w / 2.0 / unit);
+                }
+                None => obs.extend([0.0; ASTEROID_VALUES]),
+            }
+        }
+
+        let reach = self.config.ray_length * unit;
+        for k in 0..self.config.rays {
+            let angle = heading + k as f32 * 2.0 * PI / self.config.rays as f32;
+            let end = ship.pos + vec2(angle.sin(), -angle.cos()) * reach;
+            let mut nearest = reach;
+            for (offset, i) in near.iter() {
+                let a = &gs.asteroids[*i];
+                if offset.length() - a.w / 2.0 > nearest {
+                    break; // sorted by distance, nothing further on can be closer
+                }
+                // as if the asteroid were on the ship's side of the screen edge
+                let shift = ship.pos + *offset - a.pos;
+                let p = a.points();
+                for j in 0..p.len() {
+                    let (c, d) = (p[j] + shift, p[(j + 1) % p.len()] + shift);
+                    if let Some(hit) = intersection(ship.pos, end, c, d) {
+                        nearest = nearest.min(hit.distance(ship.pos));
+                    }
+                }
+            }
+            obs.push(nearest / reach);
+        }
+        obs
+    }
+}
@@ -0,0 +1,70 @@
+/*
+    Stand-ins for the few macroquad types the game state holds, for builds
+    without the gui feature like the dedicated server. Nothing is drawn,
+    played or read off a keyboard there, so they only have to exist and
+    keep the same shape.
+*/
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color { r, g, b, a }
+    }
+}
+
+macro_rules! color_u8 {
+    ($r:expr, $g:expr, $b:expr, $a:expr) => {
+        Color::new(
+            $r as f32 / 255.0,
+            $g as f32 / 255.0,
+            $b as f32 / 255.0,
+            $a as f32 / 255.0,
+        )
+    };
+}
+pub(crate) use color_u8;
+
+// the keys in the bindings
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyCode {
+    A,
+    D,
+    E,
+    I,
+    J,
+    K,
+    L,
+    M,
+    O,
+    Q,
+    S,
+    U,
+    W,
+    Space,
+    Comma,
+    Period,
+    Slash,
+    Left,
+    Right,
+    Up,
+    Down,
+    RightControl,
+    Kp0,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    KpEnter,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sound;
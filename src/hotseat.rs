@@ -0,0 +1,75 @@
+use super::{get_new_game_state, stage, GameState, Mode, RunState, PLAYER_COLORS};
+
+/*
+    Classic alternating play. Every player gets a whole game of their own,
+    the active one lives in the GameState everything else works on and the
+    others wait in its bench until their turn comes around again.
+*/
+pub fn new_game(seats: usize) -> GameState {
+    let mut games: Vec<GameState> = (0..seats)
+        .map(|seat| {
+            let mut gs = get_new_game_state(1, Mode::Campaign);
+            gs.mode = Mode::HotSeat;
+            gs.seat = seat;
+            gs.players[0].color = PLAYER_COLORS[seat];
+            gs
+        })
+        .collect();
+    let mut gs = games.remove(0);
+    gs.bench = games;
+    gs
+}
+
+// the next waiting game that still has lives left, in seat order
+fn next_up(gs: &GameState) -> Option<usize> {
+    let n = gs.bench.len() + 1;
+    (0..gs.bench.len())
+        .filter(|k| gs.bench[*k].players[0].lives > 0)
+        .min_by_key(|k| (gs.bench[*k].seat + n - gs.seat) % n)
+}
+
+/*
+    Called when the active player loses a life, the ship goes back to the
+    start and the turn ends. With no one else left it carries on like a solo
+    game instead.
+*/
+pub fn end_turn(gs: &mut GameState) {
+    let alive = gs.players[0].lives > 0;
+    if alive {
+        stage::respawn(gs, 0);
+    }
+    gs.run_state = match next_up(gs) {
+        Some(_) => RunState::TurnChange,
+        None if alive => RunState::Death,
+        None => RunState::StageComplete,
+    };
+}
+
+/*
+    Swaps the next player's game in once the frame that ended the turn is
+    done, the finished game goes on the bench as it is. The new one waits
+    behind the "PLAYER N UP" screen until its player is ready.
+*/
+pub fn update(gs: &mut GameState) {
+    if gs.mode != Mode::HotSeat || gs.run_state != RunState::TurnChange {
+        return;
+    }
+    let Some(k) = next_up(gs) else {
+        return;
+    };
+
+    let mut next = gs.bench.remove(k);
+    next.sounds = std::mem::take(&mut gs.sounds);
+    next.bench = std::mem::take(&mut gs.bench);
+    next.debug = gs.debug;
+    next.run_state = RunState::TurnChange;
+    let done = std::mem::replace(gs, next);
+    gs.bench.push(done);
+}
+
+// every seat's game in seat order, the active one included
+pub fn games(gs: &GameState) -> Vec<&GameState> {
+    let mut games: Vec<&GameState> = gs.bench.iter().chain(Some(gs)).collect();
+    games.sort_by_key(|g| g.seat);
+    games
+}
@@ -1,89 +1,219 @@
-use std::ops::Add;
-
 use super::{
-    audio, audio::GameSound, get_new_game_state, spawner, Bullet, Exhaust, GameState, RunState,
-    ANGLE_STEP, BULLET_VEL, EXHAUST_COOLDOWN, EXHAUST_VEL, PLAYER_ACCL, PLAYER_WIDTH,
-    TURRET_COOLDOWN,
-};
-use macroquad::{
-    audio::{play_sound, PlaySoundParams},
-    prelude::{get_frame_time, get_time, is_key_down, is_key_pressed, rand, vec2, KeyCode},
+    audio, audio::GameSound, get_new_game_state, spawner, stage, versus, wingman, zone, Action,
+    Bullet, GameState, Mode, Owner, RunState, Weapon, ANGLE_STEP, BULLET_VEL, EXHAUST_VEL,
+    PLAYER_ACCL, RAPID_COOLDOWN, SPREAD_ANGLE, TICK, TURRET_COOLDOWN,
 };
+// the keyboard, only there with the gui feature
+#[cfg(feature = "gui")]
+use super::{Bindings, HOTSEAT_PLAYERS, MAX_VERSUS_PLAYERS};
+use glam::vec2;
+#[cfg(feature = "gui")]
+use macroquad::prelude::{is_key_down, is_key_pressed, KeyCode};
+
+#[cfg(feature = "gui")]
+fn any_down(keys: &[KeyCode]) -> bool {
+    keys.iter().any(|k| is_key_down(*k))
+}
+
+// starts over with `players` ships, keeping the loaded sounds and settings
+fn restart(gs: &mut GameState, players: usize, mode: Mode) {
+    let sounds = std::mem::take(&mut gs.sounds);
+    let friendly_fire = gs.friendly_fire;
+    let wingman_enabled = gs.wingman_enabled && mode == Mode::Campaign;
+    let (screen, debug) = (gs.screen, gs.debug);
+    // a stage pack loaded in place of the built in stages stays
+    let stages = (gs.mode == mode).then(|| std::mem::take(&mut gs.stages));
+    *gs = get_new_game_state(players, mode);
+    if let Some(stages) = stages {
+        gs.stages = stages;
+        stage::start(gs, 0);
+    }
+    stage::resize(gs, screen);
+    gs.sounds = sounds;
+    gs.friendly_fire = friendly_fire;
+    gs.debug = debug;
+    if wingman_enabled {
+        wingman::toggle(gs);
+    }
+}
+
+// the keys held right now, as an action for the player with these bindings
+#[cfg(feature = "gui")]
+pub fn read_action(keys: &Bindings) -> Action {
+    Action {
+        left: any_down(keys.left),
+        right: any_down(keys.right),
+        thrust: any_down(keys.thrust),
+        reverse: any_down(keys.reverse),
+        strafe_left: any_down(keys.strafe_left),
+        strafe_right: any_down(keys.strafe_right),
+        fire: any_down(keys.fire),
+        confirm: is_key_down(KeyCode::Enter),
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn local_actions(gs: &GameState) -> Vec<Action> {
+    gs.players
+        .iter()
+        .map(|p| read_action(&p.bindings))
+        .collect()
+}
+
+fn control_ship(gs: &mut GameState, i: usize, action: Action, delta: f32, time: f64) {
+    let ship = &mut gs.players[i].ship;
+    let rotation = ship.angle.to_radians();
+    let sh = ship.h * gs.scl; // ship height
+    ship.strafing = (false, false);
+
+    if action.left {
+        ship.angle = (ship.angle - ANGLE_STEP) % 360.0;
+    }
+    if action.right {
+        ship.angle = (ship.angle + ANGLE_STEP) % 360.0;
+    }
+
+    // ion storms knock the thrusters out, steering still works
+    let thrusters = zone::thrusters_online(ship, time);
+    if thrusters && action.thrust {
+        ship.vel = vec2(
+            ship.vel.x + (PLAYER_ACCL * delta) * rotation.sin(),
+            ship.vel.y - (PLAYER_ACCL * delta) * rotation.cos(),
+        );
+        spawner::exhaust_particles(gs, i, EXHAUST_VEL, rotation, sh);
+    }
+    let ship = &mut gs.players[i].ship;
+    if thrusters && action.reverse {
+        ship.vel = vec2(
+            ship.vel.x - PLAYER_ACCL / 2.0 * delta * rotation.sin(),
+            ship.vel.y + PLAYER_ACCL / 2.0 * delta * rotation.cos(),
+        );
+        spawner::exhaust_particles(gs, i, -EXHAUST_VEL, rotation, -sh / 4.0);
+    }
+    let ship = &mut gs.players[i].ship;
+    if thrusters && action.strafe_left {
+        ship.vel = vec2(
+            ship.vel.x - PLAYER_ACCL / 2.0 * delta * rotation.cos(),
+            ship.vel.y - PLAYER_ACCL / 2.0 * delta * rotation.sin(),
+        );
+        ship.strafing = (false, true);
+    }
+    if thrusters && action.strafe_right {
+        ship.vel = vec2(
+            ship.vel.x + PLAYER_ACCL * delta * rotation.cos(),
+            ship.vel.y + PLAYER_ACCL * delta * rotation.sin(),
+        );
+        ship.strafing = (true, false);
+    }
+    let cooldown = match gs.modifiers.weapon {
+        Weapon::Rapid => RAPID_COOLDOWN,
+        _ => TURRET_COOLDOWN,
+    };
+    if action.fire && time - ship.last_turret_frame > cooldown {
+        ship.last_turret_frame = time;
+        let ship = &gs.players[i].ship;
+        let pos = vec2(
+            ship.pos.x + rotation.sin() * sh / 2.,
+            ship.pos.y - rotation.cos() * sh / 2.,
+        );
+        audio::play_audio(&gs.sounds, GameSound::Shot);
+        let spread: &[f32] = match gs.modifiers.weapon {
+            Weapon::Spread => &[-SPREAD_ANGLE, 0.0, SPREAD_ANGLE],
+            _ => &[0.0],
+        };
+        for offset in spread {
+            let a = rotation + offset.to_radians();
+            gs.players[i].shots += 1;
+            gs.bullets.push(Bullet {
+                pos,
+                created_at: time,
+                vel: vec2(BULLET_VEL * a.sin(), -(BULLET_VEL * a.cos())),
+                collision: false,
+                owner: Owner::Player(i),
+            })
+        }
+    }
+}
 
+// keys outside the game itself, these switch modes and settings
+#[cfg(feature = "gui")]
 pub fn handle_input(gs: &mut GameState) {
-    let delta = get_frame_time();
-    let rotation = gs.player.angle.to_radians();
-    let sh = gs.player.h * gs.scl; // ship height
-    let time = get_time();
-    gs.player.strafing = (false, false);
+    if is_key_pressed(KeyCode::Key1) {
+        restart(gs, 1, Mode::Campaign);
+    }
+    if is_key_pressed(KeyCode::Key2) {
+        restart(gs, 2, Mode::Campaign);
+    }
+    // each press adds a ship to the versus match, wrapping back to two
+    if is_key_pressed(KeyCode::V) {
+        let players = match gs.mode {
+            Mode::Versus if gs.players.len() < MAX_VERSUS_PLAYERS => gs.players.len() + 1,
+            _ => 2,
+        };
+        restart(gs, players, Mode::Versus);
+    }
+    if is_key_pressed(KeyCode::H) {
+        restart(gs, HOTSEAT_PLAYERS, Mode::HotSeat);
+    }
+    if is_key_pressed(KeyCode::F) {
+        gs.friendly_fire = !gs.friendly_fire;
+    }
+    if is_key_pressed(KeyCode::G) {
+        gs.debug = !gs.debug;
+    }
+    // the daily challenge is flown the same way by everyone, without a wingman
+    if is_key_pressed(KeyCode::C) && gs.mode == Mode::Campaign && !gs.daily {
+        wingman::toggle(gs);
+    }
+}
+
+// what the players do this tick, part of the simulation
+pub fn apply_actions(gs: &mut GameState, actions: &[Action]) {
+    let time = gs.time;
+    let pressed = |gs: &GameState, i: usize, held: fn(&Action) -> bool| {
+        held(&actions[i]) && !held(&gs.players[i].last_action)
+    };
+    let fire = |a: &Action| a.fire;
+    let confirm = |a: &Action| a.confirm;
+    let anyone = |gs: &GameState, held| (0..gs.players.len()).any(|i| pressed(gs, i, held));
 
     match gs.run_state {
         RunState::Running | RunState::StageComplete => {
-            if is_key_down(KeyCode::Left) || is_key_down(KeyCode::A) {
-                gs.player.angle = (gs.player.angle - ANGLE_STEP) % 360.0;
-            }
-            if is_key_down(KeyCode::Right) || is_key_down(KeyCode::D) {
-                gs.player.angle = (gs.player.angle + ANGLE_STEP) % 360.0;
+            // downed ships wait for a teammate to revive them
+            for (i, action) in actions.iter().enumerate() {
+                if !gs.players[i].down {
+                    control_ship(gs, i, *action, TICK, time);
+                }
             }
 
-            if is_key_down(KeyCode::Up) || is_key_down(KeyCode::W) {
-                gs.player.vel = vec2(
-                    gs.player.vel.x + (PLAYER_ACCL * delta) * rotation.sin(),
-                    gs.player.vel.y - (PLAYER_ACCL * delta) * rotation.cos(),
-                );
-                spawner::exhaust_particles(gs, EXHAUST_VEL, rotation, sh);
-            }
-            if is_key_down(KeyCode::Down) || is_key_down(KeyCode::S) {
-                gs.player.vel = vec2(
-                    gs.player.vel.x - PLAYER_ACCL / 2.0 * delta * rotation.sin(),
-                    gs.player.vel.y + PLAYER_ACCL / 2.0 * delta * rotation.cos(),
-                );
-                spawner::exhaust_particles(gs, -EXHAUST_VEL, rotation, -sh / 4.0);
-            }
-            if is_key_down(KeyCode::Q) {
-                gs.player.vel = vec2(
-                    gs.player.vel.x - PLAYER_ACCL / 2.0 * delta * rotation.cos(),
-                    gs.player.vel.y - PLAYER_ACCL / 2.0 * delta * rotation.sin(),
-                );
-                gs.player.strafing = (false, true);
-            }
-            if is_key_down(KeyCode::E) {
-                gs.player.vel = vec2(
-                    gs.player.vel.x + PLAYER_ACCL * delta * rotation.cos(),
-                    gs.player.vel.y + PLAYER_ACCL * delta * rotation.sin(),
-                );
-                gs.player.strafing = (true, false);
-            }
-            if is_key_down(KeyCode::Space) && time - gs.player.last_turret_frame > TURRET_COOLDOWN {
-                gs.player.last_turret_frame = time;
-                audio::play_audio(&gs.sounds, GameSound::Shot);
-                gs.bullets.push(Bullet {
-                    pos: vec2(
-                        gs.player.pos.x + rotation.sin() * sh / 2.,
-                        gs.player.pos.y - rotation.cos() * sh / 2.,
-                    ),
-                    created_at: time,
-                    vel: vec2(BULLET_VEL * rotation.sin(), -(BULLET_VEL * rotation.cos())),
-                    collision: false,
-                })
+            if gs.run_state == RunState::StageComplete && anyone(gs, confirm) {
+                if gs.mode == Mode::Versus {
+                    versus::start_round(gs, gs.round + 1);
+                } else if !stage::is_game_over(gs) && stage::is_cleared(gs) && stage::has_next(gs) {
+                    stage::start(gs, gs.stage + 1);
+                } else {
+                    restart(gs, gs.players.len(), gs.mode);
+                }
             }
-
-            if is_key_pressed(KeyCode::G) {
-                gs.debug = !gs.debug;
+        }
+        RunState::TurnChange => {
+            if pressed(gs, 0, fire) {
+                gs.run_state = RunState::Running;
             }
-
-            if gs.run_state == RunState::StageComplete {
-                if is_key_down(KeyCode::Enter) {
-                    *gs = get_new_game_state();
-                }
+        }
+        RunState::MatchSummary => {
+            if anyone(gs, confirm) {
+                restart(gs, gs.players.len(), Mode::Versus);
             }
         }
         RunState::Death => {
-            if is_key_down(KeyCode::Space) {
+            if actions[0].fire {
                 gs.run_state = RunState::Running;
             }
         }
+    }
 
-        _ => {}
+    for (player, action) in gs.players.iter_mut().zip(actions) {
+        player.last_action = *action;
     }
 }
@@ -0,0 +1,620 @@
+use super::{
+    daily::{self, Challenge},
+    scores::{Entry, Table},
+    stage, step, Modifiers, LEADERBOARD_DAILY_DAYS, LEADERBOARD_DEADLINE, LEADERBOARD_KEEP,
+    LEADERBOARD_MAX_TICKS, LEADERBOARD_OUTBOX, LEADERBOARD_PORT, LEADERBOARD_REQUESTS,
+    LEADERBOARD_TIMEOUT, LEADERBOARD_TOP, MAX_MESSAGE_LEN,
+};
+use nanoserde::{DeJson, SerJson};
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/*
+    Scores go to the leaderboard as a POST of the whole Entry to /scores,
+    replay and all, and the best ones come back from a GET of
+    /scores?seed=<seed>&top=<n>. Both are JSON over plain HTTP/1.1, under
+    whatever path the leaderboard URL has.
+*/
+
+// one line of the online high scores
+#[derive(SerJson, DeJson, Clone, PartialEq, Debug)]
+pub struct Row {
+    pub name: String,
+    pub score: i32,
+    pub stage: usize,
+    pub ticks: u32,
+}
+
+#[derive(SerJson, DeJson, Default, Debug)]
+pub struct Top {
+    pub rows: Vec<Row>,
+}
+
+// the answer to an accepted score, `place` counts from 0
+#[derive(SerJson, DeJson, Debug)]
+pub struct Verdict {
+    pub ranked: bool,
+    pub place: usize,
+}
+
+pub struct Config {
+    pub url: String,
+    pub name: String,
+    pub top: usize,
+    pub outbox: String, // scores that couldn't be sent wait here for the next start
+}
+
+const USAGE: &str = "usage: asteroids-rs --leaderboard <http://host:port/path> [--name name] \
+    [--top n]";
+
+impl Config {
+    // None unless the game was started with --leaderboard
+    pub fn from_args() -> Option<Result<Config, String>> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let i = args.iter().position(|a| a == "--leaderboard")?;
+        Some(Self::parse(&args, i))
+    }
+
+    fn parse(args: &[String], leaderboard: usize) -> Result<Config, String> {
+        let value = |i: usize| args.get(i).ok_or_else(|| USAGE.to_string());
+        let url = value(leaderboard + 1)?.clone();
+        Url::parse(&url)?;
+        let mut config = Config {
+            url,
+            name: std::env::var("USER").unwrap_or_else(|_| "anonymous".to_string()),
+            top: LEADERBOARD_TOP,
+            outbox: LEADERBOARD_OUTBOX.to_string(),
+        };
+
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--name" => {
+                    config.name = value(i + 1)?.clone();
+                    i += 1;
+                }
+                "--top" => {
+                    config.top = value(i + 1)?
+                        .parse()
+                        .map_err(|_| "--top needs a number".to_string())?;
+                    i += 1;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        Ok(config)
+    }
+}
+
+// only plain http, there's nothing secret in a high score
+#[derive(Clone)]
+struct Url {
+    host: String,
+    port: u16,
+    path: String, // without the trailing slash
+}
+
+impl Url {
+    fn parse(url: &str) -> Result<Url, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("{}: only http:// leaderboards are supported", url))?;
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("{}: bad port {}", url, port))?,
+            ),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("{}: no host", url));
+        }
+        Ok(Url {
+            host: host.to_string(),
+            port,
+            path: path.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+// a request that gives up after LEADERBOARD_TIMEOUT, the status and body of the response
+fn request(url: &Url, method: &str, target: &str, body: &str) -> Result<(u16, String), String> {
+    let timeout = Duration::from_secs_f32(LEADERBOARD_TIMEOUT);
+    let addr = (url.host.as_str(), url.port)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or_else(|| format!("{} not found", url.host))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|e| e.to_string())?;
+
+    let head = format!(
+        "{} {}{} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\nConnection: close\r\n\r\n",
+        method,
+        url.path,
+        target,
+        url.host,
+        url.port,
+        body.len()
+    );
+    stream
+        .write_all(head.as_bytes())
+        .and_then(|_| stream.write_all(body.as_bytes()))
+        .map_err(|e| e.to_string())?;
+
+    let mut response = Vec::new();
+    stream
+        .take(MAX_MESSAGE_LEN as u64)
+        .read_to_end(&mut response)
+        .map_err(|e| e.to_string())?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("incomplete response")?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or("not an HTTP response")?;
+    let chunked = head
+        .lines()
+        .any(|l| l.eq_ignore_ascii_case("transfer-encoding: chunked"));
+    let body = if chunked {
+        dechunk(body)
+    } else {
+        body.to_string()
+    };
+    Ok((status, body))
+}
+
+// the body of a chunked response, whatever was complete of it
+fn dechunk(mut body: &str) -> String {
+    let mut out = String::new();
+    while let Some((size, rest)) = body.split_once("\r\n") {
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or("").trim(), 16);
+        match size {
+            Ok(size) if size > 0 && size <= rest.len() => {
+                out.push_str(&rest[..size]);
+                body = rest[size..].trim_start_matches("\r\n");
+            }
+            _ => break,
+        }
+    }
+    out
+}
+
+enum Reply {
+    Submitted(Entry, Result<Verdict, Failure>),
+    Top(Result<Vec<Row>, String>),
+}
+
+enum Failure {
+    Offline(String),  // worth trying again later
+    Rejected(String), // the leaderboard didn't believe the score
+}
+
+/*
+    Sends finished runs to an online leaderboard and fetches its best
+    scores. Requests run on threads of their own so the game never waits
+    on the network, poll() picks up what came back. A score that can't be
+    sent is kept in the outbox file and sent again the next time the game
+    starts, it stays there until the leaderboard has taken or rejected it.
+*/
+pub struct Client {
+    config: Config,
+    url: Url,
+    sender: Sender<Reply>,
+    replies: Receiver<Reply>,
+    pub top: Option<Vec<Row>>,  // the last list fetched
+    pub status: Option<String>, // how the last score went
+}
+
+impl Client {
+    pub fn new(config: Config) -> Result<Client, String> {
+        let url = Url::parse(&config.url)?;
+        let (sender, replies) = mpsc::channel();
+        let client = Client {
+            config,
+            url,
+            sender,
+            replies,
+            top: None,
+            status: None,
+        };
+        for entry in Table::load(&client.config.outbox).entries {
+            client.send(entry);
+        }
+        Ok(client)
+    }
+
+    pub fn submit(&mut self, mut entry: Entry) {
+        if entry.replay.is_empty() {
+            self.status = Some("This run can't be verified".to_string());
+            return;
+        }
+        entry.name = self.config.name.clone();
+        self.status = Some("Sending score".to_string());
+        self.send(entry);
+    }
+
+    fn send(&self, entry: Entry) {
+        let body = entry.serialize_json();
+        let url = self.url.clone();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let verdict = match request(&url, "POST", "/scores", &body) {
+                Ok((200, body)) => DeJson::deserialize_json(&body)
+                    .map_err(|e| Failure::Offline(format!("bad answer: {}", e))),
+                Ok((422, reason)) => Err(Failure::Rejected(reason)),
+                Ok((status, _)) => Err(Failure::Offline(format!("status {}", status))),
+                Err(e) => Err(Failure::Offline(e)),
+            };
+            let _ = sender.send(Reply::Submitted(entry, verdict));
+        });
+    }
+
+    pub fn fetch(&self, seed: u64) {
+        let url = self.url.clone();
+        let target = format!("/scores?seed={}&top={}", seed, self.config.top);
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let top = match request(&url, "GET", &target, "") {
+                Ok((200, body)) => Top::deserialize_json(&body)
+                    .map(|t| t.rows)
+                    .map_err(|e| e.to_string()),
+                Ok((status, _)) => Err(format!("status {}", status)),
+                Err(e) => Err(e),
+            };
+            let _ = sender.send(Reply::Top(top));
+        });
+    }
+
+    // takes in whatever the leaderboard has answered so far
+    pub fn poll(&mut self) {
+        while let Ok(reply) = self.replies.try_recv() {
+            // answered either way, it doesn't need sending again
+            if let Reply::Submitted(entry, Ok(_) | Err(Failure::Rejected(_))) = &reply {
+                let mut outbox = Table::load(&self.config.outbox);
+                if let Some(i) = outbox.entries.iter().position(|e| e == entry) {
+                    outbox.entries.remove(i);
+                    self.save_outbox(&outbox);
+                }
+            }
+            match reply {
+                Reply::Submitted(_, Ok(verdict)) if verdict.ranked => {
+                    self.status = Some(format!("Online place {}", verdict.place + 1));
+                }
+                Reply::Submitted(_, Ok(_)) => {
+                    self.status = Some("Score sent".to_string());
+                }
+                Reply::Submitted(_, Err(Failure::Rejected(reason))) => {
+                    self.status = Some(format!("Score rejected: {}", reason));
+                }
+                Reply::Submitted(entry, Err(Failure::Offline(e))) => {
+                    eprintln!("{}: {}", self.config.url, e);
+                    // one sent again from the outbox is still in it
+                    let mut outbox = Table::load(&self.config.outbox);
+                    if !outbox.entries.contains(&entry) {
+                        outbox.entries.push(entry);
+                        self.save_outbox(&outbox);
+                    }
+                    self.status = Some("Leaderboard offline, score saved".to_string());
+                }
+                Reply::Top(Ok(rows)) => self.top = Some(rows),
+                Reply::Top(Err(e)) => eprintln!("{}: {}", self.config.url, e),
+            }
+        }
+    }
+
+    fn save_outbox(&self, outbox: &Table) {
+        if let Err(e) = outbox.save(&self.config.outbox) {
+            eprintln!("could not save {}: {}", self.config.outbox, e);
+        }
+    }
+}
+
+pub struct ServerConfig {
+    pub port: u16,
+    pub file: String,
+    pub keep: usize,
+}
+
+const SERVER_USAGE: &str = "usage: leaderboard [--port n] [--file scores.json] [--keep n]";
+
+impl ServerConfig {
+    pub fn parse(args: &[String]) -> Result<ServerConfig, String> {
+        let mut config = ServerConfig {
+            port: LEADERBOARD_PORT,
+            file: "leaderboard.json".to_string(),
+            keep: LEADERBOARD_KEEP,
+        };
+
+        let mut i = 0;
+        while i < args.len() {
+            let value = args.get(i + 1).ok_or_else(|| SERVER_USAGE.to_string())?;
+            match args[i].as_str() {
+                "--port" => {
+                    config.port = value.parse().map_err(|_| format!("bad port: {}", value))?
+                }
+                "--file" => config.file = value.clone(),
+                "--keep" => {
+                    config.keep = value
+                        .parse()
+                        .map_err(|_| "--keep needs a number".to_string())?
+                }
+                _ => return Err(SERVER_USAGE.to_string()),
+            }
+            i += 2;
+        }
+        if config.keep == 0 {
+            return Err("--keep can't be 0".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/*
+    The rules a run on `seed` has to be played by. The daily challenges of
+    the last few days have their own, a day ahead too for clocks running
+    fast, and every other seed is the plain campaign.
+*/
+fn rules(seed: u64) -> Modifiers {
+    let today = daily::today();
+    (today.saturating_sub(LEADERBOARD_DAILY_DAYS)..=today + 1)
+        .map(Challenge::for_day)
+        .find(|c| c.seed == seed)
+        .map_or(Modifiers::default(), |c| c.modifiers)
+}
+
+// why verify() turned a score down
+pub enum Refusal {
+    Invalid(String), // the run can't be ranked or wasn't played
+    TooSlow,         // the deadline passed before the replay was checked
+}
+
+/*
+    Replays a submitted run from the start and checks it ends the way the
+    entry says. The simulation is deterministic, so a score that doesn't
+    come out of its own replay wasn't played. Gives up once `deadline`
+    has passed, which says nothing about the run itself.
+*/
+pub fn verify(entry: &Entry, deadline: Instant) -> Result<(), Refusal> {
+    let replay = &entry.replay;
+    if replay.len() as u32 > LEADERBOARD_MAX_TICKS {
+        return Err(Refusal::Invalid("the run is too long".to_string()));
+    }
+    let [w, h] = replay.screen;
+    if !(100.0..=10000.0).contains(&w) || !(100.0..=10000.0).contains(&h) {
+        return Err(Refusal::Invalid("odd screen size".to_string()));
+    }
+    if replay.wingman {
+        return Err(Refusal::Invalid(
+            "runs with the wingman aren't ranked".to_string(),
+        ));
+    }
+    if replay.modifiers != rules(replay.seed) {
+        return Err(Refusal::Invalid(
+            "the rules don't match the seed".to_string(),
+        ));
+    }
+    let mut gs = replay.start();
+    for tick in 0..replay.len() {
+        let Some(action) = replay.action(tick) else {
+            break;
+        };
+        step(&mut gs, &[action]);
+        if tick % 600 == 0 && Instant::now() >= deadline {
+            return Err(Refusal::TooSlow);
+        }
+    }
+    if !stage::is_run_over(&gs) {
+        return Err(Refusal::Invalid("the run doesn't end".to_string()));
+    }
+    if gs.players[0].score != entry.score || gs.stage != entry.stage || gs.tick != entry.ticks {
+        return Err(Refusal::Invalid(
+            "the replay doesn't match the score".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/*
+    A leaderboard to run locally or for a team, it keeps the best `keep`
+    runs for every seed in one file. Every request is answered on a thread
+    of its own, so a long replay or a slow client doesn't hold up the
+    others, and every score only after its replay checked out.
+*/
+pub struct Server {
+    config: ServerConfig,
+    listener: TcpListener,
+    pub table: Mutex<Table>,
+    busy: AtomicUsize, // requests being answered
+}
+
+impl Server {
+    pub fn bind(config: ServerConfig) -> io::Result<Server> {
+        let listener = TcpListener::bind(("0.0.0.0", config.port))?;
+        Ok(Server {
+            table: Mutex::new(Table::load(&config.file)),
+            config,
+            listener,
+            busy: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(self) {
+        let server = Arc::new(self);
+        loop {
+            let Ok((stream, _)) = server.listener.accept() else {
+                continue;
+            };
+            // dropped unanswered when too busy, clients keep the score for later
+            if server.busy.load(Ordering::SeqCst) >= LEADERBOARD_REQUESTS {
+                continue;
+            }
+            server.busy.fetch_add(1, Ordering::SeqCst);
+            let server = server.clone();
+            thread::spawn(move || {
+                if let Err(e) = server.handle(stream) {
+                    eprintln!("{}", e);
+                }
+                server.busy.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    }
+
+    /*
+        Answers one request on its own connection. Each read and write
+        gives up after LEADERBOARD_TIMEOUT and the whole request, replay
+        included, after LEADERBOARD_DEADLINE.
+    */
+    pub fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let deadline = Instant::now() + Duration::from_secs_f32(LEADERBOARD_DEADLINE);
+        let timeout = |stream: &TcpStream| {
+            let left = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|left| !left.is_zero())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "request too slow"))?;
+            let timeout = Some(left.min(Duration::from_secs_f32(LEADERBOARD_TIMEOUT)));
+            stream.set_read_timeout(timeout)?;
+            stream.set_write_timeout(timeout)
+        };
+
+        let mut data = Vec::new();
+        let mut buf = [0; 4096];
+        let (head, body) = loop {
+            timeout(&stream)?;
+            let n = stream.read(&mut buf)?;
+            data.extend(&buf[..n]);
+            let text = String::from_utf8_lossy(&data);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .filter_map(|l| l.split_once(':'))
+                    .find(|(k, _)| k.trim().eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, v)| v.trim().parse().ok())
+                    .unwrap_or(0);
+                if body.len() >= length || n == 0 {
+                    break (head.to_string(), body.to_string());
+                }
+            }
+            if n == 0 || data.len() > MAX_MESSAGE_LEN {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "bad request"));
+            }
+        };
+
+        let mut request = head.split_whitespace();
+        let method = request.next().unwrap_or("");
+        let target = request.next().unwrap_or("");
+        let (status, body) = self.respond(method, target, &body, deadline);
+        let reason = match status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            503 => "Service Unavailable",
+            _ => "Unprocessable Entity",
+        };
+        // only answers are JSON, anything else is the reason it went wrong
+        let content = match status {
+            200 => "application/json",
+            _ => "text/plain",
+        };
+        timeout(&stream)?;
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+            Connection: close\r\n\r\n{}",
+            status,
+            reason,
+            content,
+            body.len(),
+            body
+        )
+    }
+
+    // the status and body answering a request, anything under a path is fine
+    pub fn respond(
+        &self,
+        method: &str,
+        target: &str,
+        body: &str,
+        deadline: Instant,
+    ) -> (u16, String) {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if !path.ends_with("/scores") {
+            return (404, "no such thing".to_string());
+        }
+        let param = |name: &str| {
+            query
+                .split('&')
+                .filter_map(|p| p.split_once('='))
+                .find(|(k, _)| *k == name)
+                .and_then(|(_, v)| v.parse().ok())
+        };
+        match method {
+            "GET" => {
+                let seed = param("seed").unwrap_or(0);
+                let top =
+                    (param("top").unwrap_or(LEADERBOARD_TOP as u64) as usize).min(self.config.keep);
+                let table = self.table.lock().unwrap();
+                let rows = table
+                    .for_seed(seed)
+                    .take(top)
+                    .map(|e| Row {
+                        name: e.name.clone(),
+                        score: e.score,
+                        stage: e.stage,
+                        ticks: e.ticks,
+                    })
+                    .collect();
+                (200, Top { rows }.serialize_json())
+            }
+            "POST" => {
+                let Ok(entry) = Entry::deserialize_json(body) else {
+                    return (400, "not a score".to_string());
+                };
+                // a replay not checked in time is worth sending again later
+                match verify(&entry, deadline) {
+                    Ok(()) => {}
+                    Err(Refusal::Invalid(reason)) => return (422, reason),
+                    Err(Refusal::TooSlow) => {
+                        return (503, "the replay took too long to check".to_string())
+                    }
+                }
+                let mut table = self.table.lock().unwrap();
+                let place = table.insert_within(entry, self.config.keep);
+                if place.is_some() {
+                    if let Err(e) = table.save(&self.config.file) {
+                        eprintln!("could not save {}: {}", self.config.file, e);
+                    }
+                }
+                let verdict = Verdict {
+                    ranked: place.is_some(),
+                    place: place.unwrap_or(self.config.keep),
+                };
+                (200, verdict.serialize_json())
+            }
+            _ => (404, "no such thing".to_string()),
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use glam::{vec2, Vec2};
+pub mod audio;
+pub mod autopilot;
+pub mod boss;
+pub mod comet;
+pub mod components;
+pub mod coop;
+pub mod daily;
+use components::{Material, *};
+#[cfg(feature = "gui")]
+pub mod draw;
+pub mod evolve;
+pub mod fracture;
+pub mod ghost;
+pub mod gravity;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod gym;
+#[cfg(not(feature = "gui"))]
+pub mod headless;
+pub mod hotseat;
+#[cfg(feature = "gui")]
+use draw::*;
+pub mod input;
+pub mod leaderboard;
+pub mod net;
+pub mod quicksave;
+pub mod replay;
+pub mod rollback;
+pub mod scores;
+pub mod server;
+pub mod spawner;
+pub mod speedrun;
+pub mod stage;
+pub mod stream;
+pub mod swarm;
+use input::*;
+pub mod utils;
+pub mod versus;
+pub mod wingman;
+use utils::*;
+pub mod zone;
+
+/*
+    Alone the game pauses until the player is ready again. In co-op the
+    ship goes down where it was hit and the game carries on, see coop.rs.
+    In versus it just respawns, lives don't run out. In hot-seat the turn
+    passes to the next player, see hotseat.rs.
+*/
+fn kill_player(gs: &mut GameState, i: usize) {
+    if gs.mode == Mode::Versus {
+        if versus::is_invulnerable(&gs.players[i], gs.time) {
+            return;
+        }
+        audio::play_audio(&gs.sounds, audio::GameSound::Death);
+        let pos = gs.players[i].ship.pos;
+        gs.explosions.push(Explosion::new(
+            pos.x - gs.scl,
+            pos.y - gs.scl,
+            gs.scl * 2.0,
+            2.0,
+            gs.time,
+        ));
+        gs.players[i].deaths += 1;
+        versus::respawn(gs, i);
+        return;
+    }
+
+    audio::play_audio(&gs.sounds, audio::GameSound::Death);
+    gs.players[i].lives -= 1;
+    if gs.mode == Mode::HotSeat {
+        hotseat::end_turn(gs);
+        return;
+    }
+    if gs.players.len() == 1 {
+        if gs.players[i].lives > 0 {
+            stage::respawn(gs, i);
+            gs.run_state = RunState::Death;
+        } else {
+            gs.run_state = RunState::StageComplete;
+        }
+        return;
+    }
+
+    gs.players[i].down = true;
+    gs.players[i].ship.vel = Vec2::ZERO;
+    if stage::is_game_over(gs) {
+        gs.run_state = RunState::StageComplete;
+    }
+}
+
+// position of the closest ship that is still flying
+fn nearest_ship(gs: &GameState, pos: Vec2) -> Option<Vec2> {
+    gs.players
+        .iter()
+        .filter(|p| !p.down)
+        .map(|p| p.ship.pos)
+        .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos)))
+}
+
+fn score_kill(gs: &mut GameState, i: usize, pos: Vec2, vel: Vec2, size: f32, material: Material) {
+    let player = &mut gs.players[i];
+    player.combo_time = COMBO_TIMER;
+    player.combo += 1;
+    if player.combo % 5 == 0 {
+        player.score_multiplier += 1;
+    }
+
+    let mut collision_score = SCORE_BASE * size as i32 * player.score_multiplier;
+    if material == Material::Crystal {
+        collision_score *= CRYSTAL_SCORE_BONUS;
+    }
+    player.score += collision_score;
+
+    gs.flying_points.push(FlyingPoint {
+        created_at: gs.time,
+        pos,
+        vel,
+        val: collision_score,
+    });
+}
+
+/*
+    Advances the game by one fixed tick with the players' actions for it.
+    Everything that changes the game goes through here so that the same
+    actions from the same state always give the same result.
+*/
+pub fn step(gs: &mut GameState, actions: &[Action]) {
+    gs.tick += 1;
+    gs.time += TICK as f64;
+    apply_actions(gs, actions);
+    update(gs);
+}
+
+fn update(gs: &mut GameState) {
+    let delta = TICK;
+    let time = gs.time;
+    let screen = gs.screen;
+    match gs.run_state {
+        RunState::Running | RunState::Death | RunState::StageComplete => {
+            if gs.run_state == RunState::Running {
+                gs.play_time += delta;
+                for player in gs.players.iter_mut() {
+                    if player.combo_time > 0.0 {
+                        player.combo_time = f32::max(player.combo_time - delta, 0.0);
+                    } else {
+                        player.combo = 0;
+                        player.score_multiplier = 1;
+                    }
+                }
+            }
+
+            // apply space friction, orbits would decay with it
+            let orbit = stage::is_orbit(gs);
+            let frictions: Vec<f32> = gs
+                .players
+                .iter()
+                .map(|p| zone::friction(gs, p.ship.pos))
+                .collect();
+            for (player, frict) in gs.players.iter_mut().zip(frictions) {
+                if player.down {
+                    continue;
+                }
+                let ship = &mut player.ship;
+                ship.pos = ship.pos + ship.vel;
+                let mut new_vel = ship.vel;
+                if ship.vel.x > 0.0 {
+                    new_vel.x = (ship.vel.x - frict * delta).clamp(0.0, ship.vel.x);
+                } else {
+                    new_vel.x = (ship.vel.x + frict * delta).clamp(ship.vel.x, 0.0);
+                };
+                if ship.vel.y > 0.0 {
+                    new_vel.y = (ship.vel.y - frict * delta).clamp(0.0, ship.vel.y);
+                } else {
+                    new_vel.y = (ship.vel.y + frict * delta).clamp(ship.vel.y, 0.0);
+                };
+                if !orbit {
+                    ship.vel = new_vel;
+                }
+            }
+
+            gravity::update(gs, delta);
+            zone::update(gs, delta, time);
+
+            // update asteroids
+            for asteroid in gs.asteroids.iter_mut() {
+                asteroid.pos = wrap(
+                    asteroid.pos + (asteroid.vel * delta),
+                    asteroid.w,
+                    asteroid.w,
+                    screen,
+                );
+                asteroid.angle = (asteroid.angle + asteroid.spin) % 360.0;
+            }
+
+            // check for collisions with players
+            if gs.run_state == RunState::Running {
+                let hit: Vec<usize> = (0..gs.players.len())
+                    .filter(|i| !gs.players[*i].down)
+                    .filter(|i| {
+                        let ship = gs.players[*i].ship.points(gs.scl);
+                        gs.asteroids
+                            .iter()
+                            .any(|a| touches_ship(&a.points(), &ship))
+                    })
+                    .collect();
+                for i in hit {
+                    kill_player(gs, i);
+                }
+            }
+
+            //update particles
+            for ex in gs.exhaust.iter_mut() {
+                ex.pos += ex.vel * delta;
+                ex.size = f32::max(ex.size - 0.5 * delta, 0.0);
+            }
+            gs.exhaust
+                .retain(|e| time - e.created_at < EXHAUST_LIVE_TIME || e.size <= 0.0);
+
+            for d in gs.debris.iter_mut() {
+                d.pos += d.vel * delta;
+                d.angle = (d.angle + d.spin) % 360.0;
+            }
+            gs.debris.retain(|d| time - d.created_at < DEBRIS_LIVE_TIME);
+
+            for point in gs.flying_points.iter_mut() {
+                point.vel += GRAVITY;
+                point.pos += point.vel * delta;
+            }
+
+            // update bullets
+            let mut kills = Vec::new();
+            for bullet in gs.bullets.iter_mut() {
+                let a = bullet.pos;
+                bullet.pos = wrap(
+                    bullet.pos + (bullet.vel * delta),
+                    BULLET_WIDTH,
+                    BULLET_WIDTH,
+                    screen,
+                );
+                let b = bullet.pos;
+
+                // check for collisions
+                for ast in gs.asteroids.iter_mut().filter(|a| !a.collision) {
+                    let p = ast.points();
+                    let mut hit = None;
+                    let mut normal = None;
+                    for i in 0..p.len() {
+                        let (c, d) = (p[i], p[(i + 1) % p.len()]);
+                        if let Some(pos) = intersection(a, b, c, d) {
+                            hit = Some(pos);
+                            normal = Some(vec2(d.y - c.y, c.x - d.x).normalize_or_zero());
+                            break;
+                        }
+                    }
+                    if hit.is_none() && contains(&p, b) {
+                        hit = Some(b);
+                    }
+                    let Some(pos) = hit else {
+                        continue;
+                    };
+
+                    ast.hits -= 1;
+                    if ast.hits > 0 {
+                        // metallic plating deflects the bullet off the struck edge
+                        match normal {
+                            Some(n) => {
+                                bullet.vel -= n * 2.0 * bullet.vel.dot(n);
+                                bullet.pos =
+                                    pos + bullet.vel.normalize_or_zero() * BULLET_WIDTH * gs.scl;
+                            }
+                            None => bullet.collision = true,
+                        }
+                        break;
+                    }
+
+                    bullet.collision = true;
+                    ast.collision = true;
+                    ast.impact = Some(Impact {
+                        pos,
+                        dir: bullet.vel.normalize_or_zero(),
+                        owner: bullet.owner.player(),
+                    });
+                    kills.push((
+                        bullet.owner.player(),
+                        pos,
+                        bullet.vel / 2.0,
+                        ast.size,
+                        ast.material,
+                    ));
+                    break;
+                }
+            }
+            boss::update(gs, delta, time);
+            comet::update(gs, delta, time);
+            swarm::update(gs, delta);
+            wingman::update(gs, delta, time);
+            coop::update(gs, delta);
+            versus::update(gs);
+            for bullet in gs.bullets.iter().filter(|b| b.collision) {
+                if let Owner::Player(i) = bullet.owner {
+                    if let Some(player) = gs.players.get_mut(i) {
+                        player.hits += 1;
+                    }
+                }
+            }
+            gs.bullets
+                .retain(|b| time - b.created_at < BULLET_LIVE_TIME && !b.collision);
+
+            gs.flying_points
+                .retain(|f| time - f.created_at < FLYING_POINT_LIVE_TIME);
+
+            let mut new_asteroids = Vec::new();
+            let mut blasts = Vec::new();
+            gs.asteroids.retain(|a| {
+                if a.collision {
+                    if a.material == Material::Explosive {
+                        let r = a.w * EXPLOSIVE_BLAST_RADIUS;
+                        blasts.push((a.pos, r, a.impact.as_ref().map_or(0, |i| i.owner)));
+                        gs.explosions.push(Explosion::new(
+                            a.pos.x - r,
+                            a.pos.y - r,
+                            r * 2.0,
+                            ASTEROID_MAX_SIZE,
+                            time,
+                        ));
+                    }
+
+                    gs.explosions.push(Explosion::new(
+                        a.pos.x - a.w / 2.0,
+                        a.pos.y - a.w / 2.0,
+                        a.w * 0.75,
+                        a.size,
+                        time,
+                    ));
+
+                    match a.size as usize {
+                        3 => audio::play_audio(&gs.sounds, audio::GameSound::ExplosionLarge),
+                        2 => audio::play_audio(&gs.sounds, audio::GameSound::ExplosionMedium),
+                        1 => audio::play_audio(&gs.sounds, audio::GameSound::ExplosionSmall),
+                        _ => {}
+                    }
+
+                    let stage = &gs.stages[gs.stage];
+                    let (mut children, mut debris) =
+                        fracture::fracture(a, stage, gs.scl, time, &mut gs.rng);
+                    new_asteroids.append(&mut children);
+                    gs.debris.append(&mut debris);
+                }
+
+                !a.collision
+            });
+
+            // explosive asteroids damage everything inside the blast, the
+            // destroyed ones go off next frame which lets explosions chain
+            for (center, radius, owner) in blasts {
+                for ast in gs.asteroids.iter_mut().filter(|a| !a.collision) {
+                    if ast.pos.distance(center) - ast.w / 2.0 < radius {
+                        ast.hits -= 1;
+                        if ast.hits <= 0 {
+                            ast.collision = true;
+                            ast.impact = Some(Impact {
+                                pos: center,
+                                dir: (ast.pos - center).normalize_or_zero(),
+                                owner,
+                            });
+                            kills.push((owner, ast.pos, ast.vel, ast.size, ast.material));
+                        }
+                    }
+                }
+                for i in 0..gs.players.len() {
+                    let player = &gs.players[i];
+                    if gs.run_state == RunState::Running
+                        && !player.down
+                        && player.ship.pos.distance(center) < radius
+                    {
+                        kill_player(gs, i);
+                    }
+                }
+            }
+            for (owner, pos, vel, size, material) in kills {
+                score_kill(gs, owner, pos, vel, size, material);
+            }
+
+            if new_asteroids.len() > 0 {
+                gs.asteroids.append(&mut new_asteroids);
+            }
+
+            gs.explosions
+                .retain(|e| time - e.created_at < EXPLOSION_LIVE_TIME);
+
+            if gs.run_state == RunState::Running {
+                stage::spawn_due_groups(gs);
+            }
+            if gs.mode != Mode::Versus && stage::is_cleared(gs) {
+                gs.run_state = RunState::StageComplete;
+            }
+
+            // handle player bounds
+            for player in gs.players.iter_mut() {
+                let ship = &mut player.ship;
+                ship.pos = wrap(ship.pos, ship.w, ship.h, screen);
+            }
+            hotseat::update(gs);
+        }
+        _ => {}
+    }
+}
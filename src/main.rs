@@ -1,205 +1,412 @@
-use macroquad::{audio::load_sound, prelude::*};
-mod components;
-use components::*;
-mod draw;
-mod gui;
-use draw::*;
-mod input;
-mod spawner;
-use input::*;
-mod utils;
-use utils::*;
-mod audio;
-
-fn update(gs: &mut GameState) {
-    let delta = get_frame_time();
-    let time = get_time();
-    match gs.run_state {
-        RunState::Running | RunState::Death | RunState::StageComplete => {
-            if gs.run_state == RunState::Running {
-                gs.play_time += delta;
-                if gs.combo_time > 0.0 {
-                    gs.combo_time = f32::max(gs.combo_time - delta, 0.0);
-                } else {
-                    gs.combo = 0;
-                    gs.score_multiplier = 1;
+use asteroids_rs::{
+    audio, autopilot,
+    components::*,
+    daily::Daily,
+    draw::{draw, draw_ghost},
+    evolve::{self, Genome},
+    ghost::Race,
+    gui, gym,
+    input::*,
+    leaderboard::{self, Client},
+    net, quicksave, rollback,
+    speedrun::Timer,
+    stage, step,
+    stream::{self, Publisher},
+};
+use macroquad::prelude::*;
+use std::{
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/*
+    The title screen, any key starts the game and is returned. Left alone
+    for ATTRACT_IDLE seconds the autopilot plays a silent demo on a random
+    seed behind it until it loses, ATTRACT_TIME runs out or someone presses
+    a key. With an online leaderboard its best scores are shown instead of
+    the local ones.
+*/
+async fn title(scores: &[i32], daily: &[String], mut online: Option<&mut Client>) -> KeyCode {
+    let mut idle = 0.0;
+    let mut demo: Option<GameState> = None;
+    let mut lag = 0.0;
+    let mut rows: Vec<(String, i32)> = scores.iter().map(|s| (String::new(), *s)).collect();
+    loop {
+        if let Some(key) = get_last_key_pressed() {
+            return key;
+        }
+        // the online scores take over once they arrive
+        if let Some(client) = online.as_deref_mut() {
+            client.poll();
+            if let Some(top) = &client.top {
+                rows = top.iter().map(|r| (r.name.clone(), r.score)).collect();
+            }
+        }
+        let screen = vec2(screen_width(), screen_height());
+        match &mut demo {
+            Some(gs) if stage::is_game_over(gs) || gs.time > ATTRACT_TIME as f64 => {
+                demo = None;
+                idle = 0.0;
+                clear_background(DARK);
+            }
+            Some(gs) => {
+                stage::resize(gs, screen);
+                lag += get_frame_time();
+                let mut ticks = 0;
+                while lag >= TICK && ticks < MAX_TICKS_PER_FRAME {
+                    step(gs, &[autopilot::act(gs, 0)]);
+                    lag -= TICK;
+                    ticks += 1;
                 }
+                if ticks == MAX_TICKS_PER_FRAME {
+                    lag = 0.0;
+                }
+                draw(gs);
             }
-
-            gs.player.pos = gs.player.pos + gs.player.vel;
-            // apply space friction
-            let mut new_vel = gs.player.vel;
-            if gs.player.vel.x > 0.0 {
-                new_vel.x = clamp(gs.player.vel.x - (FRICT * delta), 0.0, gs.player.vel.x);
-            } else {
-                new_vel.x = clamp(gs.player.vel.x + (FRICT * delta), gs.player.vel.x, 0.0);
-            };
-            if gs.player.vel.y > 0.0 {
-                new_vel.y = clamp(gs.player.vel.y - (FRICT * delta), 0.0, gs.player.vel.y);
-            } else {
-                new_vel.y = clamp(gs.player.vel.y + (FRICT * delta), gs.player.vel.y, 0.0);
-            };
-            gs.player.vel = new_vel;
-
-            // update asteroids
-            let mut player_collision = false;
-            for asteroid in gs.asteroids.iter_mut() {
-                asteroid.pos = wrap(
-                    asteroid.pos + (asteroid.vel * delta),
-                    asteroid.w,
-                    asteroid.w,
-                );
-                asteroid.angle = (asteroid.angle + 1.5 / asteroid.size) % 360.0;
-
-                // check for collisions with player
-                if gs.run_state == RunState::Running {
-                    let p1 = gs.player.points(gs.scl);
-                    let p2 = asteroid.points();
-                    for i in 0..3 {
-                        let a = p1[i];
-                        let b = p1[(i + 1) % 3];
-                        for j in 0..p2.len() {
-                            if intersects(a, b, p2[j], p2[(j + 1) % p2.len()]) {
-                                player_collision = true;
-                                break;
-                            }
-                        }
-                        if player_collision {
-                            audio::play_audio(&gs.sounds, audio::GameSound::Death);
-                            gs.lives -= 1;
-                            if gs.lives > 0 {
-                                gs.player.reset();
-                                gs.run_state = RunState::Death;
-                            } else {
-                                gs.run_state = RunState::StageComplete;
-                            }
-                            break;
-                        }
-                    }
+            None => {
+                idle += get_frame_time();
+                if idle > ATTRACT_IDLE {
+                    let mut gs = get_new_game_state(1, Mode::Campaign);
+                    gs.seed = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_nanos() as u64);
+                    stage::start(&mut gs, 0);
+                    demo = Some(gs);
+                    lag = 0.0;
                 }
+                clear_background(DARK);
             }
+        }
+        gui::draw_title(demo.is_some(), &rows, daily);
 
-            //update particles
-            for ex in gs.exhaust.iter_mut() {
-                ex.pos += ex.vel * delta;
-                ex.size = f32::max(ex.size - 0.5 * delta, 0.0);
-            }
-            gs.exhaust
-                .retain(|e| time - e.created_at < EXHAUST_LIVE_TIME || e.size <= 0.0);
+        next_frame().await
+    }
+}
 
-            for point in gs.flying_points.iter_mut() {
-                point.vel += GRAVITY;
-                point.pos += point.vel * delta;
-            }
+/*
+    An online game against one peer, see rollback.rs. Both sides play at
+    the same fixed screen size and only the keys that fly the ship work.
+*/
+async fn play_online(mut gs: GameState, config: net::Config, mut publisher: Option<Publisher>) {
+    let link = match net::Link::bind(&config) {
+        Ok(link) => link,
+        Err(e) => {
+            eprintln!("could not open port {}: {}", config.port, e);
+            return;
+        }
+    };
+    let mut session = rollback::Session::new(config.player, link);
+    while !session.connect(config.mode) {
+        clear_background(DARK);
+        gui::draw_net_status(&format!("Waiting for {}", config.peer), false);
+        next_frame().await;
+    }
 
-            // update bullets
-            for bullet in gs.bullets.iter_mut() {
-                let a = bullet.pos;
-                bullet.pos = wrap(
-                    bullet.pos + (bullet.vel * delta),
-                    BULLET_WIDTH,
-                    BULLET_WIDTH,
-                );
-                let b = bullet.pos;
-
-                // check for collisions
-                for ast in gs.asteroids.iter_mut() {
-                    let p = ast.points();
-                    for i in 0..p.len() {
-                        if intersects(a, b, p[i], p[(i + 1) % p.len()]) {
-                            bullet.collision = true;
-                            break;
-                        }
-                    }
-
-                    if bullet.collision {
-                        gs.combo_time = COMBO_TIMER;
-                        gs.combo += 1;
-                        if gs.combo % 5 == 0 {
-                            gs.score_multiplier += 1;
-                        }
-
-                        let collision_score = SCORE_BASE * ast.size as i32 * gs.score_multiplier;
-                        gs.score += collision_score;
-                        ast.collision = true;
-
-                        gs.flying_points.push(FlyingPoint {
-                            created_at: time,
-                            pos: bullet.pos,
-                            vel: bullet.vel / 2.0,
-                            val: collision_score,
-                        });
-                        break;
-                    }
+    let sounds = std::mem::take(&mut gs.sounds);
+    gs = get_new_game_state(2, config.mode);
+    gs.sounds = sounds;
+
+    let mut lag = 0.0;
+    loop {
+        if is_key_pressed(KeyCode::G) {
+            gs.debug = !gs.debug;
+        }
+        lag += get_frame_time();
+        let mut ticks = 0;
+        while lag >= TICK && ticks < MAX_TICKS_PER_FRAME {
+            if session.advance(&mut gs, read_action(&SOLO_BINDINGS)) {
+                if let Some(publisher) = &mut publisher {
+                    publisher.publish(&gs);
                 }
             }
-            gs.bullets
-                .retain(|b| time - b.created_at < BULLET_LIVE_TIME && !b.collision);
-
-            gs.flying_points
-                .retain(|f| time - f.created_at < FLYING_POINT_LIVE_TIME);
-
-            let mut new_asteroids = Vec::new();
-            gs.asteroids.retain(|a| {
-                if a.collision {
-                    gs.explosions.push(Explosion::new(
-                        a.pos.x - a.w / 2.0,
-                        a.pos.y - a.w / 2.0,
-                        a.w * 0.75,
-                        a.size,
-                    ));
-
-                    match a.size as usize {
-                        3 => audio::play_audio(&gs.sounds, audio::GameSound::ExplosionLarge),
-                        2 => audio::play_audio(&gs.sounds, audio::GameSound::ExplosionMedium),
-                        1 => audio::play_audio(&gs.sounds, audio::GameSound::ExplosionSmall),
-                        _ => {}
-                    }
-
-                    if a.size > 1.0 {
-                        new_asteroids.append(&mut spawner::asteroids(
-                            a.pos,
-                            a.w / 4.0,
-                            a.size as i32,
-                            a.size - 1.0,
-                            gs.scl,
-                        ));
-                    }
-                }
+            lag -= TICK;
+            ticks += 1;
+        }
+        if ticks == MAX_TICKS_PER_FRAME {
+            lag = 0.0;
+        }
+        draw(&gs);
+        gui::draw_net_status(&session.status(&gs), session.desync.is_some());
+
+        next_frame().await
+    }
+}
 
-                !a.collision
-            });
-            if new_asteroids.len() > 0 {
-                gs.asteroids.append(&mut new_asteroids);
+/*
+    Shows a game published with --stream somewhere else, see stream.rs.
+    The window takes on the size of the one being watched.
+*/
+async fn watch(addr: SocketAddr) {
+    let mut spectator = match stream::Spectator::connect(addr) {
+        Ok(spectator) => spectator,
+        Err(e) => {
+            eprintln!("could not connect to {}: {}", addr, e);
+            return;
+        }
+    };
+    let mut screen = vec2(SCREEN_WIDTH, SCREEN_HEIGHT);
+    loop {
+        let live = spectator.update();
+        match &spectator.game {
+            Some(gs) => {
+                if gs.screen != screen {
+                    screen = gs.screen;
+                    request_new_screen_size(screen.x, screen.y);
+                }
+                draw(gs);
+            }
+            None => {
+                clear_background(DARK);
+                gui::draw_net_status(&format!("Waiting for {}", addr), false);
             }
+        }
+        if !live {
+            gui::draw_net_status("The stream has ended", true);
+        }
 
-            gs.explosions
-                .retain(|e| time - e.created_at < EXPLOSION_LIVE_TIME);
+        next_frame().await
+    }
+}
 
-            if gs.asteroids.len() == 0 {
-                gs.run_state = RunState::StageComplete;
+/*
+    Plays back a genome bred with the evolve binary, one seed after the
+    other from 0, in the environment it was bred in.
+*/
+async fn play_genome(genome: Genome) {
+    let mut env = gym::Env::new(genome.env_config(0));
+    let mut seed = 0;
+    let mut obs = env.reset(seed);
+    let mut lag = 0.0;
+    loop {
+        lag += get_frame_time();
+        let mut ticks = 0;
+        while lag >= TICK && ticks < MAX_TICKS_PER_FRAME {
+            let (next, _, done) = env.step(genome.act(&obs));
+            obs = next;
+            if done {
+                seed += 1;
+                obs = env.reset(seed);
             }
+            lag -= TICK;
+            ticks += 1;
+        }
+        if ticks == MAX_TICKS_PER_FRAME {
+            lag = 0.0;
+        }
+        draw(env.game());
+        gui::draw_net_status(
+            &format!(
+                "generation {}  fitness {:.0}  seed {}",
+                genome.generation, genome.fitness, seed
+            ),
+            false,
+        );
 
-            // handle player bounds
-            gs.player.pos = wrap(gs.player.pos, gs.player.w, gs.player.h)
+        next_frame().await
+    }
+}
+
+// swaps today's challenge in, unless it was played already
+fn start_daily(gs: &mut GameState, daily: &mut Daily) {
+    if let Some(game) = daily.start(vec2(screen_width(), screen_height())) {
+        let sounds = std::mem::take(&mut gs.sounds);
+        let debug = gs.debug;
+        *gs = game;
+        gs.sounds = sounds;
+        gs.debug = debug;
+    }
+}
+
+/*
+    F5 saves the game as it is and F9 puts the last save back, see
+    quicksave.rs, never in a run that counts for a ranking. A loaded game
+    isn't the run it started out as any more, so it's no longer raced or
+    timed. What happened is returned to be shown, and whether it failed.
+*/
+fn quicksave(
+    gs: &mut GameState,
+    race: &mut Race,
+    timer: &mut Timer,
+    online: bool,
+) -> Option<(String, bool)> {
+    let save = is_key_pressed(KeyCode::F5);
+    if !save && !is_key_pressed(KeyCode::F9) {
+        return None;
+    }
+    if quicksave::is_ranked(gs, online) {
+        return Some(("No quicksaves in ranked play".to_string(), true));
+    }
+    if save {
+        return Some(match quicksave::save(gs, QUICKSAVE_FILE) {
+            Ok(()) => ("Quicksaved".to_string(), false),
+            Err(e) => (format!("could not save {}: {}", QUICKSAVE_FILE, e), true),
+        });
+    }
+    match quicksave::load(QUICKSAVE_FILE) {
+        Ok(game) => {
+            // the window takes on the size the game was saved at
+            if game.screen != gs.screen {
+                request_new_screen_size(game.screen.x, game.screen.y);
+            }
+            let sounds = std::mem::take(&mut gs.sounds);
+            let debug = gs.debug;
+            *gs = game;
+            gs.sounds = sounds;
+            gs.debug = debug;
+            race.abandon();
+            timer.abandon();
+            Some(("Quickloaded".to_string(), false))
         }
-        _ => {}
+        Err(e) => Some((e, true)),
     }
 }
 
 #[macroquad::main("asteroids.rs")]
 async fn main() {
     request_new_screen_size(SCREEN_WIDTH, SCREEN_HEIGHT);
-    let mut gs = get_new_game_state();
+    let mut gs = get_new_game_state(1, Mode::Campaign);
 
     audio::load_assets(&mut gs).await;
 
+    match evolve::genome_from_args() {
+        Some(Ok(genome)) => return play_genome(genome).await,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+        None => {}
+    }
+    match stream::watch_from_args() {
+        Some(Ok(addr)) => return watch(addr).await,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+        None => {}
+    }
+    let mut publisher = match stream::port_from_args() {
+        Some(Ok(port)) => match Publisher::bind(port) {
+            Ok(publisher) => Some(publisher),
+            Err(e) => {
+                eprintln!("could not open port {}: {}", port, e);
+                return;
+            }
+        },
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+        None => None,
+    };
+
+    match net::Config::from_args() {
+        Some(Ok(config)) => return play_online(gs, config, publisher).await,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+        None => {}
+    }
+
+    let mut online = match leaderboard::Config::from_args().map(|c| c.and_then(Client::new)) {
+        Some(Ok(client)) => Some(client),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+        None => None,
+    };
+    if let Some(client) = &online {
+        client.fetch(gs.seed);
+    }
+
+    let mut race = Race::new(HIGH_SCORES_FILE);
+    let mut timer = Timer::new(SPLITS_FILE);
+    let best: Vec<i32> = race
+        .table
+        .for_seed(gs.seed)
+        .take(5)
+        .map(|e| e.score)
+        .collect();
+    let mut daily = Daily::new(DAILY_FILE);
+    let challenge = &daily.challenge;
+    let daily_lines = [
+        match daily.board.played(&challenge.date) {
+            Some(attempt) => format!("Daily challenge done, {} points", attempt.score),
+            None => format!("Y for the daily challenge {}", challenge.date),
+        },
+        challenge.describe(),
+    ];
+    if title(&best, &daily_lines, online.as_mut()).await == KeyCode::Y {
+        start_daily(&mut gs, &mut daily);
+    }
+
+    // the simulation runs at a fixed rate whatever the frame rate is
+    let mut lag = 0.0;
+    let mut notice: Option<(String, bool, f64)> = None; // shown until the time given
     loop {
-        gs.scl = screen_height() / UNITS;
+        gs.screen = vec2(screen_width(), screen_height());
+        gs.scl = gs.screen.y / UNITS;
 
         handle_input(&mut gs);
-        update(&mut gs);
+        if is_key_pressed(KeyCode::Y) {
+            start_daily(&mut gs, &mut daily);
+        }
+        if is_key_pressed(KeyCode::T) {
+            timer.visible = !timer.visible;
+        }
+        if let Some((text, alert)) = quicksave(&mut gs, &mut race, &mut timer, online.is_some()) {
+            notice = Some((text, alert, get_time() + QUICKSAVE_NOTICE as f64));
+        }
+        lag += get_frame_time();
+        let mut ticks = 0;
+        while lag >= TICK && ticks < MAX_TICKS_PER_FRAME {
+            let actions = local_actions(&gs);
+            race.before(&gs);
+            timer.before(&gs);
+            step(&mut gs, &actions);
+            let finished = race.after(&gs, actions[0]);
+            timer.after(&gs);
+            let attempt = daily.after(&gs, actions[0]);
+            if let Some(client) = &mut online {
+                if let Some(entry) = finished.or(attempt.map(|a| a.entry())) {
+                    client.submit(entry);
+                }
+            }
+            if let Some(publisher) = &mut publisher {
+                publisher.publish(&gs);
+            }
+            lag -= TICK;
+            ticks += 1;
+        }
+        if ticks == MAX_TICKS_PER_FRAME {
+            lag = 0.0;
+        }
         draw(&gs);
+        if let Some(ship) = race.ghost(&gs) {
+            draw_ghost(ship, gs.scl);
+        }
+        if let Some((score, time)) = race.deltas(&gs) {
+            gui::draw_race(score, time);
+        }
+        if let Some(time) = timer.time(&gs).filter(|_| timer.visible) {
+            gui::draw_speedrun(time, timer.run_splits());
+        }
+        if gs.daily && stage::is_run_over(&gs) {
+            gui::draw_daily(&daily.board.best(), &daily.challenge.date);
+        }
+        if let Some(client) = &mut online {
+            client.poll();
+            if let Some(status) = client.status.as_ref().filter(|_| stage::is_run_over(&gs)) {
+                gui::draw_net_status(status, false);
+            }
+        }
+        if let Some(publisher) = publisher.as_ref().filter(|p| p.viewers() > 0) {
+            gui::draw_net_status(&format!("{} watching", publisher.viewers()), false);
+        }
+        if let Some((text, alert, _)) = notice.as_ref().filter(|n| get_time() < n.2) {
+            gui::draw_net_status(text, *alert);
+        }
 
         next_frame().await
     }
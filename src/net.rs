@@ -0,0 +1,286 @@
+use super::{Mode, Rng};
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/*
+    Artificial network conditions for testing on one machine. Outgoing
+    packets are held back for the latency give or take the jitter, which
+    also reorders them, and some are dropped outright. Both instances
+    usually run with the same settings so it applies in both directions.
+*/
+#[derive(Clone, Copy, Default)]
+pub struct Shim {
+    pub latency: f64, // seconds
+    pub jitter: f64,
+    pub loss: f32, // 0..1
+}
+
+pub struct Config {
+    pub port: u16,
+    pub peer: SocketAddr,
+    pub player: usize,
+    pub mode: Mode,
+    pub shim: Shim,
+}
+
+const USAGE: &str = "usage: asteroids-rs --net <port> <peer address> \
+    [--player 1|2] [--versus] [--latency ms] [--jitter ms] [--loss percent]";
+
+impl Config {
+    // None unless the game was started with --net
+    pub fn from_args() -> Option<Result<Config, String>> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let i = args.iter().position(|a| a == "--net")?;
+        Some(Self::parse(&args, i))
+    }
+
+    fn parse(args: &[String], net: usize) -> Result<Config, String> {
+        let value = |i: usize| args.get(i).ok_or_else(|| USAGE.to_string());
+        let port = value(net + 1)?.parse().map_err(|_| USAGE.to_string())?;
+        let peer = value(net + 2)?
+            .parse()
+            .map_err(|e| format!("bad peer address: {}", e))?;
+        let mut config = Config {
+            port,
+            peer,
+            player: 0,
+            mode: Mode::Campaign,
+            shim: Shim::default(),
+        };
+
+        let mut i = 0;
+        while i < args.len() {
+            let number = |i: usize| -> Result<f64, String> {
+                value(i + 1)?
+                    .parse()
+                    .map_err(|_| format!("{} needs a number", args[i]))
+            };
+            match args[i].as_str() {
+                "--player" => {
+                    config.player = match number(i)? as usize {
+                        1 => 0,
+                        2 => 1,
+                        _ => return Err("--player is 1 or 2".to_string()),
+                    };
+                    i += 1;
+                }
+                "--versus" => config.mode = Mode::Versus,
+                "--latency" => {
+                    config.shim.latency = number(i)? / 1000.0;
+                    i += 1;
+                }
+                "--jitter" => {
+                    config.shim.jitter = number(i)? / 1000.0;
+                    i += 1;
+                }
+                "--loss" => {
+                    config.shim.loss = (number(i)? / 100.0).clamp(0.0, 1.0) as f32;
+                    i += 1;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        Ok(config)
+    }
+}
+
+pub enum Packet {
+    // sent until the peer answers, both sides must agree on the game
+    Hello {
+        mode: u8,
+        player: u8,
+    },
+    /*
+        The sender's inputs from `first` on, resent until acknowledged so a
+        lost packet is covered by the next one. `ack` is the first tick the
+        sender is still missing inputs for, `advantage` how many ticks it is
+        ahead of us as far as it knows, and `hash` the checksum of its game
+        at `hash_tick`, the newest one it had all inputs for.
+    */
+    Input {
+        tick: u32,
+        ack: u32,
+        advantage: i8,
+        hash_tick: u32,
+        hash: u64,
+        first: u32,
+        actions: Vec<u8>,
+    },
+}
+
+const HELLO: u8 = 1;
+const INPUT: u8 = 2;
+
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Packet::Hello { mode, player } => {
+                out.extend([HELLO, *mode, *player]);
+            }
+            Packet::Input {
+                tick,
+                ack,
+                advantage,
+                hash_tick,
+                hash,
+                first,
+                actions,
+            } => {
+                out.push(INPUT);
+                out.extend(tick.to_le_bytes());
+                out.extend(ack.to_le_bytes());
+                out.extend(advantage.to_le_bytes());
+                out.extend(hash_tick.to_le_bytes());
+                out.extend(hash.to_le_bytes());
+                out.extend(first.to_le_bytes());
+                out.push(actions.len() as u8);
+                out.extend(actions);
+            }
+        }
+        out
+    }
+
+    // None for anything malformed
+    pub fn decode(bytes: &[u8]) -> Option<Packet> {
+        let mut r = Reader { bytes, at: 1 };
+        match *bytes.first()? {
+            HELLO => Some(Packet::Hello {
+                mode: r.u8()?,
+                player: r.u8()?,
+            }),
+            INPUT => Some(Packet::Input {
+                tick: r.u32()?,
+                ack: r.u32()?,
+                advantage: r.u8()? as i8,
+                hash_tick: r.u32()?,
+                hash: r.u64()?,
+                first: r.u32()?,
+                actions: {
+                    let count = r.u8()? as usize;
+                    r.take(count)?.to_vec()
+                },
+            }),
+            _ => None,
+        }
+    }
+}
+
+// pulls little endian fields off the front of a message, None past its end
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, at: 0 }
+    }
+
+    pub fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let field = self.bytes.get(self.at..self.at + n)?;
+        self.at += n;
+        Some(field)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    pub fn i32(&mut self) -> Option<i32> {
+        Some(self.u32()? as i32)
+    }
+
+    pub fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_bits(self.u32()?))
+    }
+
+    pub fn string(&mut self) -> Option<String> {
+        let len = self.u8()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.at == self.bytes.len()
+    }
+}
+
+// a non-blocking UDP socket talking to one peer through the shim
+pub struct Link {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    shim: Shim,
+    rng: Rng,
+    delayed: Vec<(Instant, Vec<u8>)>,
+}
+
+impl Link {
+    pub fn bind(config: &Config) -> std::io::Result<Link> {
+        let socket = UdpSocket::bind(("0.0.0.0", config.port))?;
+        socket.set_nonblocking(true)?;
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Ok(Link {
+            socket,
+            peer: config.peer,
+            shim: config.shim,
+            rng: Rng::new(seed),
+            delayed: Vec::new(),
+        })
+    }
+
+    pub fn send(&mut self, packet: &Packet) {
+        if self.rng.gen_range(0.0, 1.0) < self.shim.loss {
+            return;
+        }
+        let jitter = self.rng.gen_range(-1.0, 1.0) as f64 * self.shim.jitter;
+        let delay = (self.shim.latency + jitter).max(0.0);
+        self.delayed.push((
+            Instant::now() + Duration::from_secs_f64(delay),
+            packet.encode(),
+        ));
+        self.flush();
+    }
+
+    // sends whatever the shim has held back long enough
+    fn flush(&mut self) {
+        let now = Instant::now();
+        let (due, held): (Vec<_>, Vec<_>) = self.delayed.drain(..).partition(|p| p.0 <= now);
+        self.delayed = held;
+        for (_, bytes) in due {
+            // a full send buffer is just more packet loss
+            let _ = self.socket.send_to(&bytes, self.peer);
+        }
+    }
+
+    pub fn receive(&mut self) -> Vec<Packet> {
+        self.flush();
+        let mut packets = Vec::new();
+        let mut buf = [0; 512];
+        while let Ok((len, from)) = self.socket.recv_from(&mut buf) {
+            if from != self.peer {
+                continue;
+            }
+            if let Some(packet) = Packet::decode(&buf[..len]) {
+                packets.push(packet);
+            }
+        }
+        packets
+    }
+}
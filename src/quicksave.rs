@@ -0,0 +1,195 @@
+#[cfg(not(feature = "gui"))]
+use super::headless::{Color, Sound};
+use super::{replay, Bindings, GameState, Mode, COOP_BINDINGS, SOLO_BINDINGS, VERSUS_BINDINGS};
+use glam::{vec2, Vec2};
+#[cfg(feature = "gui")]
+use macroquad::{audio::Sound, prelude::Color};
+use nanoserde::{DeBin, SerBin};
+use std::{fs, io};
+
+/*
+    A snapshot of the whole simulation, taken with F5 and put back with F9
+    to practice a hard stretch or to hand a bug over with the state that
+    shows it. The game state derives SerBin and DeBin, behind a header so
+    a save from another version is turned away rather than read wrong.
+    VERSION goes up whenever what's written changes.
+*/
+const MAGIC: &[u8; 4] = b"ASTQ";
+const VERSION: u16 = 3;
+
+// whether the run counts for a ranking, there are no quicksaves in those
+pub fn is_ranked(gs: &GameState, online: bool) -> bool {
+    gs.daily || (online && replay::is_recordable(gs))
+}
+
+pub fn save(gs: &GameState, path: &str) -> io::Result<()> {
+    fs::write(path, encode(gs))
+}
+
+pub fn load(path: &str) -> Result<GameState, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    decode(&bytes).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn encode(gs: &GameState) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend(MAGIC);
+    out.extend(VERSION.to_le_bytes());
+    gs.ser_bin(&mut out);
+    out
+}
+
+/*
+    The sounds aren't part of a save, the caller hands its own over. The
+    stages are, a game on a stage pack loads with the pack it was saved
+    with whatever the running game plays.
+*/
+pub fn decode(bytes: &[u8]) -> Result<GameState, String> {
+    let Some(header) = bytes.get(..MAGIC.len() + 2) else {
+        return Err("not a quicksave".to_string());
+    };
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err("not a quicksave".to_string());
+    }
+    match u16::from_le_bytes([header[4], header[5]]) {
+        VERSION => {}
+        version => return Err(format!("made by another version ({})", version)),
+    }
+    let mut at = header.len();
+    let mut gs = GameState::de_bin(&mut at, bytes)
+        .ok()
+        .filter(|_| at == bytes.len())
+        .ok_or_else(|| "damaged".to_string())?;
+    rebind(&mut gs).ok_or_else(|| "damaged".to_string())?;
+    Ok(gs)
+}
+
+// the keys of a player, the same for every game of that mode and size
+fn bindings(mode: Mode, players: usize, i: usize) -> Option<Bindings> {
+    match (mode, players) {
+        (Mode::Versus, _) => VERSUS_BINDINGS.get(i).copied(),
+        (_, 1) => Some(SOLO_BINDINGS),
+        _ => COOP_BINDINGS.get(i).copied(),
+    }
+}
+
+/*
+    Puts back the keys, which aren't saved, in the game and the benched
+    hot-seat games. None for a game that would panic the first time it's
+    stepped, with more players than keys or a stage past the last.
+*/
+fn rebind(gs: &mut GameState) -> Option<()> {
+    let count = gs.players.len();
+    for (i, player) in gs.players.iter_mut().enumerate() {
+        player.bindings = bindings(gs.mode, count, i)?;
+    }
+    for game in gs.bench.iter_mut() {
+        rebind(game)?;
+    }
+    (gs.stage < gs.stages.len()).then_some(())
+}
+
+/*
+    Stand-ins written in place of the fields of types from other crates,
+    which can't derive SerBin, and of the ones that aren't saved at all.
+*/
+#[derive(SerBin, DeBin)]
+pub struct Xy {
+    x: f32,
+    y: f32,
+}
+
+impl From<&Vec2> for Xy {
+    fn from(v: &Vec2) -> Self {
+        Xy { x: v.x, y: v.y }
+    }
+}
+
+impl From<&Xy> for Vec2 {
+    fn from(v: &Xy) -> Self {
+        vec2(v.x, v.y)
+    }
+}
+
+#[derive(SerBin, DeBin)]
+pub struct Points(Vec<Xy>);
+
+impl From<&Vec<Vec2>> for Points {
+    fn from(points: &Vec<Vec2>) -> Self {
+        Points(points.iter().map(Xy::from).collect())
+    }
+}
+
+impl From<&Points> for Vec<Vec2> {
+    fn from(points: &Points) -> Self {
+        points.0.iter().map(Vec2::from).collect()
+    }
+}
+
+#[derive(SerBin, DeBin)]
+pub struct Craters(Vec<Points>);
+
+impl From<&Vec<Vec<Vec2>>> for Craters {
+    fn from(craters: &Vec<Vec<Vec2>>) -> Self {
+        Craters(craters.iter().map(Points::from).collect())
+    }
+}
+
+impl From<&Craters> for Vec<Vec<Vec2>> {
+    fn from(craters: &Craters) -> Self {
+        craters.0.iter().map(Vec::from).collect()
+    }
+}
+
+#[derive(SerBin, DeBin)]
+pub struct Rgba {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
+impl From<&Color> for Rgba {
+    fn from(c: &Color) -> Self {
+        Rgba {
+            r: c.r,
+            g: c.g,
+            b: c.b,
+            a: c.a,
+        }
+    }
+}
+
+impl From<&Rgba> for Color {
+    fn from(c: &Rgba) -> Self {
+        Color::new(c.r, c.g, c.b, c.a)
+    }
+}
+
+// nothing at all, the keys and sounds are put back after loading
+#[derive(SerBin, DeBin)]
+pub struct Unsaved;
+
+impl From<&Bindings> for Unsaved {
+    fn from(_: &Bindings) -> Self {
+        Unsaved
+    }
+}
+
+impl From<&Unsaved> for Bindings {
+    fn from(_: &Unsaved) -> Self {
+        SOLO_BINDINGS
+    }
+}
+
+impl From<&Vec<Option<Sound>>> for Unsaved {
+    fn from(_: &Vec<Option<Sound>>) -> Self {
+        Unsaved
+    }
+}
+
+impl From<&Unsaved> for Vec<Option<Sound>> {
+    fn from(_: &Unsaved) -> Self {
+        vec![None; 10]
+    }
+}
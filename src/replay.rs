@@ -0,0 +1,92 @@
+use super::{get_new_game_state, stage, step, wingman, Action, GameState, Mode, Modifiers, UNITS};
+use glam::vec2;
+use nanoserde::{DeJson, SerJson};
+
+/*
+    Everything needed to play a solo campaign run again: how it started and
+    the actions of every tick. The simulation is deterministic, so a game
+    from start() stepped with the actions in order goes exactly the same
+    way as the recorded one did.
+*/
+#[derive(SerJson, DeJson, Clone, PartialEq, Debug, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub screen: [f32; 2],
+    pub wingman: bool,
+    #[nserde(default)]
+    pub modifiers: Modifiers,
+    pub actions: String, // two hex digits per tick, see Action::to_bits
+}
+
+/*
+    Only solo campaign runs are recorded, they're the ones with high scores.
+    The daily challenge keeps its own, see daily.rs.
+*/
+pub fn is_recordable(gs: &GameState) -> bool {
+    gs.mode == Mode::Campaign && gs.players.len() == 1 && !gs.daily
+}
+
+impl Replay {
+    // a new recording of `gs`, which has to be at the start of stage 0
+    pub fn new(gs: &GameState) -> Replay {
+        Replay {
+            seed: gs.seed,
+            screen: [gs.screen.x, gs.screen.y],
+            wingman: gs.wingman_enabled,
+            modifiers: gs.modifiers,
+            actions: String::new(),
+        }
+    }
+
+    // the game as it was on the first tick
+    pub fn start(&self) -> GameState {
+        let mut gs = get_new_game_state(1, Mode::Campaign);
+        gs.seed = self.seed;
+        gs.screen = vec2(self.screen[0], self.screen[1]);
+        gs.scl = gs.screen.y / UNITS;
+        gs.modifiers = self.modifiers;
+        if self.wingman {
+            wingman::toggle(&mut gs);
+        }
+        stage::start(&mut gs, 0);
+        gs
+    }
+
+    // whether `gs` started out the way this run did
+    pub fn matches(&self, gs: &GameState) -> bool {
+        self.seed == gs.seed
+            && self.screen == [gs.screen.x, gs.screen.y]
+            && self.wingman == gs.wingman_enabled
+            && self.modifiers == gs.modifiers
+    }
+
+    pub fn push(&mut self, action: Action) {
+        self.actions.push_str(&format!("{:02x}", action.to_bits()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    // None past the end, or for a mangled recording
+    pub fn action(&self, tick: usize) -> Option<Action> {
+        let hex = self.actions.get(tick * 2..tick * 2 + 2)?;
+        u8::from_str_radix(hex, 16).ok().map(Action::from_bits)
+    }
+
+    // the game at the end of the run
+    pub fn play(&self) -> GameState {
+        let mut gs = self.start();
+        for tick in 0..self.len() {
+            let Some(action) = self.action(tick) else {
+                break;
+            };
+            step(&mut gs, &[action]);
+        }
+        gs
+    }
+}
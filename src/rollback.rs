@@ -0,0 +1,301 @@
+use super::{
+    net::{Link, Packet},
+    step, Action, GameState, Mode, HASH_INTERVAL, INPUT_DELAY, MAX_INPUTS_PER_PACKET,
+    MAX_PREDICTION,
+};
+use glam::Vec2;
+use std::collections::{BTreeMap, VecDeque};
+
+// FNV-1a over everything that matters to how the game plays out
+pub fn checksum(gs: &GameState) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut mix = |bits: u64| {
+        for byte in bits.to_le_bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    };
+    let vec = |mix: &mut dyn FnMut(u64), v: Vec2| {
+        mix(v.x.to_bits() as u64);
+        mix(v.y.to_bits() as u64);
+    };
+
+    mix(gs.tick as u64);
+    mix(gs.rng.state());
+    mix(gs.play_time.to_bits() as u64);
+    mix(gs.run_state as u64);
+    mix(gs.stage as u64);
+    for p in gs.players.iter() {
+        vec(&mut mix, p.ship.pos);
+        vec(&mut mix, p.ship.vel);
+        mix(p.ship.angle.to_bits() as u64);
+        mix(p.lives as u64);
+        mix(p.score as u64);
+        mix(p.kills as u64);
+        mix(p.down as u64);
+    }
+    for a in gs.asteroids.iter() {
+        vec(&mut mix, a.pos);
+        vec(&mut mix, a.vel);
+        mix(a.hits as u64);
+    }
+    for b in gs.bullets.iter() {
+        vec(&mut mix, b.pos);
+    }
+    for d in gs.drones.iter() {
+        vec(&mut mix, d.pos);
+    }
+    for c in gs.comets.iter() {
+        vec(&mut mix, c.pos);
+    }
+    if let Some(boss) = &gs.boss {
+        vec(&mut mix, boss.pos);
+        mix(boss.health as u64);
+    }
+    hash
+}
+
+/*
+    GGPO style rollback between two peers. Local inputs are scheduled
+    INPUT_DELAY ticks ahead and the game runs on without waiting for the
+    remote ones, guessing that the remote player keeps doing what they did
+    last. A snapshot is kept for every tick that still has a guess in it;
+    when the real input turns out different the game is put back to that
+    tick and played forward again with sound off. Once both inputs of a tick
+    are known it can't change any more, and every HASH_INTERVAL ticks the
+    peers compare checksums of those confirmed states to catch desyncs.
+*/
+pub struct Session {
+    pub local: usize, // player index controlled from here
+    link: Link,
+    local_inputs: BTreeMap<u32, Action>,
+    remote_inputs: BTreeMap<u32, Action>,
+    predicted: BTreeMap<u32, Action>, // guesses the game has been run with
+    snapshots: VecDeque<GameState>,   // the game at the start of every unconfirmed tick
+    confirmed: u32,                   // remote inputs are known for every tick before this
+    remote_ack: u32,                  // the remote has our inputs for every tick before this
+    remote_tick: u32,
+    remote_advantage: i32,
+    hash: (u32, u64),
+    hashes: BTreeMap<u32, u64>,
+    pub desync: Option<u32>,
+    pub rollback: u32, // ticks resimulated by the last rollback
+}
+
+impl Session {
+    pub fn new(local: usize, link: Link) -> Self {
+        // nobody has input for the ticks before the delay kicks in
+        let idle: BTreeMap<u32, Action> =
+            (0..INPUT_DELAY).map(|t| (t, Action::default())).collect();
+        Session {
+            local,
+            link,
+            local_inputs: idle.clone(),
+            remote_inputs: idle,
+            predicted: BTreeMap::new(),
+            snapshots: VecDeque::new(),
+            confirmed: INPUT_DELAY,
+            remote_ack: 0,
+            remote_tick: 0,
+            remote_advantage: 0,
+            hash: (0, 0),
+            hashes: BTreeMap::new(),
+            desync: None,
+            rollback: 0,
+        }
+    }
+
+    // says hello until the peer does, true once it has
+    pub fn connect(&mut self, mode: Mode) -> bool {
+        let hello = Packet::Hello {
+            mode: mode as u8,
+            player: self.local as u8,
+        };
+        self.link.send(&hello);
+        self.link.receive().iter().any(|p| match p {
+            Packet::Hello { mode: m, player } => *m == mode as u8 && *player != self.local as u8,
+            Packet::Input { .. } => true,
+        })
+    }
+
+    /*
+        Runs one tick with the local player's action, unless the game is
+        too far ahead of the remote one and has to wait for it. Returns
+        whether it ran.
+    */
+    pub fn advance(&mut self, gs: &mut GameState, action: Action) -> bool {
+        self.receive(gs);
+
+        let tick = gs.tick;
+        self.local_inputs
+            .entry(tick + INPUT_DELAY)
+            .or_insert(action);
+        self.send(tick);
+
+        // the one further ahead gives the other a tick to catch up
+        let advantage = tick as i32 - self.remote_tick as i32;
+        if tick >= self.confirmed + MAX_PREDICTION || advantage - self.remote_advantage > 2 {
+            return false;
+        }
+
+        self.snapshots.push_back(gs.clone());
+        let actions = self.actions(tick);
+        step(gs, &actions);
+        self.prune(gs.tick);
+        true
+    }
+
+    fn actions(&mut self, tick: u32) -> Vec<Action> {
+        let local = self.local_inputs.get(&tick).copied().unwrap_or_default();
+        let remote = match self.remote_inputs.get(&tick) {
+            Some(action) => *action,
+            None => {
+                let guess = self
+                    .remote_inputs
+                    .range(..tick)
+                    .next_back()
+                    .map_or(Action::default(), |(_, a)| *a);
+                self.predicted.insert(tick, guess);
+                guess
+            }
+        };
+        match self.local {
+            0 => vec![local, remote],
+            _ => vec![remote, local],
+        }
+    }
+
+    fn send(&mut self, tick: u32) {
+        let first = self.remote_ack;
+        let actions: Vec<u8> = self
+            .local_inputs
+            .range(first..)
+            .take(MAX_INPUTS_PER_PACKET)
+            .map(|(_, a)| a.to_bits())
+            .collect();
+        self.link.send(&Packet::Input {
+            tick,
+            ack: self.confirmed,
+            advantage: (tick as i32 - self.remote_tick as i32).clamp(-128, 127) as i8,
+            hash_tick: self.hash.0,
+            hash: self.hash.1,
+            first,
+            actions,
+        });
+    }
+
+    fn receive(&mut self, gs: &mut GameState) {
+        let mut mispredicted = None;
+        for packet in self.link.receive() {
+            let Packet::Input {
+                tick,
+                ack,
+                advantage,
+                hash_tick,
+                hash,
+                first,
+                actions,
+            } = packet
+            else {
+                continue;
+            };
+            self.remote_ack = self.remote_ack.max(ack);
+            if tick >= self.remote_tick {
+                self.remote_tick = tick;
+                self.remote_advantage = advantage as i32;
+            }
+            // tick 0 is never hashed, it stands for no checksum yet
+            if let Some(ours) = self.hashes.get(&hash_tick).filter(|_| hash_tick > 0) {
+                if *ours != hash && self.desync.is_none() {
+                    self.desync = Some(hash_tick);
+                }
+            }
+
+            for (k, bits) in actions.into_iter().enumerate() {
+                let t = first + k as u32;
+                if t < self.confirmed || self.remote_inputs.contains_key(&t) {
+                    continue;
+                }
+                let action = Action::from_bits(bits);
+                self.remote_inputs.insert(t, action);
+                if self
+                    .predicted
+                    .remove(&t)
+                    .is_some_and(|guess| guess != action)
+                {
+                    mispredicted = Some(mispredicted.map_or(t, |m: u32| m.min(t)));
+                }
+            }
+            while self.remote_inputs.contains_key(&self.confirmed) {
+                self.confirmed += 1;
+            }
+        }
+
+        if let Some(tick) = mispredicted {
+            self.resimulate(gs, tick);
+        }
+        self.prune(gs.tick);
+    }
+
+    fn resimulate(&mut self, gs: &mut GameState, from: u32) {
+        let Some(i) = self.snapshots.iter().position(|s| s.tick == from) else {
+            return;
+        };
+        let now = gs.tick;
+        let sounds = std::mem::take(&mut gs.sounds);
+        let debug = gs.debug;
+
+        *gs = self.snapshots[i].clone();
+        gs.sounds = vec![None; sounds.len()];
+        self.snapshots.truncate(i);
+        while gs.tick < now {
+            self.snapshots.push_back(gs.clone());
+            let actions = self.actions(gs.tick);
+            step(gs, &actions);
+        }
+
+        gs.sounds = sounds;
+        gs.debug = debug;
+        self.rollback = now - from;
+    }
+
+    // forgets what can't be rolled back to any more, checksumming on the way
+    fn prune(&mut self, now: u32) {
+        while self
+            .snapshots
+            .front()
+            .is_some_and(|s| s.tick < self.confirmed)
+        {
+            let Some(state) = self.snapshots.pop_front() else {
+                break;
+            };
+            if state.tick > 0 && state.tick % HASH_INTERVAL == 0 {
+                self.hash = (state.tick, checksum(&state));
+                self.hashes.insert(self.hash.0, self.hash.1);
+            }
+        }
+
+        // a peer that's behind can have inputs for ticks it hasn't run yet
+        let oldest = self.snapshots.front().map_or(now, |s| s.tick);
+        let keep = oldest.min(self.remote_ack);
+        self.local_inputs = self.local_inputs.split_off(&keep);
+        // the last confirmed remote input is the guess for the ones after it
+        self.remote_inputs = self.remote_inputs.split_off(&oldest.saturating_sub(1));
+        self.predicted = self.predicted.split_off(&oldest);
+        if self.hashes.len() > 16 {
+            self.hashes.pop_first();
+        }
+    }
+
+    pub fn status(&self, gs: &GameState) -> String {
+        match self.desync {
+            Some(tick) => format!("DESYNC at tick {}", tick),
+            None => format!(
+                "P{}  tick {}  ahead {}  rollback {}",
+                self.local + 1,
+                gs.tick,
+                gs.tick.saturating_sub(self.confirmed),
+                self.rollback
+            ),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use super::{replay::Replay, HIGH_SCORES};
+use nanoserde::{DeJson, SerJson};
+use std::{fs, io};
+
+// a finished solo campaign run
+#[derive(SerJson, DeJson, Clone, PartialEq, Debug)]
+pub struct Entry {
+    #[nserde(default)]
+    pub name: String, // who set it, only online scores have one
+    pub score: i32,
+    pub stage: usize, // the one it ended on
+    pub ticks: u32,
+    pub splits: Vec<u32>, // the tick each stage was cleared on
+    pub replay: Replay,   // raced against as a ghost, see ghost.rs
+}
+
+/*
+    The best runs for every seed, highest score first. Each seed keeps its
+    own HIGH_SCORES so a run on an odd seed never pushes out the normal
+    campaign's.
+*/
+#[derive(SerJson, DeJson, Default, Debug)]
+pub struct Table {
+    pub entries: Vec<Entry>,
+}
+
+impl Table {
+    // an empty table when there's no file yet or it can't be read
+    pub fn load(path: &str) -> Table {
+        let Ok(json) = fs::read_to_string(path) else {
+            return Table::default();
+        };
+        DeJson::deserialize_json(&json).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            Table::default()
+        })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.serialize_json())
+    }
+
+    // the entry's place for its seed, None if it didn't make the table
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        self.insert_within(entry, HIGH_SCORES)
+    }
+
+    // the same with `keep` entries for each seed instead of HIGH_SCORES
+    pub fn insert_within(&mut self, entry: Entry, keep: usize) -> Option<usize> {
+        let seed = entry.replay.seed;
+        let place = self
+            .entries
+            .iter()
+            .filter(|e| e.replay.seed == seed)
+            .take_while(|e| e.score >= entry.score)
+            .count();
+        if place >= keep {
+            return None;
+        }
+        let at = self
+            .entries
+            .iter()
+            .position(|e| e.score < entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(at, entry);
+
+        let mut kept = 0;
+        self.entries.retain(|e| {
+            kept += (e.replay.seed == seed) as usize;
+            e.replay.seed != seed || kept <= keep
+        });
+        Some(place)
+    }
+
+    pub fn for_seed(&self, seed: u64) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(move |e| e.replay.seed == seed)
+    }
+
+    // the best run that started out like `replay`, the one to race
+    pub fn best(&self, replay: &Replay) -> Option<&Entry> {
+        self.entries.iter().find(|e| {
+            e.replay.seed == replay.seed
+                && e.replay.screen == replay.screen
+                && e.replay.wingman == replay.wingman
+                && e.replay.modifiers == replay.modifiers
+        })
+    }
+}
@@ -0,0 +1,741 @@
+use super::{
+    get_new_game_state,
+    net::Reader,
+    stage::{self, Stage},
+    step, Action, GameState, Mode, BULLET_WIDTH, DRONE_RADIUS, MAX_MESSAGE_LEN, MAX_VERSUS_PLAYERS,
+    SERVER_PORT, SERVER_RATE, TICK,
+};
+use glam::{vec2, Vec2};
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub struct Config {
+    pub port: u16,
+    pub mode: Mode,
+    pub max_players: usize,
+    pub stages: Option<Vec<Stage>>, // a stage pack in place of the built in stages
+    pub rate: u32,
+}
+
+const USAGE: &str = "usage: server [--port n] [--mode campaign|versus] [--players n] \
+    [--stages pack.json] [--rate hz]";
+
+impl Config {
+    pub fn parse(args: &[String]) -> Result<Config, String> {
+        let mut config = Config {
+            port: SERVER_PORT,
+            mode: Mode::Campaign,
+            max_players: 2,
+            stages: None,
+            rate: SERVER_RATE,
+        };
+
+        let mut i = 0;
+        while i < args.len() {
+            let value = args.get(i + 1).ok_or_else(|| USAGE.to_string())?;
+            let number = || -> Result<u32, String> {
+                value
+                    .parse()
+                    .map_err(|_| format!("{} needs a number", args[i]))
+            };
+            match args[i].as_str() {
+                "--port" => {
+                    config.port = value.parse().map_err(|_| format!("bad port: {}", value))?
+                }
+                "--mode" => {
+                    config.mode = match value.as_str() {
+                        "campaign" => Mode::Campaign,
+                        "versus" => Mode::Versus,
+                        _ => return Err("--mode is campaign or versus".to_string()),
+                    }
+                }
+                "--players" => config.max_players = number()? as usize,
+                "--stages" => config.stages = Some(stage::load_pack(value)?),
+                "--rate" => config.rate = number()?,
+                _ => return Err(USAGE.to_string()),
+            }
+            i += 2;
+        }
+
+        // co-op is two ships at most
+        let most = match config.mode {
+            Mode::Versus => MAX_VERSUS_PLAYERS,
+            _ => 2,
+        };
+        if !(1..=most).contains(&config.max_players) {
+            return Err(format!("--players is 1 to {} in this mode", most));
+        }
+        if !(1..=60).contains(&config.rate) {
+            return Err("--rate is 1 to 60".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/*
+    Everything a client sees of the game, far less than the whole state.
+    The server only ever sends the difference to the previous frame, and
+    since TCP keeps them in order each client patches its own copy.
+*/
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Body {
+    pub pos: Vec2,
+    pub angle: f32,
+    pub size: f32, // pixels across
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Pilot {
+    pub ship: Body,
+    pub lives: i32,
+    pub score: i32,
+    pub kills: i32,
+    pub deaths: i32,
+    pub down: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Rock {
+    pub body: Body,
+    pub size: f32,
+    pub material: u8,
+}
+
+/*
+    The shape of the rock at the same place in the list, unrotated around
+    its center. Seeds can't give it, fragments are cut from their parent.
+    It only changes along with the rock, so it goes in a list of its own
+    instead of with every move.
+*/
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Outline {
+    pub points: Vec<Vec2>,
+    pub craters: Vec<Vec<Vec2>>,
+}
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Frame {
+    pub tick: u32,
+    pub mode: u8,
+    pub run_state: u8,
+    pub stage: u8,
+    pub round: u8,
+    pub play_time: f32,
+    pub screen: Vec2,
+    pub pilots: Vec<Pilot>,
+    pub asteroids: Vec<Rock>,
+    pub outlines: Vec<Outline>,
+    pub bullets: Vec<Body>,
+    pub drones: Vec<Body>,
+    pub comets: Vec<Body>,
+    pub boss: Vec<Body>, // empty or one
+}
+
+impl Frame {
+    pub fn capture(gs: &GameState) -> Frame {
+        let body = |pos: Vec2, angle: f32, size: f32| Body { pos, angle, size };
+        Frame {
+            tick: gs.tick,
+            mode: gs.mode as u8,
+            run_state: gs.run_state as u8,
+            stage: gs.stage as u8,
+            round: gs.round as u8,
+            play_time: gs.play_time,
+            screen: gs.screen,
+            pilots: gs
+                .players
+                .iter()
+                .map(|p| Pilot {
+                    ship: body(p.ship.pos, p.ship.angle, p.ship.h * gs.scl),
+                    lives: p.lives,
+                    score: p.score,
+                    kills: p.kills,
+                    deaths: p.deaths,
+                    down: p.down,
+                })
+                .collect(),
+            asteroids: gs
+                .asteroids
+                .iter()
+                .map(|a| Rock {
+                    body: body(a.pos, a.angle, a.w),
+                    size: a.size,
+                    material: a.material as u8,
+                })
+                .collect(),
+            outlines: gs
+                .asteroids
+                .iter()
+                .map(|a| Outline {
+                    points: a.points.clone(),
+                    craters: a.craters.clone(),
+                })
+                .collect(),
+            bullets: gs
+                .bullets
+                .iter()
+                .map(|b| body(b.pos, 0.0, BULLET_WIDTH * gs.scl))
+                .collect(),
+            drones: gs
+                .drones
+                .iter()
+                .map(|d| {
+                    let heading = d.vel.y.atan2(d.vel.x).to_degrees();
+                    body(d.pos, heading, DRONE_RADIUS * 2.0 * gs.scl)
+                })
+                .collect(),
+            comets: gs
+                .comets
+                .iter()
+                .map(|c| body(c.pos, 0.0, c.r * 2.0))
+                .collect(),
+            boss: gs
+                .boss
+                .iter()
+                .map(|b| body(b.pos, b.angle, b.r * 2.0))
+                .collect(),
+        }
+    }
+
+    // what turns `from` into this frame
+    pub fn delta(&self, from: &Frame) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(self.tick.to_le_bytes());
+        out.extend([self.mode, self.run_state, self.stage, self.round]);
+        for v in [self.play_time, self.screen.x, self.screen.y] {
+            out.extend(v.to_le_bytes());
+        }
+        diff(&from.pilots, &self.pilots, &mut out);
+        diff(&from.asteroids, &self.asteroids, &mut out);
+        diff(&from.outlines, &self.outlines, &mut out);
+        diff(&from.bullets, &self.bullets, &mut out);
+        diff(&from.drones, &self.drones, &mut out);
+        diff(&from.comets, &self.comets, &mut out);
+        diff(&from.boss, &self.boss, &mut out);
+        out
+    }
+
+    // None if the delta doesn't make sense, the frame is garbage after that
+    pub fn apply(&mut self, delta: &[u8]) -> Option<()> {
+        let mut r = Reader::new(delta);
+        self.tick = r.u32()?;
+        self.mode = r.u8()?;
+        self.run_state = r.u8()?;
+        self.stage = r.u8()?;
+        self.round = r.u8()?;
+        self.play_time = r.f32()?;
+        self.screen = vec2(r.f32()?, r.f32()?);
+        patch(&mut self.pilots, &mut r)?;
+        patch(&mut self.asteroids, &mut r)?;
+        patch(&mut self.outlines, &mut r)?;
+        patch(&mut self.bullets, &mut r)?;
+        patch(&mut self.drones, &mut r)?;
+        patch(&mut self.comets, &mut r)?;
+        patch(&mut self.boss, &mut r)?;
+        r.is_done().then_some(())
+    }
+}
+
+trait Entry: Clone + PartialEq + Default {
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(r: &mut Reader) -> Option<Self>;
+}
+
+impl Entry for Body {
+    fn write(&self, out: &mut Vec<u8>) {
+        for v in [self.pos.x, self.pos.y, self.angle, self.size] {
+            out.extend(v.to_le_bytes());
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        Some(Body {
+            pos: vec2(r.f32()?, r.f32()?),
+            angle: r.f32()?,
+            size: r.f32()?,
+        })
+    }
+}
+
+impl Entry for Rock {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.body.write(out);
+        out.extend(self.size.to_le_bytes());
+        out.push(self.material);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        Some(Rock {
+            body: Body::read(r)?,
+            size: r.f32()?,
+            material: r.u8()?,
+        })
+    }
+}
+
+// points go out as a u16 count and then x and y of each
+fn put_points(out: &mut Vec<u8>, points: &[Vec2]) {
+    out.extend((points.len() as u16).to_le_bytes());
+    for p in points {
+        out.extend(p.x.to_le_bytes());
+        out.extend(p.y.to_le_bytes());
+    }
+}
+
+fn read_points(r: &mut Reader) -> Option<Vec<Vec2>> {
+    let len = r.u16()?;
+    (0..len).map(|_| Some(vec2(r.f32()?, r.f32()?))).collect()
+}
+
+impl Entry for Outline {
+    fn write(&self, out: &mut Vec<u8>) {
+        put_points(out, &self.points);
+        out.extend((self.craters.len() as u16).to_le_bytes());
+        for crater in self.craters.iter() {
+            put_points(out, crater);
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        let points = read_points(r)?;
+        let len = r.u16()?;
+        Some(Outline {
+            points,
+            craters: (0..len).map(|_| read_points(r)).collect::<Option<_>>()?,
+        })
+    }
+}
+
+impl Entry for Pilot {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.ship.write(out);
+        for v in [self.lives, self.score, self.kills, self.deaths] {
+            out.extend(v.to_le_bytes());
+        }
+        out.push(self.down as u8);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        Some(Pilot {
+            ship: Body::read(r)?,
+            lives: r.i32()?,
+            score: r.i32()?,
+            kills: r.i32()?,
+            deaths: r.i32()?,
+            down: r.u8()? != 0,
+        })
+    }
+}
+
+// the new length, then only the entries that aren't the same as before
+fn diff<T: Entry>(old: &[T], new: &[T], out: &mut Vec<u8>) {
+    let changed: Vec<usize> = (0..new.len())
+        .filter(|i| old.get(*i) != Some(&new[*i]))
+        .collect();
+    out.extend((new.len() as u16).to_le_bytes());
+    out.extend((changed.len() as u16).to_le_bytes());
+    for i in changed {
+        out.extend((i as u16).to_le_bytes());
+        new[i].write(out);
+    }
+}
+
+fn patch<T: Entry>(list: &mut Vec<T>, r: &mut Reader) -> Option<()> {
+    let len = r.u16()? as usize;
+    let changed = r.u16()?;
+    list.resize(len, T::default());
+    for _ in 0..changed {
+        let i = r.u16()? as usize;
+        *list.get_mut(i)? = T::read(r)?;
+    }
+    Some(())
+}
+
+pub enum Message {
+    // client to server
+    Join { name: String },
+    Ready,
+    Input(Action), // held until the next one
+    // server to client
+    Welcome { mode: u8, max_players: u8 },
+    Refused { reason: String },
+    Lobby { players: Vec<(String, bool)> }, // names and whether they're ready
+    Start { seat: u8, players: u8 },
+    Delta(Vec<u8>), // see Frame::delta
+}
+
+const JOIN: u8 = 1;
+const READY: u8 = 2;
+const INPUT: u8 = 3;
+const WELCOME: u8 = 4;
+const REFUSED: u8 = 5;
+const LOBBY: u8 = 6;
+const START: u8 = 7;
+const DELTA: u8 = 8;
+
+// strings go out with a one byte length, so long ones are cut short
+fn put_string(out: &mut Vec<u8>, s: &str) {
+    let s: String = s.chars().take(60).collect();
+    out.push(s.len() as u8);
+    out.extend(s.as_bytes());
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Message::Join { name } => {
+                out.push(JOIN);
+                put_string(&mut out, name);
+            }
+            Message::Ready => out.push(READY),
+            Message::Input(action) => out.extend([INPUT, action.to_bits()]),
+            Message::Welcome { mode, max_players } => {
+                out.extend([WELCOME, *mode, *max_players]);
+            }
+            Message::Refused { reason } => {
+                out.push(REFUSED);
+                put_string(&mut out, reason);
+            }
+            Message::Lobby { players } => {
+                out.extend([LOBBY, players.len() as u8]);
+                for (name, ready) in players {
+                    put_string(&mut out, name);
+                    out.push(*ready as u8);
+                }
+            }
+            Message::Start { seat, players } => out.extend([START, *seat, *players]),
+            Message::Delta(delta) => {
+                out.push(DELTA);
+                out.extend(delta);
+            }
+        }
+        out
+    }
+
+    // None for anything malformed
+    pub fn decode(bytes: &[u8]) -> Option<Message> {
+        let mut r = Reader::new(bytes);
+        let message = match r.u8()? {
+            JOIN => Message::Join { name: r.string()? },
+            READY => Message::Ready,
+            INPUT => Message::Input(Action::from_bits(r.u8()?)),
+            WELCOME => Message::Welcome {
+                mode: r.u8()?,
+                max_players: r.u8()?,
+            },
+            REFUSED => Message::Refused {
+                reason: r.string()?,
+            },
+            LOBBY => {
+                let count = r.u8()?;
+                let mut players = Vec::new();
+                for _ in 0..count {
+                    players.push((r.string()?, r.u8()? != 0));
+                }
+                Message::Lobby { players }
+            }
+            START => Message::Start {
+                seat: r.u8()?,
+                players: r.u8()?,
+            },
+            DELTA => Message::Delta(r.take(bytes.len() - 1)?.to_vec()),
+            _ => return None,
+        };
+        r.is_done().then_some(message)
+    }
+}
+
+/*
+    A non-blocking TCP stream carrying length prefixed messages, used on
+    both ends. Whatever the socket won't take yet waits in the outbox, and
+    a peer that stops reading altogether is let go.
+*/
+pub struct Connection {
+    stream: TcpStream,
+    inbox: Vec<u8>,
+    outbox: Vec<u8>,
+    pub closed: bool,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            stream,
+            inbox: Vec::new(),
+            outbox: Vec::new(),
+            closed: false,
+        })
+    }
+
+    pub fn connect(addr: SocketAddr) -> io::Result<Connection> {
+        Connection::new(TcpStream::connect(addr)?)
+    }
+
+    pub fn send(&mut self, message: &Message) {
+        let bytes = message.encode();
+        self.outbox.extend((bytes.len() as u32).to_le_bytes());
+        self.outbox.extend(bytes);
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        while !self.outbox.is_empty() && !self.closed {
+            match self.stream.write(&self.outbox) {
+                Ok(0) => self.closed = true,
+                Ok(n) => {
+                    self.outbox.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+        if self.outbox.len() > MAX_MESSAGE_LEN {
+            self.closed = true;
+        }
+    }
+
+    pub fn receive(&mut self) -> Vec<Message> {
+        self.flush();
+        let mut buf = [0; 4096];
+        while !self.closed {
+            match self.stream.read(&mut buf) {
+                Ok(0) => self.closed = true,
+                Ok(n) => self.inbox.extend(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(len) = Reader::new(&self.inbox).u32() {
+            let len = len as usize;
+            if len > MAX_MESSAGE_LEN {
+                self.closed = true;
+                break;
+            }
+            let Some(bytes) = self.inbox.get(4..4 + len) else {
+                break;
+            };
+            match Message::decode(bytes) {
+                Some(message) => messages.push(message),
+                None => {
+                    self.closed = true;
+                    break;
+                }
+            }
+            self.inbox.drain(..4 + len);
+        }
+        messages
+    }
+}
+
+struct Client {
+    connection: Connection,
+    name: Option<String>, // None until it has joined the lobby
+    ready: bool,
+    seat: Option<usize>, // which ship it flies once the game is on
+    action: Action,
+}
+
+/*
+    The authoritative game for clients connecting over TCP. They join a
+    lobby and the game starts once everyone in it is ready. From then on
+    it runs at the fixed tick on the inputs the clients last sent and
+    broadcasts frame deltas `rate` times a second, with no window or sound.
+*/
+pub struct Server {
+    config: Config,
+    listener: TcpListener,
+    clients: Vec<Client>,
+    game: Option<GameState>,
+    frame: Frame, // the last one broadcast
+}
+
+impl Server {
+    pub fn bind(config: Config) -> io::Result<Server> {
+        let listener = TcpListener::bind(("0.0.0.0", config.port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Server {
+            config,
+            listener,
+            clients: Vec::new(),
+            game: None,
+            frame: Frame::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // runs the lobby and then the game until the last player has left
+    pub fn run(&mut self) {
+        let tick = Duration::from_secs_f32(TICK);
+        let mut next = Instant::now();
+        loop {
+            self.accept();
+            self.receive();
+            match self.game {
+                None => self.start_when_ready(),
+                Some(_) => self.tick(),
+            }
+            if self.game.is_some() && !self.clients.iter().any(|c| c.seat.is_some()) {
+                return;
+            }
+
+            // a server that falls behind slows the game down instead of skipping ticks
+            next += tick;
+            match next.checked_duration_since(Instant::now()) {
+                Some(wait) => thread::sleep(wait),
+                None => next = Instant::now(),
+            }
+        }
+    }
+
+    fn accept(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Ok(connection) = Connection::new(stream) {
+                self.clients.push(Client {
+                    connection,
+                    name: None,
+                    ready: false,
+                    seat: None,
+                    action: Action::default(),
+                });
+            }
+        }
+    }
+
+    fn receive(&mut self) {
+        let mut lobby_changed = false;
+        for i in 0..self.clients.len() {
+            for message in self.clients[i].connection.receive() {
+                match message {
+                    Message::Join { name } => lobby_changed |= self.join(i, name),
+                    Message::Ready if self.clients[i].name.is_some() => {
+                        self.clients[i].ready = true;
+                        lobby_changed = true;
+                    }
+                    Message::Input(action) => self.clients[i].action = action,
+                    _ => {}
+                }
+            }
+        }
+
+        let joined = self.clients.iter().filter(|c| c.name.is_some()).count();
+        self.clients.retain(|c| !c.connection.closed);
+        lobby_changed |= joined != self.clients.iter().filter(|c| c.name.is_some()).count();
+        if lobby_changed && self.game.is_none() {
+            let players = self
+                .clients
+                .iter()
+                .filter_map(|c| c.name.clone().map(|name| (name, c.ready)))
+                .collect();
+            self.send_lobby(&Message::Lobby { players });
+        }
+    }
+
+    // true if the client got in
+    fn join(&mut self, i: usize, name: String) -> bool {
+        if self.clients[i].name.is_some() {
+            return false;
+        }
+        let joined = self.clients.iter().filter(|c| c.name.is_some()).count();
+        let refusal = if self.game.is_some() {
+            Some("the game has already started")
+        } else if joined >= self.config.max_players {
+            Some("the lobby is full")
+        } else {
+            None
+        };
+
+        let client = &mut self.clients[i];
+        if let Some(reason) = refusal {
+            client.connection.send(&Message::Refused {
+                reason: reason.to_string(),
+            });
+            client.connection.closed = true;
+            return false;
+        }
+        client.connection.send(&Message::Welcome {
+            mode: self.config.mode as u8,
+            max_players: self.config.max_players as u8,
+        });
+        client.name = Some(name);
+        true
+    }
+
+    fn send_lobby(&mut self, message: &Message) {
+        for client in self.clients.iter_mut().filter(|c| c.name.is_some()) {
+            client.connection.send(message);
+        }
+    }
+
+    fn start_when_ready(&mut self) {
+        let players: Vec<usize> = (0..self.clients.len())
+            .filter(|i| self.clients[*i].name.is_some())
+            .collect();
+        let least = match self.config.mode {
+            Mode::Versus => 2,
+            _ => 1,
+        };
+        if players.len() < least || players.iter().any(|i| !self.clients[*i].ready) {
+            return;
+        }
+
+        let mut gs = get_new_game_state(players.len(), self.config.mode);
+        if let Some(stages) = &self.config.stages {
+            gs.stages = stages.clone();
+            stage::start(&mut gs, 0);
+        }
+        for (seat, i) in players.iter().enumerate() {
+            let client = &mut self.clients[*i];
+            client.seat = Some(seat);
+            client.connection.send(&Message::Start {
+                seat: seat as u8,
+                players: players.len() as u8,
+            });
+        }
+
+        // the first delta is against nothing, so it carries the whole frame
+        self.frame = Frame::default();
+        self.broadcast(Frame::capture(&gs));
+        self.game = Some(gs);
+    }
+
+    fn tick(&mut self) {
+        let Some(gs) = &mut self.game else {
+            return;
+        };
+        let mut actions = vec![Action::default(); gs.players.len()];
+        for client in self.clients.iter() {
+            if let Some(seat) = client.seat {
+                actions[seat] = client.action;
+            }
+        }
+        step(gs, &actions);
+
+        let every = ((1.0 / TICK).round() as u32 / self.config.rate).max(1);
+        if gs.tick % every == 0 {
+            let frame = Frame::capture(gs);
+            self.broadcast(frame);
+        }
+    }
+
+    fn broadcast(&mut self, frame: Frame) {
+        let delta = Message::Delta(frame.delta(&self.frame));
+        for client in self.clients.iter_mut().filter(|c| c.seat.is_some()) {
+            client.connection.send(&delta);
+        }
+        self.frame = frame;
+    }
+}
@@ -1,57 +1,144 @@
 use super::{
-    Asteroid, Exhaust, GameState, Star, ASTEROID_VEL, BULLET_VEL, EXHAUST_COOLDOWN, PLAYER_WIDTH,
+    fracture, is_simple, stage::MaterialMix, Asteroid, Exhaust, GameState, Material, Rng,
+    ShapeConfig, Star, ASTEROID_SHAPE, ASTEROID_VEL, BULLET_VEL, EXHAUST_COOLDOWN, PLAYER_WIDTH,
 };
-use macroquad::{
-    prelude::{get_time, rand, vec2, Vec2},
-    rand::{srand, RandomRange},
-};
-use std::ops::Add;
+use glam::{vec2, Vec2};
+use std::{f32::consts::PI, ops::Add};
 
-pub fn polygon(origo: Vec2, amount: i32, size: f32) -> Vec<Vec2> {
-    let mut points = Vec::new();
-    let angle_inc = 360.0 / amount as f32;
+pub struct Shape {
+    pub outline: Vec<Vec2>,
+    pub craters: Vec<Vec<Vec2>>,
+}
 
-    for i in 1..=amount {
-        let rot = (angle_inc * i as f32).to_radians();
-        let r = rand::gen_range(0.5, 1.0);
-        points.push(vec2(
-            origo.x + PLAYER_WIDTH * r * size * rot.sin(),
-            origo.y - PLAYER_WIDTH * r * size * rot.cos(),
-        ));
+// periodic value noise around the outline, one value per vertex in -1..1
+fn outline_noise(rng: &mut Rng, config: &ShapeConfig) -> Vec<f32> {
+    let mut noise = vec![0.0; config.vertices];
+    let mut amp = 1.0;
+    let mut total = 0.0;
+    for octave in 0..config.octaves {
+        let freq = config.base_freq << octave;
+        let lattice: Vec<f32> = (0..freq).map(|_| rng.gen_range(-1.0, 1.0)).collect();
+        for (v, n) in noise.iter_mut().enumerate() {
+            let t = v as f32 / config.vertices as f32 * freq as f32;
+            let i = t as usize;
+            let f = (1.0 - ((t - i as f32) * PI).cos()) / 2.0;
+            *n += amp * (lattice[i % freq] * (1.0 - f) + lattice[(i + 1) % freq] * f);
+        }
+        total += amp;
+        amp /= 2.0;
+    }
+    noise.iter().map(|n| n / total).collect()
+}
+
+/*
+    Builds an asteroid outline from a seed. Vertices sit at evenly spaced
+    angles around the origin with only the radius varying, which keeps the
+    outline star-shaped and therefore simple no matter how deep the noise or
+    notches go. Craters are placed inside the smallest radius.
+*/
+pub fn shape(seed: u64, size: f32, config: &ShapeConfig) -> Shape {
+    let mut rng = Rng::new(seed);
+    let noise = outline_noise(&mut rng, config);
+    let mut radii: Vec<f32> = noise.iter().map(|n| 0.75 + n * config.roughness).collect();
+
+    let n = config.vertices;
+    for _ in 0..config.notches {
+        let v = (rng.gen_range(0.0, n as f32) as usize) % n;
+        radii[v] *= 1.0 - config.notch_depth;
+        radii[(v + 1) % n] *= 1.0 - config.notch_depth / 2.0;
+        radii[(v + n - 1) % n] *= 1.0 - config.notch_depth / 2.0;
     }
 
-    points
+    let angle_inc = 360.0 / n as f32;
+    let outline: Vec<Vec2> = radii
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let rot = (angle_inc * (i + 1) as f32).to_radians();
+            let r = r.clamp(0.3, 1.0) * PLAYER_WIDTH * size;
+            vec2(r * rot.sin(), -r * rot.cos())
+        })
+        .collect();
+    debug_assert!(is_simple(&outline));
+
+    let inner = outline.iter().fold(f32::MAX, |m, p| m.min(p.length())) * (PI / n as f32).cos();
+    let mut craters = Vec::new();
+    for _ in 0..config.craters {
+        let cr = rng.gen_range(0.1, 0.25) * inner;
+        let rot = rng.gen_range(0.0, 2.0 * PI);
+        let dist = rng.gen_range(0.0, inner - cr);
+        let center = vec2(dist * rot.cos(), dist * rot.sin());
+        craters.push(
+            (0..6)
+                .map(|i| {
+                    let a = i as f32 * PI / 3.0;
+                    center + vec2(a.cos(), a.sin()) * cr * rng.gen_range(0.8, 1.0)
+                })
+                .collect(),
+        );
+    }
+
+    Shape { outline, craters }
 }
 
-pub fn asteroids(spawn_point: Vec2, r: f32, amount: i32, size: f32, scl: f32) -> Vec<Asteroid> {
+pub fn asteroid(
+    pos: Vec2,
+    vel: Vec2,
+    size: f32,
+    material: Material,
+    angle: f32,
+    scl: f32,
+    rng: &mut Rng,
+) -> Asteroid {
+    let seed = rng.next_u64();
+    let Shape { outline, craters } = shape(seed, size * scl, &ASTEROID_SHAPE);
+    let w = 2.0 * outline.iter().fold(0.0, |m: f32, p| m.max(p.length()));
+    Asteroid {
+        pos,
+        vel,
+        size,
+        mass: fracture::area(&outline) / (scl * scl),
+        points: outline,
+        craters,
+        seed,
+        material,
+        hits: material.hits(size),
+        w,
+        angle,
+        spin: 1.5 / size,
+        collision: false,
+        impact: None,
+    }
+}
+
+pub fn asteroids(
+    spawn_point: Vec2,
+    r: f32,
+    amount: i32,
+    size: f32,
+    materials: &MaterialMix,
+    scl: f32,
+    rng: &mut Rng,
+) -> Vec<Asteroid> {
     let mut asteroids = Vec::new();
     let angle_inc = 360.0 / amount as f32;
 
     for i in 1..=amount {
         let rot =
-            ((angle_inc * i as f32 + (30.0 * (rand::gen_range(0.1, 1.0)))) % 360.0).to_radians();
+            ((angle_inc * i as f32 + (30.0 * (rng.gen_range(0.1, 1.0)))) % 360.0).to_radians();
         let pos = vec2(spawn_point.x + r * rot.sin(), spawn_point.y - r * rot.cos());
         let vel = pos * ASTEROID_VEL / 20.0 / size;
-        let points = polygon(vec2(0.0, 0.0), 8, size * scl);
-        let w = points[0].distance(points[(points.len() / 2) as usize]);
-        let a = Asteroid {
-            pos,
-            vel,
-            size,
-            points,
-            w,
-            angle: rot,
-            collision: false,
-        };
-        asteroids.push(a)
+        let material = materials.pick(rng);
+        asteroids.push(asteroid(pos, vel, size, material, rot, scl, rng))
     }
 
     asteroids
 }
 
-pub fn exhaust_particles(gs: &mut GameState, vel: f32, rotation: f32, h: f32) {
-    let time = get_time();
-    if time - gs.player.last_exhaust_frame <= EXHAUST_COOLDOWN {
+pub fn exhaust_particles(gs: &mut GameState, i: usize, vel: f32, rotation: f32, h: f32) {
+    let time = gs.time;
+    let ship = &gs.players[i].ship;
+    if time - ship.last_exhaust_frame <= EXHAUST_COOLDOWN {
         return;
     }
 
@@ -59,16 +146,16 @@ pub fn exhaust_particles(gs: &mut GameState, vel: f32, rotation: f32, h: f32) {
     let mut s;
     let mut diff;
     for _i in 0..3 {
-        factor = rand::gen_range(0.3, 1.0);
-        s = rand::gen_range(0.1, 1.);
-        diff = if rand::gen_range(0, 100) < 50 {
+        factor = gs.rng.gen_range(0.3, 1.0);
+        s = gs.rng.gen_range(0.1, 1.);
+        diff = if gs.rng.gen_range(0.0, 1.0) < 0.5 {
             vec2(-(rotation.cos() * h / 4.0), -(rotation.sin() * h / 4.0))
         } else {
             vec2(rotation.cos() * h / 4.0, rotation.sin() * h / 4.0)
         };
         let pos = vec2(
-            gs.player.pos.x - (h / 2.0 + (h / 3.0) * factor) * rotation.sin(),
-            gs.player.pos.y + (h / 2.0 + (h / 3.0) * factor) * rotation.cos(),
+            ship.pos.x - (h / 2.0 + (h / 3.0) * factor) * rotation.sin(),
+            ship.pos.y + (h / 2.0 + (h / 3.0) * factor) * rotation.cos(),
         );
         gs.exhaust.push(Exhaust {
             created_at: time,
@@ -77,14 +164,14 @@ pub fn exhaust_particles(gs: &mut GameState, vel: f32, rotation: f32, h: f32) {
             vel: vec2(-(vel * rotation.sin()), vel * rotation.cos()).add(diff),
         });
     }
-    gs.player.last_exhaust_frame = time;
+    gs.players[i].ship.last_exhaust_frame = time;
 }
 
 pub fn stars(amount: i32, map_width: f32, map_height: f32) -> Vec<Star> {
-    srand(421337421337);
+    let mut rng = Rng::new(421337421337);
     let mut stars = Vec::new();
     for _i in 0..amount {
-        let sr = rand::gen_range(1, 10);
+        let sr = rng.gen_range(1.0, 10.0) as i32;
         let size;
         match sr {
             1 => size = 3.0,
@@ -93,13 +180,12 @@ pub fn stars(amount: i32, map_width: f32, map_height: f32) -> Vec<Star> {
         }
         stars.push(Star {
             pos: vec2(
-                rand::gen_range(0.0, map_width),
-                rand::gen_range(0.0, map_height),
+                rng.gen_range(0.0, map_width),
+                rng.gen_range(0.0, map_height),
             ),
             size,
         });
     }
-    rand::srand(macroquad::miniquad::date::now() as _);
 
     stars
 }
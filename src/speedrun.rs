@@ -0,0 +1,312 @@
+use super::{replay, stage, GameState, TICK};
+use nanoserde::{DeJson, SerJson};
+use std::{fs, io, path::Path};
+
+/*
+    The splits for one seed of the campaign, flown alone or with the
+    wingman, all times in ticks from the start of the run. A split is the
+    time a stage was cleared at, a segment the time from the previous
+    split to it.
+*/
+#[derive(SerJson, DeJson, Clone, PartialEq, Debug, Default)]
+pub struct Category {
+    pub seed: u64,
+    #[nserde(default)]
+    pub wingman: bool,
+    pub stages: Vec<String>, // names, a different stage list starts over
+    pub attempts: u32,
+    pub personal_best: Vec<u32>, // the fastest run that cleared every stage
+    pub best_segments: Vec<u32>, // the fastest each stage was ever cleared
+}
+
+impl Category {
+    fn name(&self) -> String {
+        let name = match self.seed {
+            0 => "Campaign".to_string(),
+            seed => format!("Campaign seed {}", seed),
+        };
+        match self.wingman {
+            true => name + " with wingman",
+            false => name,
+        }
+    }
+
+    fn file_name(&self) -> String {
+        let mut name = String::from("splits");
+        if self.seed != 0 {
+            name += &format!("-{}", self.seed);
+        }
+        if self.wingman {
+            name += "-wingman";
+        }
+        name + ".lss"
+    }
+
+    /*
+        The splits as a LiveSplit .lss file. The simulation clock is both
+        the real and the game time, it only runs while the game does.
+    */
+    pub fn to_livesplit(&self) -> String {
+        let time = |ticks: u32| {
+            let t = format_time(ticks);
+            format!("<RealTime>{}</RealTime><GameTime>{}</GameTime>", t, t)
+        };
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml += "<Run version=\"1.7.0\">\n";
+        xml += "  <GameIcon />\n";
+        xml += "  <GameName>asteroids-rs</GameName>\n";
+        xml += &format!("  <CategoryName>{}</CategoryName>\n", self.name());
+        xml += "  <Offset>00:00:00</Offset>\n";
+        xml += &format!("  <AttemptCount>{}</AttemptCount>\n", self.attempts);
+        xml += "  <AttemptHistory />\n";
+        xml += "  <Segments>\n";
+        for (i, name) in self.stages.iter().enumerate() {
+            xml += "    <Segment>\n";
+            xml += &format!("      <Name>{}</Name>\n", escape(name));
+            xml += "      <Icon />\n";
+            xml += "      <SplitTimes>\n";
+            match self.personal_best.get(i) {
+                Some(t) => {
+                    xml += "        <SplitTime name=\"Personal Best\">";
+                    xml += &time(*t);
+                    xml += "</SplitTime>\n";
+                }
+                None => xml += "        <SplitTime name=\"Personal Best\" />\n",
+            }
+            xml += "      </SplitTimes>\n";
+            match self.best_segments.get(i) {
+                Some(t) => {
+                    xml += &format!("      <BestSegmentTime>{}</BestSegmentTime>\n", time(*t))
+                }
+                None => xml += "      <BestSegmentTime />\n",
+            }
+            xml += "      <SegmentHistory />\n";
+            xml += "    </Segment>\n";
+        }
+        xml += "  </Segments>\n";
+        xml += "  <AutoSplitterSettings />\n";
+        xml += "</Run>\n";
+        xml
+    }
+}
+
+// hours:minutes:seconds with the seven decimals LiveSplit writes
+fn format_time(ticks: u32) -> String {
+    // whole seconds counted in ticks so they don't pick up float error
+    let per_second = (1.0 / TICK).round() as u32;
+    let seconds = ticks / per_second;
+    let fraction = (ticks % per_second) as f64 / per_second as f64;
+    format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        (fraction * 1e7).round() as u32
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[derive(SerJson, DeJson, Default, Debug)]
+pub struct Splits {
+    pub categories: Vec<Category>,
+}
+
+impl Splits {
+    // no splits when there's no file yet or it can't be read
+    pub fn load(path: &str) -> Splits {
+        let Ok(json) = fs::read_to_string(path) else {
+            return Splits::default();
+        };
+        DeJson::deserialize_json(&json).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            Splits::default()
+        })
+    }
+
+    /*
+        Writes the splits and a LiveSplit file for every category next to
+        them, splits.lss for the normal campaign and splits-<seed>.lss for
+        the other seeds, with -wingman before the .lss for wingman runs.
+    */
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.serialize_json())?;
+        for category in self.categories.iter() {
+            let lss = Path::new(path).with_file_name(category.file_name());
+            fs::write(lss, category.to_livesplit())?;
+        }
+        Ok(())
+    }
+}
+
+// how a stage was cleared compared with the personal best
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mark {
+    Gold, // the fastest this stage was ever cleared
+    Ahead,
+    Behind,
+    New, // nothing to compare with yet
+}
+
+#[derive(Clone, Debug)]
+pub struct Split {
+    pub stage: String,
+    pub time: u32,
+    pub delta: Option<i64>, // ticks against the personal best, negative is faster
+    pub mark: Mark,
+}
+
+struct Run {
+    category: usize,
+    splits: Vec<Split>,
+    end: Option<u32>, // the tick it ended on
+}
+
+/*
+    Times every solo campaign run from its first tick and compares each
+    cleared stage with the personal best for the same seed and wingman.
+    Call before() and after() around every tick of the live game. The
+    best segments are saved as soon as they're beaten, the personal best
+    when a run clears every stage.
+*/
+pub struct Timer {
+    path: String,
+    pub splits: Splits,
+    run: Option<Run>,
+    pub visible: bool,
+}
+
+impl Timer {
+    pub fn new(path: &str) -> Timer {
+        Timer {
+            path: path.to_string(),
+            splits: Splits::load(path),
+            run: None,
+            visible: true,
+        }
+    }
+
+    // stops timing the run, the splits of a quickloaded one don't count
+    pub fn abandon(&mut self) {
+        self.run = None;
+    }
+
+    pub fn before(&mut self, gs: &GameState) {
+        if gs.tick == 0 {
+            self.run = replay::is_recordable(gs).then(|| self.start(gs));
+        }
+        // the wingman switched mid-run would time it in the wrong category
+        if let Some(run) = &self.run {
+            if self.splits.categories[run.category].wingman != gs.wingman_enabled {
+                self.run = None;
+            }
+        }
+    }
+
+    pub fn after(&mut self, gs: &GameState) {
+        let Some(run) = &mut self.run else {
+            return;
+        };
+        if run.end.is_some() {
+            return;
+        }
+
+        let category = &mut self.splits.categories[run.category];
+        let mut save = false;
+        if let Some(tick) = stage::split(gs, run.splits.len()) {
+            let i = gs.stage;
+            let previous = run.splits.last().map_or(0, |s| s.time);
+            let segment = tick - previous;
+            let delta = category
+                .personal_best
+                .get(i)
+                .map(|pb| tick as i64 - *pb as i64);
+            let mark = match (category.best_segments.get(i), delta) {
+                (Some(best), _) if segment < *best => Mark::Gold,
+                (_, Some(d)) if d <= 0 => Mark::Ahead,
+                (_, Some(_)) => Mark::Behind,
+                _ => Mark::New,
+            };
+            match category.best_segments.get_mut(i) {
+                Some(best) => *best = (*best).min(segment),
+                None => category.best_segments.push(segment),
+            }
+            run.splits.push(Split {
+                stage: category.stages[i].clone(),
+                time: tick,
+                delta,
+                mark,
+            });
+            save = true;
+        }
+
+        if stage::is_run_over(gs) {
+            run.end = Some(gs.tick);
+            let times: Vec<u32> = run.splits.iter().map(|s| s.time).collect();
+            let complete = times.len() == category.stages.len();
+            let faster = category
+                .personal_best
+                .last()
+                .is_none_or(|pb| times.last().is_some_and(|t| t < pb));
+            if complete && faster {
+                category.personal_best = times;
+            }
+            save = true;
+        }
+        if save {
+            if let Err(e) = self.splits.save(&self.path) {
+                eprintln!("could not save {}: {}", self.path, e);
+            }
+        }
+    }
+
+    fn start(&mut self, gs: &GameState) -> Run {
+        let stages: Vec<String> = gs.stages.iter().map(|s| s.name.clone()).collect();
+        let categories = &mut self.splits.categories;
+        let wingman = gs.wingman_enabled;
+        let category = match categories
+            .iter()
+            .position(|c| c.seed == gs.seed && c.wingman == wingman)
+        {
+            Some(i) => i,
+            None => {
+                categories.push(Category {
+                    seed: gs.seed,
+                    wingman,
+                    ..Category::default()
+                });
+                categories.len() - 1
+            }
+        };
+        let c = &mut categories[category];
+        if c.stages != stages {
+            *c = Category {
+                seed: gs.seed,
+                wingman,
+                stages,
+                ..Category::default()
+            };
+        }
+        c.attempts += 1;
+        Run {
+            category,
+            splits: Vec::new(),
+            end: None,
+        }
+    }
+
+    // seconds into the current run, stopped where it ended
+    pub fn time(&self, gs: &GameState) -> Option<f32> {
+        let run = self.run.as_ref()?;
+        Some(run.end.unwrap_or(gs.tick) as f32 * TICK)
+    }
+
+    pub fn run_splits(&self) -> &[Split] {
+        self.run.as_ref().map_or(&[], |r| &r.splits)
+    }
+}
@@ -0,0 +1,446 @@
+// DeJson derives unwrap Option fields in a way clippy::question_mark flags,
+// and an allow on the struct doesn't reach the generated impl
+#![allow(clippy::question_mark)]
+use super::{
+    boss, comet, gravity, spawner, swarm, versus, wingman, BossKind, GameState, GravityWell,
+    Material, Mode, Rng, RunState, WellKind, Zone, ZoneKind, DEFAULT_SUN, FRAGMENT_RULES,
+    SCREEN_WIDTH, TICK, UNITS,
+};
+use glam::{vec2, Vec2};
+use nanoserde::{DeBin, DeJson, SerBin};
+
+// relative weights of each material within an asteroid group
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct MaterialMix {
+    #[nserde(default)]
+    pub rocky: f32,
+    #[nserde(default)]
+    pub metallic: f32,
+    #[nserde(default)]
+    pub explosive: f32,
+    #[nserde(default)]
+    pub ice: f32,
+    #[nserde(default)]
+    pub crystal: f32,
+}
+
+impl MaterialMix {
+    pub fn rocky() -> Self {
+        MaterialMix {
+            rocky: 1.0,
+            metallic: 0.0,
+            explosive: 0.0,
+            ice: 0.0,
+            crystal: 0.0,
+        }
+    }
+
+    pub fn pick(&self, rng: &mut Rng) -> Material {
+        let weights = [
+            (Material::Rocky, self.rocky),
+            (Material::Metallic, self.metallic),
+            (Material::Explosive, self.explosive),
+            (Material::Ice, self.ice),
+            (Material::Crystal, self.crystal),
+        ];
+        let total: f32 = weights.iter().map(|w| w.1).sum();
+        if total <= 0.0 {
+            return Material::Rocky;
+        }
+
+        let mut roll = rng.gen_range(0.0, total);
+        for (material, weight) in weights {
+            if roll < weight {
+                return material;
+            }
+            roll -= weight;
+        }
+        Material::Rocky
+    }
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct AsteroidGroup {
+    pub origo: [f32; 2],
+    pub radius: f32,
+    pub amount: i32,
+    pub size: f32,
+    pub delay: f32,
+    pub materials: Option<MaterialMix>,
+    #[nserde(default)]
+    pub eccentricity: f32, // orbit stages only, asteroids start at periapsis
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct BossSpec {
+    pub kind: BossKind,
+    pub origo: [f32; 2],
+    pub size: f32,
+    pub health: i32,
+    pub parts: usize,
+    pub part_hits: i32,
+    pub spawn_interval: f64,
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct WellSpec {
+    #[nserde(default)]
+    pub kind: WellKind,
+    pub origo: [f32; 2],
+    pub strength: f32,
+    #[nserde(default)]
+    pub radius: f32, // units
+}
+
+// a comet crossing from one point to another, both usually off screen
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct CometSpec {
+    pub from: [f32; 2],
+    pub to: [f32; 2],
+    pub speed: f32, // units per second
+    pub delay: f32,
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct SwarmSpec {
+    pub origo: [f32; 2],
+    pub radius: f32,
+    pub amount: usize,
+    pub delay: f32,
+}
+
+// only the fields of the zone's kind are used
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct ZoneSpec {
+    pub kind: ZoneKind,
+    pub origo: [f32; 2],
+    pub radius: f32,
+    #[nserde(default)]
+    pub friction: f32,
+    #[nserde(default)]
+    pub visibility: f32, // units
+    #[nserde(default)]
+    pub outage_rate: f32,
+    #[nserde(default)]
+    pub outage_time: f64,
+    #[nserde(default)]
+    pub damage: f32,
+    #[nserde(default)]
+    pub drift: [f32; 2], // units per second squared
+}
+
+/*
+    What an asteroid of a given size breaks into when destroyed. Rules
+    without a material apply to every material not listed explicitly.
+    Stages without any use FRAGMENT_RULES.
+*/
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct FragmentRule {
+    pub material: Option<Material>,
+    pub size: f32,
+    pub children: Vec<f32>, // sizes of the pieces, none shatters it into debris
+}
+
+pub fn default_fragments() -> Vec<FragmentRule> {
+    FRAGMENT_RULES
+        .iter()
+        .map(|(material, size, children)| FragmentRule {
+            material: *material,
+            size: *size,
+            children: children.to_vec(),
+        })
+        .collect()
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone, Copy, PartialEq, Default)]
+pub enum StageKind {
+    #[default]
+    Field,
+    Boss,
+    Orbit, // asteroids and ship start out orbiting the stage's planet
+}
+
+#[derive(DeJson, SerBin, DeBin, Clone)]
+pub struct Stage {
+    pub name: String,
+    #[nserde(rename = "type")]
+    #[nserde(default)]
+    pub kind: StageKind,
+    pub start_pos: [f32; 2],
+    pub seed: u64,
+    pub asteroids: Vec<AsteroidGroup>,
+    pub boss: Option<BossSpec>,
+    #[nserde(default)]
+    pub wells: Vec<WellSpec>,
+    #[nserde(default)]
+    pub zones: Vec<ZoneSpec>,
+    #[nserde(default)]
+    pub comets: Vec<CometSpec>,
+    #[nserde(default)]
+    pub swarms: Vec<SwarmSpec>,
+    #[nserde(default)]
+    pub comet_interval: f32, // seconds between random comets, none when 0
+    pub sun: Option<[f32; 2]>,
+    #[nserde(default_with = "default_fragments")]
+    pub fragments: Vec<FragmentRule>,
+}
+
+impl Stage {
+    // a rule for the material wins over one for every material, wherever it's listed
+    pub fn fragment_sizes(&self, material: Material, size: f32) -> &[f32] {
+        let rule = |m: Option<Material>| {
+            self.fragments
+                .iter()
+                .find(|r| r.size == size && r.material == m)
+        };
+        rule(Some(material))
+            .or_else(|| rule(None))
+            .map_or(&[], |r| &r.children)
+    }
+}
+
+pub fn load() -> Vec<Stage> {
+    parse("stages.json", include_str!("stages.json")).unwrap_or_else(|e| panic!("invalid {}", e))
+}
+
+// a stage pack from disk in the same format as stages.json
+pub fn load_pack(path: &str) -> Result<Vec<Stage>, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse(path, &json)
+}
+
+// the stages in `json`, turned away when they couldn't be played through
+fn parse(path: &str, json: &str) -> Result<Vec<Stage>, String> {
+    let stages: Vec<Stage> =
+        DeJson::deserialize_json(json).map_err(|e| format!("{}: {}", path, e))?;
+    if stages.is_empty() {
+        return Err(format!("{}: no stages", path));
+    }
+    if let Some(stage) = stages
+        .iter()
+        .find(|s| s.kind == StageKind::Boss && s.boss.is_none())
+    {
+        return Err(format!("{}: boss stage {} has no boss", path, stage.name));
+    }
+    Ok(stages)
+}
+
+// versus rounds cycle through these
+pub fn load_arenas() -> Vec<Stage> {
+    parse("arenas.json", include_str!("arenas.json")).unwrap_or_else(|e| panic!("invalid {}", e))
+}
+
+// stage coordinates are pixel offsets from the center of a SCREEN_WIDTH wide screen
+fn to_screen(gs: &GameState, offset: [f32; 2]) -> Vec2 {
+    gs.screen / 2.0 + vec2(offset[0], offset[1]) * factor(gs)
+}
+
+fn factor(gs: &GameState) -> f32 {
+    gs.screen.x / SCREEN_WIDTH
+}
+
+/*
+    Lays the current stage out again for another screen size. Network games
+    run at a fixed size on every peer whatever their windows are.
+*/
+pub fn resize(gs: &mut GameState, screen: Vec2) {
+    if gs.screen == screen {
+        return;
+    }
+    gs.screen = screen;
+    gs.scl = screen.y / UNITS;
+    start(gs, gs.stage);
+}
+
+pub fn planet(gs: &GameState) -> Option<&GravityWell> {
+    gs.wells.iter().find(|w| w.kind == WellKind::Planet)
+}
+
+pub fn is_orbit(gs: &GameState) -> bool {
+    gs.stages[gs.stage].kind == StageKind::Orbit
+}
+
+fn spawn_group(gs: &mut GameState, group: &AsteroidGroup) {
+    let materials = group.materials.clone().unwrap_or_else(MaterialMix::rocky);
+    let amount = (group.amount as f32 * gs.modifiers.asteroids)
+        .round()
+        .max(1.0) as i32;
+    let mut asteroids = spawner::asteroids(
+        to_screen(gs, group.origo),
+        group.radius * factor(gs),
+        amount,
+        group.size,
+        &materials,
+        gs.scl,
+        &mut gs.rng,
+    );
+    // orbits keep their speed, any other would send them off into space
+    match (is_orbit(gs), planet(gs)) {
+        (true, Some(planet)) => {
+            for a in asteroids.iter_mut() {
+                a.vel = gravity::orbital_velocity(planet, a.pos, gs.scl)
+                    * (1.0 + group.eccentricity).sqrt();
+            }
+        }
+        _ => {
+            for a in asteroids.iter_mut() {
+                a.vel *= gs.modifiers.speed;
+            }
+        }
+    }
+    gs.asteroids.append(&mut asteroids);
+}
+
+/*
+    Puts a ship back at the stage start, in orbit on orbit stages. Ships
+    line up side by side in co-op. The first player gets a fresh wingman if
+    it is turned on.
+*/
+pub fn respawn(gs: &mut GameState, i: usize) {
+    let stage = &gs.stages[gs.stage];
+    let offset = (i as f32 - (gs.players.len() - 1) as f32 / 2.0) * 2.0 * gs.scl;
+    let pos = to_screen(gs, stage.start_pos) + vec2(offset, 0.0);
+    let vel = match (is_orbit(gs), planet(gs)) {
+        (true, Some(planet)) => gravity::orbital_velocity(planet, pos, gs.scl) * TICK,
+        _ => Vec2::ZERO,
+    };
+
+    let player = &mut gs.players[i];
+    player.down = false;
+    player.ship.reset();
+    player.ship.pos = pos;
+    player.ship.vel = vel;
+    if i == 0 && gs.wingman_enabled {
+        wingman::spawn(gs);
+    }
+}
+
+pub fn start(gs: &mut GameState, index: usize) {
+    let stage = gs.stages[index].clone();
+    gs.rng = Rng::new(stage.seed ^ gs.seed);
+
+    gs.stage = index;
+    gs.play_time = 0.0;
+    gs.asteroids.clear();
+    gs.bullets.clear();
+    gs.pending_groups = stage.asteroids;
+    gs.comets.clear();
+    gs.pending_comets = stage.comets;
+    gs.drones.clear();
+    gs.pending_swarms = stage.swarms;
+    gs.comet_interval = stage.comet_interval;
+    gs.last_comet = 0.0;
+    gs.sun = stage
+        .sun
+        .map_or(DEFAULT_SUN, |s| vec2(s[0], s[1]))
+        .normalize_or_zero();
+    gs.boss = match (stage.kind, stage.boss) {
+        (StageKind::Boss, Some(spec)) => Some(boss::spawn(
+            &spec,
+            to_screen(gs, spec.origo),
+            gs.scl,
+            stage.seed,
+        )),
+        // the loaders turn these away, one made up in code is just a field
+        _ => None,
+    };
+    gs.wells = stage
+        .wells
+        .iter()
+        .map(|w| GravityWell {
+            kind: w.kind,
+            pos: to_screen(gs, w.origo),
+            strength: w.strength,
+            radius: w.radius * gs.scl,
+        })
+        .collect();
+    gs.zones = stage
+        .zones
+        .iter()
+        .map(|z| Zone {
+            kind: z.kind,
+            pos: to_screen(gs, z.origo),
+            radius: z.radius * factor(gs),
+            friction: z.friction,
+            visibility: z.visibility * gs.scl,
+            outage_rate: z.outage_rate,
+            outage_time: z.outage_time,
+            damage: z.damage,
+            drift: vec2(z.drift[0], z.drift[1]) * gs.scl,
+        })
+        .collect();
+    // players out of lives stay down in co-op until a teammate revives them
+    for i in 0..gs.players.len() {
+        if gs.mode == Mode::Versus {
+            versus::respawn(gs, i);
+            continue;
+        }
+        respawn(gs, i);
+        gs.players[i].down = gs.players[i].lives <= 0;
+    }
+    spawn_due_groups(gs);
+    gs.run_state = RunState::Running;
+}
+
+// spawns the groups, comets and swarms whose delay has passed
+pub fn spawn_due_groups(gs: &mut GameState) {
+    let time = gs.play_time;
+    let (due, pending): (Vec<_>, Vec<_>) =
+        gs.pending_groups.drain(..).partition(|g| g.delay <= time);
+    gs.pending_groups = pending;
+    for group in due.iter() {
+        spawn_group(gs, group);
+    }
+
+    let (due, pending): (Vec<_>, Vec<_>) =
+        gs.pending_comets.drain(..).partition(|c| c.delay <= time);
+    gs.pending_comets = pending;
+    for spec in due.iter() {
+        let from = to_screen(gs, spec.from);
+        let dir = (to_screen(gs, spec.to) - from).normalize_or_zero();
+        gs.comets
+            .push(comet::spawn(from, dir * spec.speed * gs.scl, gs.scl));
+    }
+
+    let (due, pending): (Vec<_>, Vec<_>) =
+        gs.pending_swarms.drain(..).partition(|s| s.delay <= time);
+    gs.pending_swarms = pending;
+    for spec in due.iter() {
+        let origo = to_screen(gs, spec.origo);
+        let r = spec.radius * factor(gs);
+        swarm::spawn(gs, origo, r, spec.amount);
+    }
+}
+
+pub fn is_cleared(gs: &GameState) -> bool {
+    gs.asteroids.is_empty()
+        && gs.pending_groups.is_empty()
+        && gs.boss.is_none()
+        && gs.drones.is_empty()
+        && gs.pending_swarms.is_empty()
+}
+
+// every life lost, or in co-op nobody left flying to revive the others
+pub fn is_game_over(gs: &GameState) -> bool {
+    gs.players.iter().all(|p| p.lives <= 0 || p.down)
+}
+
+pub fn has_next(gs: &GameState) -> bool {
+    gs.stage + 1 < gs.stages.len()
+}
+
+/*
+    The tick the current stage was cleared on, for a run that has split
+    `splits` stages so far. Some only on the first tick it's cleared, the
+    speedrun timer and the ghost race both split on it so they agree.
+*/
+pub fn split(gs: &GameState, splits: usize) -> Option<u32> {
+    let cleared = gs.run_state == RunState::StageComplete && is_cleared(gs);
+    (cleared && splits == gs.stage).then_some(gs.tick)
+}
+
+// whether the run has ended, every stage cleared or every life lost
+pub fn is_run_over(gs: &GameState) -> bool {
+    gs.run_state == RunState::StageComplete
+        && (is_game_over(gs) || (is_cleared(gs) && !has_next(gs)))
+}
@@ -0,0 +1,242 @@
+use super::{
+    comet, get_new_game_state,
+    server::{Connection, Frame, Message, Outline, Rock},
+    stage, Asteroid, Bullet, Drone, GameState, Material, Mode, Owner, RunState, TICK, UNITS,
+};
+use glam::{vec2, Vec2};
+use std::{
+    io,
+    net::{SocketAddr, TcpListener},
+};
+
+// the port to publish on when started with --stream, None otherwise
+pub fn port_from_args() -> Option<Result<u16, String>> {
+    let args: Vec<String> = std::env::args().collect();
+    let i = args.iter().position(|a| a == "--stream")?;
+    Some(
+        args.get(i + 1)
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(|| "usage: asteroids-rs --stream <port>".to_string()),
+    )
+}
+
+// the address to watch when started with --watch, None otherwise
+pub fn watch_from_args() -> Option<Result<SocketAddr, String>> {
+    let args: Vec<String> = std::env::args().collect();
+    let i = args.iter().position(|a| a == "--watch")?;
+    Some(
+        args.get(i + 1)
+            .and_then(|a| a.parse().ok())
+            .ok_or_else(|| "usage: asteroids-rs --watch <address:port>".to_string()),
+    )
+}
+
+/*
+    Sends the running game to whoever connects, one Delta message per tick
+    framed like the dedicated server's, see server.rs. Someone tuning in
+    gets the whole frame first and only the changes after that.
+*/
+pub struct Publisher {
+    listener: TcpListener,
+    viewers: Vec<Connection>,
+    frame: Frame, // the last one published
+}
+
+impl Publisher {
+    pub fn bind(port: u16) -> io::Result<Publisher> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Publisher {
+            listener,
+            viewers: Vec::new(),
+            frame: Frame::default(),
+        })
+    }
+
+    pub fn publish(&mut self, gs: &GameState) {
+        let frame = Frame::capture(gs);
+        let delta = Message::Delta(frame.delta(&self.frame));
+        for viewer in self.viewers.iter_mut() {
+            viewer.send(&delta);
+            // nothing is expected back, this notices when they hang up
+            viewer.receive();
+        }
+        self.viewers.retain(|v| !v.closed);
+
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Ok(mut viewer) = Connection::new(stream) {
+                viewer.send(&Message::Delta(frame.delta(&Frame::default())));
+                self.viewers.push(viewer);
+            }
+        }
+        self.frame = frame;
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn viewers(&self) -> usize {
+        self.viewers.len()
+    }
+}
+
+// the other end, keeps a game state to draw up to date with the stream
+pub struct Spectator {
+    connection: Connection,
+    frame: Frame,
+    pub game: Option<GameState>,
+}
+
+impl Spectator {
+    pub fn connect(addr: SocketAddr) -> io::Result<Spectator> {
+        Ok(Spectator {
+            connection: Connection::connect(addr)?,
+            frame: Frame::default(),
+            game: None,
+        })
+    }
+
+    // takes in whatever has arrived, false once the stream has ended
+    pub fn update(&mut self) -> bool {
+        let mut changed = false;
+        for message in self.connection.receive() {
+            if let Message::Delta(delta) = message {
+                if self.frame.apply(&delta).is_none() {
+                    self.connection.closed = true;
+                    break;
+                }
+                changed = true;
+            }
+        }
+        if changed {
+            let gs = self
+                .game
+                .get_or_insert_with(|| get_new_game_state(1, Mode::Campaign));
+            restore(gs, &self.frame);
+        }
+        !self.connection.closed
+    }
+}
+
+/*
+    Dresses a game state up as the frame so the usual draw functions can
+    show it. Only what the frame has is right, the rest is whatever a fresh
+    game of the same mode and stage would have. The state is never stepped.
+*/
+pub fn restore(gs: &mut GameState, frame: &Frame) {
+    let mode = Mode::ALL
+        .get(frame.mode as usize)
+        .copied()
+        .unwrap_or(Mode::Campaign);
+    if gs.mode != mode || gs.players.len() != frame.pilots.len() {
+        *gs = get_new_game_state(frame.pilots.len(), mode);
+    }
+    // a stage pack the spectator doesn't have gets the wrong background
+    let index = (frame.stage as usize).min(gs.stages.len() - 1);
+    let beaten = gs.boss.is_none() && !frame.boss.is_empty(); // the stage started over
+    if gs.screen != frame.screen || gs.stage != index || beaten {
+        gs.screen = frame.screen;
+        gs.scl = frame.screen.y / UNITS;
+        stage::start(gs, index);
+    }
+
+    gs.tick = frame.tick;
+    gs.time = frame.tick as f64 * TICK as f64;
+    gs.play_time = frame.play_time;
+    gs.round = frame.round as usize;
+    gs.run_state = RunState::ALL
+        .get(frame.run_state as usize)
+        .copied()
+        .unwrap_or(RunState::Running);
+
+    for (player, pilot) in gs.players.iter_mut().zip(frame.pilots.iter()) {
+        player.ship.pos = pilot.ship.pos;
+        player.ship.angle = pilot.ship.angle;
+        player.lives = pilot.lives;
+        player.score = pilot.score;
+        player.kills = pilot.kills;
+        player.deaths = pilot.deaths;
+        player.down = pilot.down;
+    }
+
+    // outlines are only copied for asteroids that weren't there before
+    let rocks = frame.asteroids.iter().zip(frame.outlines.iter());
+    gs.asteroids.truncate(rocks.len());
+    for (i, (rock, outline)) in rocks.enumerate() {
+        match gs.asteroids.get_mut(i) {
+            Some(a) if a.points == outline.points => {
+                a.pos = rock.body.pos;
+                a.angle = rock.body.angle;
+            }
+            Some(a) => *a = asteroid(rock, outline),
+            None => gs.asteroids.push(asteroid(rock, outline)),
+        }
+    }
+
+    let time = gs.time;
+    gs.bullets = frame
+        .bullets
+        .iter()
+        .map(|b| Bullet {
+            pos: b.pos,
+            created_at: time,
+            vel: Vec2::ZERO,
+            collision: false,
+            owner: Owner::Player(usize::MAX), // not in the frame, they're drawn without a player color
+        })
+        .collect();
+    gs.drones = frame
+        .drones
+        .iter()
+        .map(|d| {
+            let heading = d.angle.to_radians();
+            Drone {
+                pos: d.pos,
+                vel: vec2(heading.cos(), heading.sin()),
+                swarm: 0,
+                collision: false,
+            }
+        })
+        .collect();
+    gs.comets = frame
+        .comets
+        .iter()
+        .map(|c| {
+            let mut comet = comet::spawn(c.pos, Vec2::ZERO, gs.scl);
+            comet.r = c.size / 2.0;
+            comet
+        })
+        .collect();
+    match (frame.boss.first(), &mut gs.boss) {
+        (Some(b), Some(boss)) => {
+            boss.pos = b.pos;
+            boss.angle = b.angle;
+        }
+        (None, boss) => *boss = None,
+        _ => {}
+    }
+}
+
+fn asteroid(rock: &Rock, outline: &Outline) -> Asteroid {
+    let material = Material::ALL
+        .get(rock.material as usize)
+        .copied()
+        .unwrap_or(Material::Rocky);
+    Asteroid {
+        pos: rock.body.pos,
+        vel: Vec2::ZERO,
+        angle: rock.body.angle,
+        spin: 0.0,
+        size: rock.size,
+        mass: 0.0,
+        points: outline.points.clone(),
+        craters: outline.craters.clone(),
+        seed: 0,
+        material,
+        hits: material.hits(rock.size),
+        w: rock.body.size,
+        collision: false,
+        impact: None,
+    }
+}
@@ -0,0 +1,172 @@
+use super::{
+    audio, contains, kill_player, nearest_ship, score_kill, segment_distance, wrap, Drone,
+    Explosion, GameState, Grid, Material, RunState, ALIGNMENT_WEIGHT, COHESION_WEIGHT,
+    DRONE_MAX_VEL, DRONE_RADIUS, DRONE_SEPARATION, DRONE_VIEW, SEEK_WEIGHT, SEPARATION_WEIGHT,
+};
+use glam::{vec2, Vec2};
+use std::f32::consts::PI;
+
+pub fn spawn(gs: &mut GameState, pos: Vec2, r: f32, amount: usize) {
+    let swarm = gs.drones.iter().map(|d| d.swarm + 1).max().unwrap_or(0);
+    for _ in 0..amount {
+        let a = gs.rng.gen_range(0.0, 2.0 * PI);
+        let dir = vec2(a.cos(), a.sin());
+        gs.drones.push(Drone {
+            pos: pos + dir * gs.rng.gen_range(0.0, r),
+            vel: dir * DRONE_MAX_VEL * gs.scl * 0.5,
+            swarm,
+            collision: false,
+        });
+    }
+}
+
+/*
+    Boids: each drone steers away from flockmates that are too close, towards
+    their average heading and towards their center, plus towards the nearest ship.
+    Only drones of the same swarm count as flockmates.
+*/
+fn steering(gs: &GameState, grid: &Grid, i: usize) -> Vec2 {
+    let view = DRONE_VIEW * gs.scl;
+    let max_vel = DRONE_MAX_VEL * gs.scl;
+    let d = &gs.drones[i];
+
+    let mut separation = Vec2::ZERO;
+    let mut heading = Vec2::ZERO;
+    let mut center = Vec2::ZERO;
+    let mut count = 0;
+    for j in grid.near(d.pos) {
+        let other = &gs.drones[j];
+        if j == i || other.swarm != d.swarm {
+            continue;
+        }
+        let off = d.pos - other.pos;
+        let dist = off.length() / gs.scl;
+        if dist * gs.scl > view {
+            continue;
+        }
+        if dist < DRONE_SEPARATION {
+            separation += off.normalize_or_zero() * (DRONE_SEPARATION - dist) / DRONE_SEPARATION;
+        }
+        heading += other.vel;
+        center += other.pos;
+        count += 1;
+    }
+
+    let seek = match nearest_ship(gs, d.pos) {
+        Some(target) => (target - d.pos).normalize_or_zero() * max_vel - d.vel,
+        None => Vec2::ZERO,
+    };
+    let mut force = separation * SEPARATION_WEIGHT * max_vel + seek * SEEK_WEIGHT;
+    if count > 0 {
+        force += (heading / count as f32 - d.vel) * ALIGNMENT_WEIGHT;
+        force += (center / count as f32 - d.pos) * COHESION_WEIGHT;
+    }
+    force
+}
+
+/*
+    Drones that can reach each other through flockmates within DRONE_VIEW
+    stay one swarm, the rest of a swarm that got cut apart flies off as
+    swarms of their own.
+*/
+fn split(gs: &mut GameState) {
+    let view = DRONE_VIEW * gs.scl;
+    let grid = Grid::new(gs.drones.iter().map(|d| d.pos), view, gs.screen);
+    let old: Vec<usize> = gs.drones.iter().map(|d| d.swarm).collect();
+    let mut assigned = vec![false; gs.drones.len()];
+    let mut next = 0;
+    for start in 0..gs.drones.len() {
+        if assigned[start] {
+            continue;
+        }
+        assigned[start] = true;
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            gs.drones[i].swarm = next;
+            for j in grid.near(gs.drones[i].pos) {
+                if !assigned[j]
+                    && old[j] == old[i]
+                    && gs.drones[i].pos.distance(gs.drones[j].pos) < view
+                {
+                    assigned[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+        next += 1;
+    }
+}
+
+pub fn update(gs: &mut GameState, delta: f32) {
+    if gs.drones.is_empty() {
+        return;
+    }
+
+    let grid = Grid::new(
+        gs.drones.iter().map(|d| d.pos),
+        DRONE_VIEW * gs.scl,
+        gs.screen,
+    );
+    let forces: Vec<Vec2> = (0..gs.drones.len())
+        .map(|i| steering(gs, &grid, i))
+        .collect();
+    let max_vel = DRONE_MAX_VEL * gs.scl;
+    let r = DRONE_RADIUS * gs.scl;
+    for (d, force) in gs.drones.iter_mut().zip(forces) {
+        d.vel = (d.vel + force * delta).clamp_length_max(max_vel);
+        d.pos = wrap(d.pos + d.vel * delta, r, r, gs.screen);
+    }
+
+    // bullets only need to look at the drones around them
+    let grid = Grid::new(
+        gs.drones.iter().map(|d| d.pos),
+        DRONE_VIEW * gs.scl,
+        gs.screen,
+    );
+    let mut killed = Vec::new();
+    for bullet in gs.bullets.iter_mut().filter(|b| !b.collision) {
+        let a = bullet.pos - bullet.vel * delta;
+        if let Some(i) = grid
+            .near(bullet.pos)
+            .find(|i| segment_distance(gs.drones[*i].pos, a, bullet.pos) < r)
+        {
+            bullet.collision = true;
+            if !gs.drones[i].collision {
+                gs.drones[i].collision = true;
+                killed.push((gs.drones[i].pos, gs.drones[i].vel, bullet.owner.player()));
+            }
+        }
+    }
+
+    for k in 0..gs.players.len() {
+        if gs.run_state != RunState::Running || gs.players[k].down {
+            continue;
+        }
+        let ship = gs.players[k].ship.points(gs.scl);
+        let touched = grid.near(gs.players[k].ship.pos).any(|i| {
+            let p = gs.drones[i].pos;
+            contains(&ship, p)
+                || (0..3).any(|k| segment_distance(p, ship[k], ship[(k + 1) % 3]) < r)
+        });
+        if touched {
+            kill_player(gs, k);
+        }
+    }
+
+    if killed.is_empty() {
+        return;
+    }
+    gs.drones.retain(|d| !d.collision);
+    split(gs);
+    for (pos, vel, owner) in killed {
+        gs.explosions.push(Explosion::new(
+            pos.x - r * 2.0,
+            pos.y - r * 2.0,
+            r * 4.0,
+            1.0,
+            gs.time,
+        ));
+        score_kill(gs, owner, pos, vel, 1.0, Material::Rocky);
+    }
+    audio::play_audio(&gs.sounds, audio::GameSound::ExplosionSmall);
+}
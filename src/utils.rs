@@ -1,19 +1,20 @@
-use macroquad::prelude::{screen_height, screen_width, Vec2};
+use glam::Vec2;
+use nanoserde::{DeBin, SerBin};
 
-use crate::gui::GUI_BAR_HEIGHT;
+use crate::components::GUI_BAR_HEIGHT;
 
-pub fn wrap(pos: Vec2, width: f32, height: f32) -> Vec2 {
+pub fn wrap(pos: Vec2, width: f32, height: f32, screen: Vec2) -> Vec2 {
     let mut new_pos = pos;
-    if pos.x > screen_width() {
+    if pos.x > screen.x {
         new_pos.x = 0.0 - width;
     } else if new_pos.x < 0.0 - width {
-        new_pos.x = screen_width();
+        new_pos.x = screen.x;
     }
 
-    if new_pos.y > screen_height() - GUI_BAR_HEIGHT {
+    if new_pos.y > screen.y - GUI_BAR_HEIGHT {
         new_pos.y = 0.0 - height;
     } else if new_pos.y < 0.0 - height {
-        new_pos.y = screen_height() - GUI_BAR_HEIGHT;
+        new_pos.y = screen.y - GUI_BAR_HEIGHT;
     }
 
     new_pos
@@ -34,3 +35,218 @@ pub fn ccw(a: Vec2, b: Vec2, c: Vec2) -> bool {
 pub fn intersects(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
     ccw(a, c, d) != ccw(b, c, d) && ccw(a, b, c) != ccw(a, b, d)
 }
+
+// point where segments AB and CD cross, if they do
+pub fn intersection(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> Option<Vec2> {
+    let r = b - a;
+    let s = d - c;
+    let denom = r.x * s.y - r.y * s.x;
+    if denom == 0.0 {
+        return None;
+    }
+    let t = ((c.x - a.x) * s.y - (c.y - a.y) * s.x) / denom;
+    let u = ((c.x - a.x) * r.y - (c.y - a.y) * r.x) / denom;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some(a + r * t)
+    } else {
+        None
+    }
+}
+
+// from `from` to `to` the short way round the wrapping screen
+pub fn wrapped_offset(from: Vec2, to: Vec2, screen: Vec2) -> Vec2 {
+    let mut d = to - from;
+    if d.x > screen.x / 2.0 {
+        d.x -= screen.x;
+    } else if d.x < -screen.x / 2.0 {
+        d.x += screen.x;
+    }
+    if d.y > screen.y / 2.0 {
+        d.y -= screen.y;
+    } else if d.y < -screen.y / 2.0 {
+        d.y += screen.y;
+    }
+    d
+}
+
+/*
+    Time until a bullet fired now at `speed` meets something at offset `d`
+    moving at `vel`, the smallest positive root of |d + vel t| = speed t.
+    None when it outruns the bullets.
+*/
+pub fn intercept(d: Vec2, vel: Vec2, speed: f32) -> Option<f32> {
+    let a = vel.length_squared() - speed * speed;
+    let b = 2.0 * d.dot(vel);
+    let c = d.length_squared();
+    if a.abs() < f32::EPSILON {
+        return if b < 0.0 { Some(-c / b) } else { None };
+    }
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    let sq = disc.sqrt();
+    [(-b - sq) / (2.0 * a), (-b + sq) / (2.0 * a)]
+        .into_iter()
+        .filter(|t| *t > 0.0)
+        .reduce(f32::min)
+}
+
+// distance from P to the segment AB
+pub fn segment_distance(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+// even-odd rule, works for concave polygons
+pub fn contains(points: &[Vec2], p: Vec2) -> bool {
+    let mut inside = false;
+    let mut j = points.len().wrapping_sub(1);
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[j];
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+// catches the ship sitting wholly inside a concave notch or hull too
+pub fn touches_ship(poly: &[Vec2], ship: &[Vec2]) -> bool {
+    ship.iter().any(|p| contains(poly, *p))
+        || (0..3).any(|i| {
+            (0..poly.len()).any(|j| {
+                intersects(
+                    ship[i],
+                    ship[(i + 1) % 3],
+                    poly[j],
+                    poly[(j + 1) % poly.len()],
+                )
+            })
+        })
+}
+
+/*
+    Whether segments AB and CD share a point. Unlike intersection() this
+    is careful with parallel segments, whose rounded crossing point can
+    land anywhere: those only meet if they lie on one line and overlap.
+*/
+fn meets(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let (r, s) = (b - a, d - c);
+    let eps = 1e-5 * r.length() * s.length();
+    if r.perp_dot(s).abs() > eps {
+        return intersection(a, b, c, d).is_some();
+    }
+    if r.perp_dot(c - a).abs() > 1e-5 * r.length() * (c - a).length().max(s.length()) {
+        return false;
+    }
+    let len = r.length_squared();
+    if len == 0.0 {
+        return a == c || a == d;
+    }
+    let (tc, td) = ((c - a).dot(r) / len, (d - a).dot(r) / len);
+    tc.min(td) <= 1.0 && tc.max(td) >= 0.0
+}
+
+// true if no two non-adjacent edges meet
+pub fn is_simple(points: &[Vec2]) -> bool {
+    let n = points.len();
+    for i in 0..n {
+        for j in (i + 2)..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            if meets(
+                points[i],
+                points[(i + 1) % n],
+                points[j],
+                points[(j + 1) % n],
+            ) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/*
+    Uniform grid over the screen for neighbour queries. Each cell holds the
+    indices of the points inside it, so with cells as large as the query
+    radius only the 3x3 block around a point has to be searched.
+*/
+pub struct Grid {
+    cell: f32,
+    cols: i32,
+    rows: i32,
+    cells: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    pub fn new(points: impl Iterator<Item = Vec2>, cell: f32, screen: Vec2) -> Self {
+        let cell = cell.max(1.0);
+        let cols = (screen.x / cell).ceil() as i32 + 1;
+        let rows = (screen.y / cell).ceil() as i32 + 1;
+        let mut grid = Grid {
+            cell,
+            cols,
+            rows,
+            cells: vec![Vec::new(); (cols * rows) as usize],
+        };
+        for (i, p) in points.enumerate() {
+            let (x, y) = grid.cell_of(p);
+            grid.cells[(y * cols + x) as usize].push(i);
+        }
+        grid
+    }
+
+    fn cell_of(&self, p: Vec2) -> (i32, i32) {
+        (
+            ((p.x / self.cell) as i32).clamp(0, self.cols - 1),
+            ((p.y / self.cell) as i32).clamp(0, self.rows - 1),
+        )
+    }
+
+    // indices of the points in the cells around `p`
+    pub fn near(&self, p: Vec2) -> impl Iterator<Item = usize> + '_ {
+        let (cx, cy) = self.cell_of(p);
+        (cy - 1..=cy + 1)
+            .flat_map(move |y| (cx - 1..=cx + 1).map(move |x| (x, y)))
+            .filter(|(x, y)| (0..self.cols).contains(x) && (0..self.rows).contains(y))
+            .flat_map(move |(x, y)| self.cells[(y * self.cols + x) as usize].iter().copied())
+    }
+}
+
+/*
+    Small seedable generator (splitmix64) for things that have to come out
+    the same every time, like the shape of an asteroid with a given seed.
+*/
+#[derive(SerBin, DeBin, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn gen_range(&mut self, low: f32, high: f32) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        low + (high - low) * unit
+    }
+}
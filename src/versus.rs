@@ -0,0 +1,85 @@
+use super::{
+    stage, GameState, Mode, Player, RunState, ARENA_REFILL_DELAY, GAME_TIME,
+    RESPAWN_INVULNERABILITY, VERSUS_ROUNDS,
+};
+use glam::{vec2, Vec2};
+use std::f32::consts::PI;
+
+pub fn is_invulnerable(player: &Player, time: f64) -> bool {
+    time < player.invulnerable_until
+}
+
+// evenly spaced on a ring around the center, facing inwards
+fn spawn_point(screen: Vec2, k: usize, n: usize) -> (Vec2, f32) {
+    let center = screen / 2.0;
+    let a = PI / 4.0 + k as f32 * 2.0 * PI / n as f32;
+    let pos = center + vec2(a.cos(), a.sin()) * screen.y * 0.35;
+    let to_center = center - pos;
+    (pos, to_center.x.atan2(-to_center.y).to_degrees())
+}
+
+/*
+    Puts a ship back in at whichever spawn point is farthest from the other
+    ships, shielded for a moment so it can't be camped.
+*/
+pub fn respawn(gs: &mut GameState, i: usize) {
+    let n = gs.players.len();
+    let others: Vec<Vec2> = (0..n)
+        .filter(|j| *j != i)
+        .map(|j| gs.players[j].ship.pos)
+        .collect();
+    let clearance = |k: usize| {
+        let (pos, _) = spawn_point(gs.screen, k, n);
+        others
+            .iter()
+            .map(|o| o.distance(pos))
+            .fold(f32::INFINITY, f32::min)
+    };
+    let k = (0..n)
+        .max_by(|a, b| clearance(*a).total_cmp(&clearance(*b)))
+        .unwrap_or(i);
+    let (pos, angle) = spawn_point(gs.screen, k, n);
+    let time = gs.time;
+
+    let player = &mut gs.players[i];
+    player.down = false;
+    player.ship.reset();
+    player.ship.pos = pos;
+    player.ship.angle = angle;
+    player.invulnerable_until = time + RESPAWN_INVULNERABILITY;
+}
+
+pub fn start_round(gs: &mut GameState, round: usize) {
+    gs.round = round;
+    stage::start(gs, round % gs.stages.len());
+}
+
+// player indices, most kills first and fewest deaths breaking ties
+pub fn standings(gs: &GameState) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..gs.players.len()).collect();
+    order.sort_by_key(|i| (-gs.players[*i].kills, gs.players[*i].deaths));
+    order
+}
+
+pub fn update(gs: &mut GameState) {
+    if gs.mode != Mode::Versus || gs.run_state != RunState::Running {
+        return;
+    }
+
+    // the arena never runs out of asteroids
+    if gs.asteroids.is_empty() && gs.pending_groups.is_empty() {
+        let delay = gs.play_time + ARENA_REFILL_DELAY;
+        gs.pending_groups = gs.stages[gs.stage].asteroids.clone();
+        for group in gs.pending_groups.iter_mut() {
+            group.delay += delay;
+        }
+    }
+
+    if gs.play_time >= GAME_TIME {
+        gs.run_state = if gs.round + 1 >= VERSUS_ROUNDS {
+            RunState::MatchSummary
+        } else {
+            RunState::StageComplete
+        };
+    }
+}
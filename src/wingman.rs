@@ -0,0 +1,85 @@
+use super::{
+    audio, contains, intercept, Bullet, Explosion, GameState, Owner, RunState, Wingman, BULLET_VEL,
+    TICK, WINGMAN_COOLDOWN, WINGMAN_ORBIT, WINGMAN_ORBIT_SPEED, WINGMAN_RADIUS, WINGMAN_RANGE,
+};
+use glam::vec2;
+
+pub fn spawn(gs: &mut GameState) {
+    gs.wingman = Some(Wingman {
+        pos: gs.players[0].ship.pos + vec2(WINGMAN_ORBIT * gs.scl, 0.0),
+        orbit: 0.0,
+        aim: vec2(0.0, -1.0),
+        last_turret_frame: 0.0,
+    });
+}
+
+pub fn toggle(gs: &mut GameState) {
+    gs.wingman_enabled = !gs.wingman_enabled;
+    if gs.wingman_enabled {
+        spawn(gs);
+    } else {
+        gs.wingman = None;
+    }
+}
+
+/*
+    An asteroid is a threat when it is in range and closing in on the
+    first player. The wingman leads the nearest one and only fires when the
+    predicted hit is still in range.
+*/
+pub fn update(gs: &mut GameState, delta: f32, time: f64) {
+    let Some(mut w) = gs.wingman.take() else {
+        return;
+    };
+
+    w.orbit = (w.orbit + WINGMAN_ORBIT_SPEED * delta) % (2.0 * std::f32::consts::PI);
+    w.pos = gs.players[0].ship.pos + vec2(w.orbit.cos(), w.orbit.sin()) * WINGMAN_ORBIT * gs.scl;
+
+    let range = WINGMAN_RANGE * gs.scl;
+    let ship = gs.players[0].ship.pos;
+    let ship_vel = gs.players[0].ship.vel / TICK;
+    let target = gs
+        .asteroids
+        .iter()
+        .filter(|a| !a.collision && a.pos.distance(ship) < range)
+        .filter(|a| (ship - a.pos).dot(a.vel - ship_vel) > 0.0)
+        .min_by(|a, b| a.pos.distance(ship).total_cmp(&b.pos.distance(ship)));
+
+    if let Some(a) = target {
+        if let Some(t) = intercept(a.pos - w.pos, a.vel, BULLET_VEL) {
+            let hit = a.pos + a.vel * t;
+            w.aim = (hit - w.pos).normalize_or_zero();
+            if time - w.last_turret_frame > WINGMAN_COOLDOWN && hit.distance(w.pos) < range {
+                w.last_turret_frame = time;
+                audio::play_audio(&gs.sounds, audio::GameSound::Shot);
+                gs.bullets.push(Bullet {
+                    pos: w.pos + w.aim * WINGMAN_RADIUS * gs.scl,
+                    created_at: time,
+                    vel: w.aim * BULLET_VEL,
+                    collision: false,
+                    owner: Owner::Wingman,
+                });
+            }
+        }
+    }
+
+    // rammed by an asteroid
+    let r = WINGMAN_RADIUS * gs.scl;
+    let hit = gs.run_state == RunState::Running
+        && gs
+            .asteroids
+            .iter()
+            .any(|a| contains(&a.points(), w.pos) || a.pos.distance(w.pos) < r);
+    if hit {
+        gs.explosions.push(Explosion::new(
+            w.pos.x - r * 2.0,
+            w.pos.y - r * 2.0,
+            r * 4.0,
+            1.0,
+            time,
+        ));
+        audio::play_audio(&gs.sounds, audio::GameSound::ExplosionSmall);
+        return;
+    }
+    gs.wingman = Some(w);
+}
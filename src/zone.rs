@@ -0,0 +1,66 @@
+use super::{kill_player, GameState, RunState, Spaceship, ZoneKind, FRICT};
+use glam::Vec2;
+
+// friction on a ship at `pos`, nebulae thicken it
+pub fn friction(gs: &GameState, pos: Vec2) -> f32 {
+    gs.zones
+        .iter()
+        .filter(|z| z.kind == ZoneKind::Nebula && z.contains(pos))
+        .fold(FRICT, |f, z| f * z.friction.max(1.0))
+}
+
+pub fn thrusters_online(ship: &Spaceship, time: f64) -> bool {
+    time >= ship.thrusters_offline_until
+}
+
+/*
+    Inside a nebula only asteroids within its visibility radius of the ship
+    can be seen. The smallest radius wins when nebulae overlap, and with
+    several ships whatever one of them sees is shown.
+*/
+pub fn visible(gs: &GameState, pos: Vec2) -> bool {
+    gs.players.iter().filter(|p| !p.down).any(|p| {
+        gs.zones
+            .iter()
+            .filter(|z| z.kind == ZoneKind::Nebula && z.contains(p.ship.pos))
+            .all(|z| p.ship.pos.distance(pos) < z.visibility)
+    })
+}
+
+pub fn update(gs: &mut GameState, delta: f32, time: f64) {
+    if gs.zones.is_empty() || gs.run_state != RunState::Running {
+        return;
+    }
+
+    for i in 0..gs.players.len() {
+        if gs.players[i].down {
+            continue;
+        }
+        let ship = &mut gs.players[i].ship;
+        let mut drift = Vec2::ZERO;
+        let mut damage = 0.0;
+        for z in gs.zones.iter().filter(|z| z.contains(ship.pos)) {
+            match z.kind {
+                ZoneKind::Nebula => {}
+                ZoneKind::IonStorm => {
+                    if thrusters_online(ship, time)
+                        && gs.rng.gen_range(0.0, 1.0) < z.outage_rate * delta
+                    {
+                        ship.thrusters_offline_until = time + z.outage_time;
+                    }
+                }
+                ZoneKind::DebrisField => {
+                    drift += z.drift;
+                    damage += z.damage;
+                }
+            }
+        }
+
+        // ship velocity is in pixels per frame
+        ship.vel += drift * delta * delta;
+        ship.hull -= damage * delta;
+        if ship.hull <= 0.0 {
+            kill_player(gs, i);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use asteroids_rs::{
+    autopilot,
+    components::{get_new_game_state, Action, GameState, Mode},
+    step,
+};
+
+const MINUTE: u32 = 60 * 60; // ticks
+
+// the best score along the way, the game starts over after game over
+fn fly(gs: &mut GameState, ticks: u32) -> i32 {
+    let mut best = 0;
+    for _ in 0..ticks {
+        let actions: Vec<Action> = (0..gs.players.len())
+            .map(|i| autopilot::act(gs, i))
+            .collect();
+        step(gs, &actions);
+        for player in gs.players.iter() {
+            assert!(player.ship.pos.is_finite() && player.ship.vel.is_finite());
+            best = best.max(player.score);
+        }
+    }
+    best
+}
+
+#[test]
+fn autopilot_outscores_sitting_still() {
+    let mut idle = get_new_game_state(1, Mode::Campaign);
+    let fire = Action {
+        fire: true,
+        ..Action::default()
+    };
+    let mut sitting = 0;
+    for _ in 0..MINUTE {
+        step(&mut idle, &[fire]);
+        sitting = sitting.max(idle.players[0].score);
+    }
+    let flying = fly(&mut get_new_game_state(1, Mode::Campaign), MINUTE);
+    assert!(
+        flying > sitting * 2,
+        "the autopilot scored {}, sitting still {}",
+        flying,
+        sitting
+    );
+}
+
+// bots play every mode for a while, past deaths, stage ends and restarts
+#[test]
+fn soak_every_mode() {
+    for (players, mode) in [
+        (1, Mode::Campaign),
+        (2, Mode::Campaign),
+        (4, Mode::Versus),
+        (2, Mode::HotSeat),
+    ] {
+        let mut gs = get_new_game_state(players, mode);
+        assert!(fly(&mut gs, 5 * MINUTE) > 0);
+    }
+}
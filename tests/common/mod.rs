@@ -0,0 +1,19 @@
+// every test file takes what it needs from here, none takes all of it
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+// somewhere under the temp dir for this test run alone
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("asteroids-{}-{}", name, std::process::id()))
+}
+
+pub fn temp_file(name: &str) -> String {
+    temp_path(name).to_string_lossy().into_owned()
+}
+
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = temp_path(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,35 @@
+use asteroids_rs::{
+    components::{get_new_game_state, Action, Mode},
+    stage, step,
+};
+
+#[test]
+fn only_a_teammate_brings_a_downed_ship_back() {
+    let mut gs = get_new_game_state(2, Mode::Campaign);
+    let lives = gs.players[1].lives;
+    gs.players[1].down = true;
+    gs.players[0].ship.pos = gs.players[1].ship.pos + gs.screen / 4.0;
+
+    // pressing fire doesn't respawn it
+    let fire = Action {
+        fire: true,
+        ..Action::default()
+    };
+    for _ in 0..10 {
+        step(&mut gs, &[Action::default(), Action::default()]);
+        step(&mut gs, &[Action::default(), fire]);
+    }
+    assert!(gs.players[1].down);
+
+    gs.players[0].ship.pos = gs.players[1].ship.pos;
+    step(&mut gs, &[Action::default(), Action::default()]);
+    assert!(!gs.players[1].down);
+    assert_eq!(gs.players[1].lives, lives);
+
+    // nobody left to do the reviving
+    assert!(!stage::is_game_over(&gs));
+    for player in gs.players.iter_mut() {
+        player.down = true;
+    }
+    assert!(stage::is_game_over(&gs));
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use asteroids_rs::{
+    autopilot,
+    components::{
+        get_new_game_state, Action, Mode, Modifiers, Weapon, DAILY_ASTEROIDS, DAILY_SPEED,
+    },
+    daily::{Board, Challenge, Daily},
+    stage, step,
+};
+use common::temp_file;
+use glam::vec2;
+
+#[test]
+fn every_day_has_its_own_challenge() {
+    assert_eq!(Challenge::for_day(0).date, "1970-01-01");
+    assert_eq!(Challenge::for_day(19782).date, "2024-02-29");
+    assert_eq!(Challenge::for_day(20745).date, "2026-10-19");
+    assert_eq!(Challenge::for_day(20745), Challenge::for_day(20745));
+
+    let days: Vec<Challenge> = (20000..20100).map(Challenge::for_day).collect();
+    for (a, b) in days.iter().zip(days.iter().skip(1)) {
+        assert_ne!(a.seed, b.seed);
+    }
+    for weapon in Weapon::ALL {
+        assert!(days.iter().any(|c| c.modifiers.weapon == weapon));
+    }
+    for c in days.iter() {
+        let m = c.modifiers;
+        assert!(m.asteroids >= DAILY_ASTEROIDS.0 && m.asteroids <= DAILY_ASTEROIDS.1);
+        assert!(m.speed >= DAILY_SPEED.0 && m.speed <= DAILY_SPEED.1);
+    }
+}
+
+#[test]
+fn modifiers_change_the_field() {
+    let mut normal = get_new_game_state(1, Mode::Campaign);
+    let mut crowded = get_new_game_state(1, Mode::Campaign);
+    crowded.modifiers = Modifiers {
+        asteroids: 2.0,
+        speed: 1.5,
+        ..Modifiers::default()
+    };
+    stage::start(&mut normal, 0);
+    stage::start(&mut crowded, 0);
+    assert!(crowded.asteroids.len() > normal.asteroids.len());
+
+    crowded.modifiers.weapon = Weapon::Spread;
+    let fire = Action {
+        fire: true,
+        ..Default::default()
+    };
+    for _ in 0..2 {
+        step(&mut normal, &[fire]);
+        step(&mut crowded, &[fire]);
+    }
+    assert_eq!(crowded.players[0].shots, 3 * normal.players[0].shots);
+}
+
+#[test]
+fn one_attempt_a_day() {
+    let path = temp_file("daily.json");
+    let mut daily = Daily::new(&path);
+    let mut gs = daily.start(vec2(400.0, 300.0)).unwrap();
+    assert!(gs.daily);
+    assert_eq!(gs.seed, daily.challenge.seed);
+    assert!(daily.start(vec2(400.0, 300.0)).is_none());
+
+    for _ in 0..20 * 60 * 60 {
+        let action = autopilot::act(&gs, 0);
+        step(&mut gs, &[action]);
+        daily.after(&gs, action);
+        if stage::is_run_over(&gs) {
+            break;
+        }
+    }
+    assert!(stage::is_run_over(&gs));
+
+    let board = Board::load(&path);
+    let attempt = board.played(&daily.challenge.date).unwrap();
+    assert!(attempt.finished);
+    assert_eq!(attempt.score, gs.players[0].score);
+    assert_eq!(attempt.replay.play().players[0].score, attempt.score);
+    assert!(Daily::new(&path).start(vec2(400.0, 300.0)).is_none());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,92 @@
+use asteroids_rs::{
+    evolve::{fitness, Config, Evolution, Genome},
+    utils::Rng,
+};
+
+fn small(threads: usize) -> Config {
+    Config {
+        population: 8,
+        elite: 2,
+        hidden: 4,
+        runs: 2,
+        max_ticks: 600,
+        threads,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn threads_do_not_change_the_outcome() {
+    let mut one = Evolution::new(small(1));
+    let mut four = Evolution::new(small(4));
+    for _ in 0..3 {
+        let (a, b) = (one.step().unwrap(), four.step().unwrap());
+        assert_eq!((a.best, a.mean), (b.best, b.mean));
+    }
+    assert_eq!(one.population, four.population);
+}
+
+#[test]
+fn the_best_never_gets_worse() {
+    let mut evolution = Evolution::new(small(2));
+    let mut best = f32::MIN;
+    for _ in 0..4 {
+        let report = evolution.step().unwrap();
+        assert!(report.best >= best && report.best >= report.mean);
+        best = report.best;
+    }
+}
+
+#[test]
+fn checkpoints_play_back_the_same() {
+    let out = std::env::temp_dir().join(format!("asteroids-evolve-{}", std::process::id()));
+    let config = Config {
+        out: Some(out.to_string_lossy().into_owned()),
+        ..small(2)
+    };
+    let mut evolution = Evolution::new(config);
+    evolution.step().unwrap();
+    evolution.step().unwrap();
+
+    let best = evolution.best.clone().unwrap();
+    let saved = Genome::load(out.join("best.json").to_str().unwrap()).unwrap();
+    assert_eq!(saved, best);
+    assert!(out.join("generation-0001.json").exists());
+    assert_eq!(fitness(&saved, &evolution.config), best.fitness);
+    std::fs::remove_dir_all(out).unwrap();
+}
+
+#[test]
+fn fitness_weighs_what_it_is_told() {
+    let genome = Genome::random(8, 4, 4, &mut Rng::new(1));
+    let survival = Config {
+        score: 0.0,
+        survival: 1.0,
+        ..small(1)
+    };
+    let seconds = fitness(&genome, &survival);
+    assert!(seconds > 0.0 && seconds <= 10.0);
+    let accuracy = Config {
+        score: 0.0,
+        accuracy: 1.0,
+        ..small(1)
+    };
+    assert!((0.0..=1.0).contains(&fitness(&genome, &accuracy)));
+}
+
+#[test]
+fn config_comes_from_the_command_line() {
+    let args = |line: &str| -> Vec<String> { line.split_whitespace().map(String::from).collect() };
+    let config = Config::parse(&args(
+        "--population 32 --mutation-rate 0.2 --survival 5 --out best --threads 3",
+    ))
+    .unwrap();
+    assert_eq!((config.population, config.threads), (32, 3));
+    assert_eq!((config.mutation_rate, config.survival), (0.2, 5.0));
+    assert_eq!(config.out.as_deref(), Some("best"));
+
+    assert!(Config::parse(&args("--population 1")).is_err());
+    assert!(Config::parse(&args("--runs 0")).is_err());
+    assert!(Config::parse(&args("--ticks 0")).is_err());
+    assert!(Config::parse(&args("--fitness")).is_err());
+}
@@ -0,0 +1,80 @@
+use asteroids_rs::{
+    components::Action,
+    gym::{Config, Env},
+};
+
+fn play(env: &mut Env, seed: u64, steps: usize) -> Vec<Vec<f32>> {
+    let mut trajectory = vec![env.reset(seed)];
+    for i in 0..steps {
+        let (obs, _, done) = env.step(Action::from_bits(i as u8));
+        trajectory.push(obs);
+        if done {
+            break;
+        }
+    }
+    trajectory
+}
+
+#[test]
+fn observations_have_the_advertised_length() {
+    let mut env = Env::new(Config {
+        nearest: 3,
+        rays: 16,
+        ..Config::default()
+    });
+    assert_eq!(env.observation_len(), 7 + 3 * 5 + 16);
+    assert_eq!(env.reset(1).len(), env.observation_len());
+    let (obs, _, _) = env.step(Action::default());
+    assert_eq!(obs.len(), env.observation_len());
+    assert!(obs[obs.len() - 16..]
+        .iter()
+        .all(|r| (0.0..=1.0).contains(r)));
+}
+
+#[test]
+fn seeds_replay_exactly() {
+    let mut env = Env::new(Config {
+        rays: 8,
+        ..Config::default()
+    });
+    let a = play(&mut env, 7, 600);
+    let b = play(&mut env, 7, 600);
+    assert_eq!(a, b);
+    let c = play(&mut env, 8, 600);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn max_steps_ends_the_episode() {
+    let mut env = Env::new(Config {
+        max_steps: 10,
+        ..Config::default()
+    });
+    env.reset(0);
+    for _ in 0..9 {
+        assert!(!env.step(Action::default()).2);
+    }
+    assert!(env.step(Action::default()).2);
+}
+
+#[test]
+fn dying_is_punished() {
+    let mut env = Env::new(Config {
+        ticks_per_step: 10,
+        ..Config::default()
+    });
+    env.reset(3);
+    let thrust = Action {
+        thrust: true,
+        ..Action::default()
+    };
+    let mut punished = false;
+    for _ in 0..2000 {
+        let (_, reward, done) = env.step(thrust);
+        punished |= reward < 0.0;
+        if punished || done {
+            break;
+        }
+    }
+    assert!(punished, "the ship never crashed");
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use asteroids_rs::{
+    autopilot,
+    components::{get_new_game_state, Mode, Modifiers, Weapon},
+    daily::Challenge,
+    leaderboard::{Client, Config, Server, ServerConfig},
+    replay::Replay,
+    scores::{Entry, Table},
+    stage, step,
+};
+use common::temp_file;
+use nanoserde::SerJson;
+use std::{
+    net::TcpListener,
+    thread,
+    time::{Duration, Instant},
+};
+
+// a whole run by the autopilot, as it would be submitted
+fn finished_run(seed: u64) -> Entry {
+    let mut gs = get_new_game_state(1, Mode::Campaign);
+    gs.seed = seed;
+    stage::start(&mut gs, 0);
+    let mut replay = Replay::new(&gs);
+    while !stage::is_run_over(&gs) {
+        let action = autopilot::act(&gs, 0);
+        replay.push(action);
+        step(&mut gs, &[action]);
+    }
+    Entry {
+        name: "ace".to_string(),
+        score: gs.players[0].score,
+        stage: gs.stage,
+        ticks: gs.tick,
+        splits: Vec::new(),
+        replay,
+    }
+}
+
+fn server(name: &str) -> Server {
+    Server::bind(ServerConfig {
+        port: 0,
+        file: temp_file(name),
+        keep: 10,
+    })
+    .unwrap()
+}
+
+fn client(url: String, outbox: &str) -> Client {
+    Client::new(Config {
+        url,
+        name: "ace".to_string(),
+        top: 5,
+        outbox: outbox.to_string(),
+    })
+    .unwrap()
+}
+
+fn deadline() -> Instant {
+    Instant::now() + Duration::from_secs(60)
+}
+
+// polls until `done` or a few seconds have passed
+fn wait_for(client: &mut Client, done: impl Fn(&Client) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !done(client) {
+        assert!(Instant::now() < deadline, "timed out");
+        client.poll();
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn server_only_takes_scores_its_replay_gives() {
+    let server = server("leaderboard-verify.json");
+    let entry = finished_run(49);
+
+    let mut cheat = entry.clone();
+    cheat.score += 1000;
+    let (status, _) = server.respond("POST", "/scores", &cheat.serialize_json(), deadline());
+    assert_eq!(status, 422);
+    let (status, _) = server.respond("POST", "/scores", "{}", deadline());
+    assert_eq!(status, 400);
+
+    // played with easier rules than the seed has, or with help
+    let mut slow = entry.clone();
+    slow.replay.modifiers.speed = 0.5;
+    let mut rapid = entry.clone();
+    rapid.replay.modifiers.weapon = Weapon::Rapid;
+    let mut crowded = entry.clone();
+    crowded.replay.modifiers.asteroids = 1e9;
+    let mut helped = entry.clone();
+    helped.replay.wingman = true;
+    for cheat in [slow, rapid, crowded, helped] {
+        let (status, _) = server.respond("POST", "/scores", &cheat.serialize_json(), deadline());
+        assert_eq!(status, 422);
+    }
+    let (status, reason) =
+        server.respond("POST", "/scores", &entry.serialize_json(), Instant::now());
+    assert_eq!(status, 503);
+    assert_eq!(reason, "the replay took too long to check");
+
+    let (status, body) =
+        server.respond("POST", "/board/scores", &entry.serialize_json(), deadline());
+    assert_eq!(status, 200);
+    assert!(body.contains("\"ranked\":true"));
+    let (status, body) = server.respond("GET", "/board/scores?seed=49&top=3", "", deadline());
+    assert_eq!(status, 200);
+    assert!(body.contains(&format!("\"score\":{}", entry.score)));
+    assert!(body.contains("\"name\":\"ace\""));
+    let (_, body) = server.respond("GET", "/scores?seed=50", "", deadline());
+    assert_eq!(body, "{\"rows\":[]}");
+
+    assert_eq!(
+        Table::load(&temp_file("leaderboard-verify.json"))
+            .entries
+            .len(),
+        1
+    );
+    std::fs::remove_file(temp_file("leaderboard-verify.json")).unwrap();
+}
+
+#[test]
+fn scores_wait_in_the_outbox_while_offline() {
+    let outbox = temp_file("outbox.json");
+    let entry = finished_run(7);
+
+    // nothing listens on a port that was just let go
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut offline = client(format!("http://127.0.0.1:{}", port), &outbox);
+    offline.submit(entry.clone());
+    wait_for(&mut offline, |c| {
+        c.status.as_deref() == Some("Leaderboard offline, score saved")
+    });
+    assert_eq!(Table::load(&outbox).entries.len(), 1);
+
+    // sent again on the next start, still offline, and kept only once
+    let mut again = client(format!("http://127.0.0.1:{}", port), &outbox);
+    wait_for(&mut again, |c| c.status.is_some());
+    assert_eq!(Table::load(&outbox).entries.len(), 1);
+
+    let server = server("leaderboard-online.json");
+    let port = server.local_addr().unwrap().port();
+    thread::spawn(move || server.run());
+    let mut online = client(format!("http://127.0.0.1:{}/", port), &outbox);
+    assert_eq!(Table::load(&outbox).entries.len(), 1);
+    wait_for(&mut online, |c| c.status.is_some());
+    assert_eq!(online.status.as_deref(), Some("Online place 1"));
+    assert!(Table::load(&outbox).entries.is_empty());
+
+    online.fetch(7);
+    wait_for(&mut online, |c| c.top.is_some());
+    let top = online.top.unwrap();
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].score, entry.score);
+    std::fs::remove_file(temp_file("leaderboard-online.json")).unwrap();
+    std::fs::remove_file(outbox).unwrap();
+}
+
+#[test]
+fn daily_runs_are_checked_against_that_days_rules() {
+    let server = server("leaderboard-daily.json");
+    let challenge = Challenge::today();
+    let mut entry = Entry {
+        name: "ace".to_string(),
+        score: 0,
+        stage: 0,
+        ticks: 0,
+        splits: Vec::new(),
+        replay: Replay::new(&challenge.game(glam::vec2(800.0, 600.0))),
+    };
+    // the right rules get as far as playing the replay
+    let (_, reason) = server.respond("POST", "/scores", &entry.serialize_json(), deadline());
+    assert_eq!(reason, "the run doesn't end");
+
+    entry.replay.modifiers = Modifiers {
+        speed: challenge.modifiers.speed + 0.1,
+        ..challenge.modifiers
+    };
+    let (status, reason) = server.respond("POST", "/scores", &entry.serialize_json(), deadline());
+    assert_eq!(status, 422);
+    assert_eq!(reason, "the rules don't match the seed");
+}
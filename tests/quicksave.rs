@@ -0,0 +1,78 @@
+use asteroids_rs::{
+    autopilot,
+    components::{get_new_game_state, Mode},
+    daily::Challenge,
+    quicksave, rollback, stage, step,
+};
+use glam::vec2;
+
+#[test]
+fn a_loaded_game_plays_on_the_same() {
+    let mut gs = get_new_game_state(1, Mode::Campaign);
+    for _ in 0..600 {
+        let action = autopilot::act(&gs, 0);
+        step(&mut gs, &[action]);
+    }
+    let bytes = quicksave::encode(&gs);
+    let mut loaded = quicksave::decode(&bytes).unwrap();
+    assert_eq!(quicksave::encode(&loaded), bytes);
+    assert_eq!(loaded.asteroids[0].points, gs.asteroids[0].points);
+
+    for _ in 0..600 {
+        let action = autopilot::act(&gs, 0);
+        step(&mut gs, &[action]);
+        step(&mut loaded, &[action]);
+        assert_eq!(rollback::checksum(&loaded), rollback::checksum(&gs));
+    }
+    assert_eq!(quicksave::encode(&loaded), quicksave::encode(&gs));
+
+    // the other players' games wait on the bench in hot-seat
+    let hotseat = get_new_game_state(2, Mode::HotSeat);
+    let bytes = quicksave::encode(&hotseat);
+    assert_eq!(
+        quicksave::encode(&quicksave::decode(&bytes).unwrap()),
+        bytes
+    );
+}
+
+#[test]
+fn a_game_on_a_stage_pack_loads_with_its_stages() {
+    let mut gs = get_new_game_state(1, Mode::Campaign);
+    gs.stages.truncate(2);
+    gs.stages[1].name = "Homemade".to_string();
+    gs.stages[1].seed += 1;
+    stage::start(&mut gs, 1);
+    let loaded = quicksave::decode(&quicksave::encode(&gs)).unwrap();
+    assert_eq!(loaded.stages.len(), 2);
+    assert_eq!(loaded.stages[1].name, "Homemade");
+    assert_eq!(loaded.stages[1].seed, gs.stages[1].seed);
+    assert_eq!(rollback::checksum(&loaded), rollback::checksum(&gs));
+}
+
+#[test]
+fn only_good_saves_of_this_version_load() {
+    let gs = get_new_game_state(2, Mode::Versus);
+    let bytes = quicksave::encode(&gs);
+    assert!(quicksave::decode(&bytes).is_ok());
+
+    let mut newer = bytes.clone();
+    newer[4] += 1;
+    let e = quicksave::decode(&newer).err().unwrap();
+    assert!(e.contains("another version"), "{}", e);
+    assert!(quicksave::decode(&bytes[..bytes.len() - 1]).is_err());
+    assert!(quicksave::decode(b"{\"score\": 1}").is_err());
+    assert!(quicksave::load("no-such-quicksave.bin").is_err());
+}
+
+#[test]
+fn ranked_runs_cant_be_saved() {
+    let solo = get_new_game_state(1, Mode::Campaign);
+    assert!(!quicksave::is_ranked(&solo, false));
+    assert!(quicksave::is_ranked(&solo, true));
+    assert!(!quicksave::is_ranked(
+        &get_new_game_state(2, Mode::Campaign),
+        true
+    ));
+    let daily = Challenge::for_day(20745).game(vec2(800.0, 600.0));
+    assert!(quicksave::is_ranked(&daily, false));
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use asteroids_rs::{
+    autopilot,
+    components::{get_new_game_state, Mode, RunState, HIGH_SCORES},
+    ghost::Race,
+    replay::Replay,
+    scores::{Entry, Table},
+    stage, step,
+};
+use common::temp_file;
+
+fn entry(seed: u64, score: i32) -> Entry {
+    Entry {
+        name: String::new(),
+        score,
+        stage: 0,
+        ticks: 0,
+        splits: Vec::new(),
+        replay: Replay {
+            seed,
+            screen: [400.0, 300.0],
+            ..Replay::default()
+        },
+    }
+}
+
+#[test]
+fn replays_play_the_run_again() {
+    let mut gs = get_new_game_state(1, Mode::Campaign);
+    gs.seed = 46;
+    stage::start(&mut gs, 0);
+    let mut replay = Replay::new(&gs);
+    for _ in 0..3000 {
+        let action = autopilot::act(&gs, 0);
+        replay.push(action);
+        step(&mut gs, &[action]);
+    }
+
+    let again = replay.play();
+    assert_eq!(again.tick, gs.tick);
+    assert_eq!(again.players[0].score, gs.players[0].score);
+    assert_eq!(again.players[0].ship.pos, gs.players[0].ship.pos);
+    assert_eq!(again.asteroids.len(), gs.asteroids.len());
+}
+
+#[test]
+fn each_seed_keeps_its_own_best_scores() {
+    let mut table = Table::default();
+    for score in 0..HIGH_SCORES as i32 {
+        assert!(table.insert(entry(1, (score + 1) * 10)).is_some());
+    }
+    assert_eq!(table.insert(entry(1, 5)), None);
+    assert_eq!(table.insert(entry(1, 1000)), Some(0));
+    assert_eq!(table.insert(entry(2, 1)), Some(0));
+
+    let scores: Vec<i32> = table.for_seed(1).map(|e| e.score).collect();
+    assert_eq!(scores.len(), HIGH_SCORES);
+    assert_eq!(scores[0], 1000);
+    assert!(scores.windows(2).all(|w| w[0] >= w[1]));
+    assert_eq!(table.best(&entry(2, 0).replay).unwrap().score, 1);
+
+    let path = temp_file("scores.json");
+    table.save(&path).unwrap();
+    assert_eq!(Table::load(&path).entries, table.entries);
+    std::fs::remove_file(&path).unwrap();
+    assert!(Table::load(&path).entries.is_empty());
+}
+
+// an autopilot run until game over through a race, like the game loop does
+fn race_once(race: &mut Race, check_ghost: bool) -> i32 {
+    let mut gs = get_new_game_state(1, Mode::Campaign);
+    loop {
+        let action = autopilot::act(&gs, 0);
+        race.before(&gs);
+        step(&mut gs, &[action]);
+        race.after(&gs, action);
+        if gs.run_state == RunState::StageComplete && stage::is_game_over(&gs) {
+            return gs.players[0].score;
+        }
+        if check_ghost {
+            // the same actions on the same field, the ghost is right on the ship
+            assert_eq!(race.ghost(&gs).unwrap().pos, gs.players[0].ship.pos);
+            assert_eq!(race.deltas(&gs).unwrap().0, 0);
+        }
+        assert!(gs.tick < 60 * 60 * 30, "the run never ended");
+    }
+}
+
+#[test]
+fn finished_runs_become_ghosts() {
+    let path = temp_file("race.json");
+    let mut race = Race::new(&path);
+    assert!(race
+        .deltas(&get_new_game_state(1, Mode::Campaign))
+        .is_none());
+    let score = race_once(&mut race, false);
+
+    let saved = Table::load(&path);
+    assert_eq!(saved.entries.len(), 1);
+    assert_eq!(saved.entries[0].score, score);
+    assert_eq!(saved.entries[0].replay.play().players[0].score, score);
+
+    race_once(&mut Race::new(&path), true);
+    std::fs::remove_file(&path).unwrap();
+}
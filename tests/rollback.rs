@@ -0,0 +1,172 @@
+use asteroids_rs::{
+    components::{get_new_game_state, Action, GameState, Mode, INPUT_DELAY},
+    net::{Config, Link, Packet, Shim},
+    rollback::{checksum, Session},
+    step,
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// the players stop touching the keys here, what comes after is all settled
+const SETTLE: u32 = 240;
+const END: u32 = 300;
+
+// ports of their own for each test, tests run side by side
+fn ports(test: u16) -> (u16, u16) {
+    let base = 30000 + (std::process::id() % 8000) as u16 * 4 + test * 2;
+    (base, base + 1)
+}
+
+struct Peer {
+    session: Session,
+    gs: GameState,
+}
+
+fn peer(player: usize, port: u16, peer_port: u16, shim: Shim) -> Peer {
+    let config = Config {
+        port,
+        peer: format!("127.0.0.1:{}", peer_port).parse().unwrap(),
+        player,
+        mode: Mode::Campaign,
+        shim,
+    };
+    Peer {
+        session: Session::new(player, Link::bind(&config).unwrap()),
+        gs: get_new_game_state(2, Mode::Campaign),
+    }
+}
+
+fn pair(test: u16, shim: Shim) -> (Peer, Peer) {
+    let (a, b) = ports(test);
+    let (mut one, mut two) = (peer(0, a, b, shim), peer(1, b, a, shim));
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let (mut one_in, mut two_in) = (false, false);
+    while !(one_in && two_in) {
+        assert!(Instant::now() < deadline, "the peers never met");
+        one_in |= one.session.connect(Mode::Campaign);
+        two_in |= two.session.connect(Mode::Campaign);
+        thread::sleep(Duration::from_millis(1));
+    }
+    (one, two)
+}
+
+// something different every few ticks so the guesses keep going wrong
+fn script(player: usize, tick: u32) -> Action {
+    if tick >= SETTLE {
+        return Action::default();
+    }
+    let bits = ((tick / 5) as u8).wrapping_mul(37) ^ (player as u8).wrapping_mul(101);
+    Action {
+        confirm: false,
+        ..Action::from_bits(bits)
+    }
+}
+
+// advances both peers until `done` says so, returning the longest rollback
+fn play(peers: &mut [Peer; 2], mut done: impl FnMut(&[Peer; 2]) -> bool) -> u32 {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut rollback = 0;
+    while !done(peers) {
+        assert!(Instant::now() < deadline, "the game got stuck");
+        for p in peers.iter_mut() {
+            let action = script(p.session.local, p.gs.tick);
+            p.session.advance(&mut p.gs, action);
+            rollback = rollback.max(p.session.rollback);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    rollback
+}
+
+#[test]
+fn late_inputs_are_played_again_to_the_same_end() {
+    let shim = Shim {
+        latency: 0.03,
+        jitter: 0.01,
+        loss: 0.2,
+    };
+    let (one, two) = pair(0, shim);
+    let mut peers = [one, two];
+    let mut ends = [None, None];
+    let rollback = play(&mut peers, |peers| {
+        for (end, p) in ends.iter_mut().zip(peers) {
+            if p.gs.tick == END && end.is_none() {
+                *end = Some(checksum(&p.gs));
+            }
+        }
+        ends.iter().all(|e| e.is_some())
+    });
+    assert!(rollback > 0, "nothing was ever played again");
+    assert!(peers.iter().all(|p| p.session.desync.is_none()));
+
+    // the same game played with every input on time
+    let mut gs = get_new_game_state(2, Mode::Campaign);
+    while gs.tick < END {
+        let actions: Vec<Action> = (0..2)
+            .map(|player| match gs.tick.checked_sub(INPUT_DELAY) {
+                Some(tick) => script(player, tick),
+                None => Action::default(),
+            })
+            .collect();
+        step(&mut gs, &actions);
+    }
+    assert_eq!(ends, [Some(checksum(&gs)); 2]);
+}
+
+#[test]
+fn a_game_that_went_different_is_a_desync() {
+    let (one, mut two) = pair(1, Shim::default());
+    two.gs.players[0].score += 1;
+    let mut peers = [one, two];
+    play(&mut peers, |peers| {
+        peers
+            .iter()
+            .any(|p| p.session.desync.is_some() || p.gs.tick >= END)
+    });
+    assert!(peers.iter().any(|p| p.session.desync.is_some()));
+}
+
+#[test]
+fn packets_read_back_the_same_and_cut_short_not_at_all() {
+    let packets = [
+        Packet::Hello { mode: 1, player: 0 },
+        Packet::Input {
+            tick: 1234,
+            ack: 1200,
+            advantage: -3,
+            hash_tick: 1170,
+            hash: 0xdead_beef_cafe_f00d,
+            first: 1195,
+            actions: vec![0, 1, 255, 64],
+        },
+        Packet::Input {
+            tick: 0,
+            ack: 0,
+            advantage: 0,
+            hash_tick: 0,
+            hash: 0,
+            first: 0,
+            actions: Vec::new(),
+        },
+    ];
+    for packet in packets.iter() {
+        let bytes = packet.encode();
+        let decoded = Packet::decode(&bytes).unwrap();
+        assert_eq!(decoded.encode(), bytes);
+        for len in 0..bytes.len() {
+            assert!(Packet::decode(&bytes[..len]).is_none(), "{} bytes", len);
+        }
+    }
+    if let Some(Packet::Input {
+        advantage, actions, ..
+    }) = Packet::decode(&packets[1].encode())
+    {
+        assert_eq!(advantage, -3);
+        assert_eq!(actions, [0, 1, 255, 64]);
+    } else {
+        panic!("not an input packet");
+    }
+    assert!(Packet::decode(&[9, 1, 2]).is_none());
+}
@@ -0,0 +1,213 @@
+use asteroids_rs::{
+    components::{get_new_game_state, Action, Mode},
+    server::{Config, Connection, Frame, Message, Server},
+    step,
+};
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::mpsc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn config(mode: Mode, max_players: usize) -> Config {
+    Config {
+        port: 0,
+        mode,
+        max_players,
+        stages: None,
+        rate: 60,
+    }
+}
+
+// a server on a free port, it stops by itself once its players have left
+fn serve(config: Config) -> (SocketAddr, JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel();
+    let server = thread::spawn(move || {
+        let mut server = Server::bind(config).unwrap();
+        tx.send(server.local_addr().unwrap().port()).unwrap();
+        server.run();
+    });
+    let port = rx.recv().unwrap();
+    (SocketAddr::from(([127, 0, 0, 1], port)), server)
+}
+
+// a scripted client keeping its own copy of the frame up to date
+struct Bot {
+    connection: Connection,
+    inbox: VecDeque<Message>,
+    frame: Frame,
+}
+
+impl Bot {
+    fn join(addr: SocketAddr, name: &str) -> Bot {
+        let mut connection = Connection::connect(addr).unwrap();
+        connection.send(&Message::Join {
+            name: name.to_string(),
+        });
+        Bot {
+            connection,
+            inbox: VecDeque::new(),
+            frame: Frame::default(),
+        }
+    }
+
+    // the next message that `wanted` picks, skipping the others
+    fn wait<T>(&mut self, mut wanted: impl FnMut(&Message, &Frame) -> Option<T>) -> T {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            while let Some(message) = self.inbox.pop_front() {
+                if let Message::Delta(delta) = &message {
+                    self.frame.apply(delta).expect("bad delta");
+                }
+                if let Some(found) = wanted(&message, &self.frame) {
+                    return found;
+                }
+            }
+            assert!(Instant::now() < deadline, "timed out");
+            self.inbox.extend(self.connection.receive());
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn lobby(&mut self) -> Vec<(String, bool)> {
+        self.wait(|m, _| match m {
+            Message::Lobby { players } => Some(players.clone()),
+            _ => None,
+        })
+    }
+
+    fn start(&mut self) -> (u8, u8) {
+        self.wait(|m, _| match m {
+            Message::Start { seat, players } => Some((*seat, *players)),
+            _ => None,
+        })
+    }
+
+    fn next_frame(&mut self) -> Frame {
+        self.wait(|m, frame| matches!(m, Message::Delta(_)).then(|| frame.clone()))
+    }
+}
+
+fn wait_until_finished(server: JoinHandle<()>) {
+    let deadline = Instant::now() + TIMEOUT;
+    while !server.is_finished() {
+        assert!(Instant::now() < deadline, "server kept running");
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn game_starts_once_everyone_in_the_lobby_is_ready() {
+    let (addr, _) = serve(config(Mode::Versus, 3));
+    let mut a = Bot::join(addr, "a");
+    assert_eq!(a.lobby(), vec![("a".to_string(), false)]);
+    let mut b = Bot::join(addr, "b");
+    b.wait(|m, _| match m {
+        Message::Welcome { mode, max_players } => {
+            assert_eq!((*mode, *max_players), (Mode::Versus as u8, 3));
+            Some(())
+        }
+        _ => None,
+    });
+
+    a.connection.send(&Message::Ready);
+    let ready = vec![("a".to_string(), true), ("b".to_string(), false)];
+    while a.lobby() != ready {}
+    b.connection.send(&Message::Ready);
+
+    let mut seats = [a.start(), b.start()];
+    seats.sort();
+    assert_eq!(seats, [(0, 2), (1, 2)]);
+    assert_eq!(a.next_frame().pilots.len(), 2);
+    assert_eq!(b.next_frame().pilots.len(), 2);
+}
+
+#[test]
+fn full_lobby_refuses_more_players() {
+    let (addr, _) = serve(config(Mode::Campaign, 1));
+    let mut a = Bot::join(addr, "a");
+    a.lobby();
+    let mut b = Bot::join(addr, "b");
+    let reason = b.wait(|m, _| match m {
+        Message::Refused { reason } => Some(reason.clone()),
+        _ => None,
+    });
+    assert_eq!(reason, "the lobby is full");
+}
+
+#[test]
+fn inputs_fly_the_ship() {
+    let (addr, _) = serve(config(Mode::Campaign, 1));
+    let mut a = Bot::join(addr, "a");
+    a.connection.send(&Message::Ready);
+    assert_eq!(a.start(), (0, 1));
+    let before = a.next_frame();
+
+    a.connection.send(&Message::Input(Action {
+        thrust: true,
+        ..Action::default()
+    }));
+    let after = a.wait(|m, frame| {
+        let later = matches!(m, Message::Delta(_)) && frame.tick > before.tick + 30;
+        later.then(|| frame.clone())
+    });
+    let moved = after.pilots[0].ship.pos.distance(before.pilots[0].ship.pos);
+    assert!(moved > 1.0, "ship only moved {}", moved);
+}
+
+#[test]
+fn server_stops_when_the_players_leave() {
+    let (addr, server) = serve(config(Mode::Campaign, 2));
+    let mut a = Bot::join(addr, "a");
+    a.connection.send(&Message::Ready);
+    a.start();
+    a.next_frame();
+    drop(a);
+    wait_until_finished(server);
+}
+
+#[test]
+fn deltas_rebuild_the_frame() {
+    let mut gs = get_new_game_state(2, Mode::Campaign);
+    let fire = Action {
+        fire: true,
+        left: true,
+        ..Action::default()
+    };
+    let (mut sent, mut copy) = (Frame::default(), Frame::default());
+    for _ in 0..300 {
+        step(&mut gs, &[fire, Action::default()]);
+        let frame = Frame::capture(&gs);
+        copy.apply(&frame.delta(&sent)).unwrap();
+        assert_eq!(copy, frame);
+        sent = frame;
+    }
+    assert!(!copy.bullets.is_empty());
+}
+
+#[test]
+fn config_comes_from_the_command_line() {
+    let args = |line: &str| -> Vec<String> { line.split_whitespace().map(String::from).collect() };
+    let pack = concat!(env!("CARGO_MANIFEST_DIR"), "/src/arenas.json");
+
+    let config = Config::parse(&args(&format!(
+        "--mode versus --players 4 --stages {} --rate 20 --port 7400",
+        pack
+    )))
+    .unwrap();
+    assert!(config.mode == Mode::Versus);
+    assert_eq!(
+        (config.max_players, config.rate, config.port),
+        (4, 20, 7400)
+    );
+    assert!(config.stages.is_some_and(|s| !s.is_empty()));
+
+    assert!(Config::parse(&args("--players 3")).is_err());
+    assert!(Config::parse(&args("--mode hotseat")).is_err());
+    assert!(Config::parse(&args("--stages no/such/pack.json")).is_err());
+    assert!(Config::parse(&args("--rate")).is_err());
+}
@@ -0,0 +1,95 @@
+use asteroids_rs::{
+    components::{ShapeConfig, ASTEROID_SHAPE},
+    spawner,
+    utils::{contains, is_simple},
+};
+use glam::{vec2, Vec2};
+
+// a U standing on its base, the notch is cut out of the top
+fn u_shape() -> Vec<Vec2> {
+    vec![
+        vec2(0.0, 0.0),
+        vec2(3.0, 0.0),
+        vec2(3.0, 3.0),
+        vec2(2.0, 3.0),
+        vec2(2.0, 1.0),
+        vec2(1.0, 1.0),
+        vec2(1.0, 3.0),
+        vec2(0.0, 3.0),
+    ]
+}
+
+#[test]
+fn every_shape_is_simple() {
+    let rough = ShapeConfig {
+        vertices: 32,
+        octaves: 5,
+        roughness: 1.0,
+        notches: 8,
+        notch_depth: 0.9,
+        ..ASTEROID_SHAPE
+    };
+    for config in [&ASTEROID_SHAPE, &rough] {
+        for seed in 0..2000 {
+            for size in [1.0, 2.0, 3.0] {
+                let shape = spawner::shape(seed, size, config);
+                assert_eq!(shape.outline.len(), config.vertices);
+                assert!(is_simple(&shape.outline), "seed {} size {}", seed, size);
+                for crater in shape.craters.iter() {
+                    assert!(
+                        crater.iter().all(|p| contains(&shape.outline, *p)),
+                        "seed {} size {}",
+                        seed,
+                        size
+                    );
+                }
+            }
+        }
+    }
+
+    // the same seed always makes the same rock
+    let a = spawner::shape(7, 2.0, &ASTEROID_SHAPE);
+    let b = spawner::shape(7, 2.0, &ASTEROID_SHAPE);
+    assert_eq!(a.outline, b.outline);
+    assert_eq!(a.craters, b.craters);
+}
+
+#[test]
+fn outlines_that_cross_themselves_are_not_simple() {
+    assert!(is_simple(&u_shape()));
+    let bowtie = [
+        vec2(0.0, 0.0),
+        vec2(2.0, 2.0),
+        vec2(2.0, 0.0),
+        vec2(0.0, 2.0),
+    ];
+    assert!(!is_simple(&bowtie));
+
+    // the notch pinched shut, its corners touching
+    let mut pinched = u_shape();
+    pinched[3] = vec2(1.0, 3.0);
+    assert!(!is_simple(&pinched));
+
+    // an edge running back along the one before the last
+    let folded = [
+        vec2(0.0, 0.0),
+        vec2(3.0, 0.0),
+        vec2(3.0, 1.0),
+        vec2(1.0, 0.0),
+        vec2(0.0, 1.0),
+    ];
+    assert!(!is_simple(&folded));
+}
+
+#[test]
+fn concave_outlines_contain_their_inside_only() {
+    let u = u_shape();
+    assert!(contains(&u, vec2(0.5, 2.5)));
+    assert!(contains(&u, vec2(2.5, 2.5)));
+    assert!(contains(&u, vec2(1.5, 0.5)));
+    // in the notch, and level with its floor and corners
+    assert!(!contains(&u, vec2(1.5, 2.0)));
+    assert!(!contains(&u, vec2(1.5, 3.0)));
+    assert!(!contains(&u, vec2(4.0, 1.0)));
+    assert!(!contains(&u, vec2(-1.0, 1.5)));
+}
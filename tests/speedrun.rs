@@ -0,0 +1,90 @@
+mod common;
+
+use asteroids_rs::{
+    autopilot,
+    components::{get_new_game_state, Mode, TICK},
+    speedrun::{Category, Mark, Splits, Timer},
+    stage, step, wingman,
+};
+use common::temp_dir;
+
+#[test]
+fn livesplit_export_lists_every_stage() {
+    let category = Category {
+        seed: 0,
+        wingman: false,
+        stages: vec!["Stage 1".to_string(), "Hive & Co".to_string()],
+        attempts: 3,
+        personal_best: vec![60 * 75, 60 * 3700],
+        best_segments: vec![60 * 70],
+    };
+    let lss = category.to_livesplit();
+    assert!(lss.contains("<AttemptCount>3</AttemptCount>"));
+    assert!(lss.contains("<Name>Hive &amp; Co</Name>"));
+    assert!(lss.contains("<RealTime>00:01:15.0000000</RealTime>"));
+    assert!(lss.contains("<GameTime>01:01:40.0000000</GameTime>"));
+    assert!(lss.contains("<BestSegmentTime><RealTime>00:01:10.0000000</RealTime>"));
+    assert!(lss.contains("<BestSegmentTime />"));
+    assert_eq!(lss.matches("<Segment>").count(), 2);
+}
+
+#[test]
+fn timer_splits_cleared_stages_and_saves_them() {
+    let dir = temp_dir("splits");
+    let path = dir.join("splits.json").to_string_lossy().into_owned();
+    let mut timer = Timer::new(&path);
+    let mut gs = get_new_game_state(1, Mode::Campaign);
+    stage::start(&mut gs, 0);
+    for _ in 0..20 * 60 * 60 {
+        let action = autopilot::act(&gs, 0);
+        timer.before(&gs);
+        step(&mut gs, &[action]);
+        timer.after(&gs);
+        if !timer.run_splits().is_empty() {
+            break;
+        }
+    }
+
+    let split = &timer.run_splits()[0];
+    assert_eq!(split.stage, gs.stages[0].name);
+    assert_eq!(split.mark, Mark::New);
+    assert_eq!(split.delta, None);
+    assert_eq!(timer.time(&gs), Some(split.time as f32 * TICK));
+
+    let saved = Splits::load(&path);
+    assert_eq!(saved.categories, timer.splits.categories);
+    assert_eq!(saved.categories[0].attempts, 1);
+    assert_eq!(saved.categories[0].best_segments, vec![split.time]);
+    assert!(dir.join("splits.lss").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn wingman_runs_are_a_category_of_their_own() {
+    let dir = temp_dir("wingman-splits");
+    let path = dir.join("splits.json").to_string_lossy().into_owned();
+    let mut timer = Timer::new(&path);
+    for with_wingman in [false, true] {
+        let mut gs = get_new_game_state(1, Mode::Campaign);
+        if with_wingman {
+            wingman::toggle(&mut gs);
+        }
+        stage::start(&mut gs, 0);
+        timer.before(&gs);
+        step(&mut gs, &[Default::default()]);
+        timer.after(&gs);
+    }
+
+    let categories = &timer.splits.categories;
+    assert_eq!(categories.len(), 2);
+    assert!(!categories[0].wingman && categories[1].wingman);
+    assert!(categories.iter().all(|c| c.attempts == 1));
+    assert!(categories[1]
+        .to_livesplit()
+        .contains("<CategoryName>Campaign with wingman</CategoryName>"));
+
+    timer.splits.save(&path).unwrap();
+    assert!(dir.join("splits.lss").exists());
+    assert!(dir.join("splits-wingman.lss").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use asteroids_rs::{
+    components::{get_new_game_state, Material, Mode},
+    stage::{self, StageKind},
+};
+use common::temp_file;
+
+fn load(name: &str, json: &str) -> Result<Vec<stage::Stage>, String> {
+    let path = temp_file(name);
+    std::fs::write(&path, json).unwrap();
+    let stages = stage::load_pack(&path);
+    std::fs::remove_file(&path).unwrap();
+    stages
+}
+
+#[test]
+fn stages_can_change_how_asteroids_break() {
+    let stages = load(
+        "fragments.json",
+        r#"[
+            {"name": "built in", "start_pos": [0, 0], "seed": 1, "asteroids": []},
+            {
+                "name": "brittle", "start_pos": [0, 0], "seed": 2, "asteroids": [],
+                "fragments": [
+                    {"material": "Crystal", "size": 3, "children": []},
+                    {"size": 3, "children": [1, 1, 1, 1]}
+                ]
+            }
+        ]"#,
+    )
+    .unwrap();
+
+    let built_in = &stages[0];
+    assert_eq!(
+        built_in.fragment_sizes(Material::Rocky, 3.0),
+        &[2.0, 2.0, 2.0]
+    );
+    assert_eq!(built_in.fragment_sizes(Material::Ice, 2.0).len(), 4);
+    assert!(built_in.fragment_sizes(Material::Rocky, 1.0).is_empty());
+
+    let brittle = &stages[1];
+    assert_eq!(brittle.fragment_sizes(Material::Rocky, 3.0), &[1.0; 4]);
+    assert!(brittle.fragment_sizes(Material::Crystal, 3.0).is_empty());
+    assert!(brittle.fragment_sizes(Material::Rocky, 2.0).is_empty());
+}
+
+#[test]
+fn a_rule_for_the_material_wins_over_one_listed_before_it() {
+    let stages = load(
+        "fragment-order.json",
+        r#"[{
+            "name": "generic first", "start_pos": [0, 0], "seed": 1, "asteroids": [],
+            "fragments": [
+                {"size": 2, "children": [1, 1]},
+                {"material": "Ice", "size": 2, "children": [1, 1, 1]}
+            ]
+        }]"#,
+    )
+    .unwrap();
+    assert_eq!(stages[0].fragment_sizes(Material::Ice, 2.0), &[1.0; 3]);
+    assert_eq!(stages[0].fragment_sizes(Material::Rocky, 2.0), &[1.0; 2]);
+}
+
+#[test]
+fn a_boss_stage_needs_its_boss() {
+    let e = load(
+        "bossless.json",
+        r#"[{"name": "empty throne", "type": "Boss", "start_pos": [0, 0], "seed": 1, "asteroids": []}]"#,
+    )
+    .err()
+    .unwrap();
+    assert!(e.contains("empty throne has no boss"), "{}", e);
+}
+
+#[test]
+fn a_boss_stage_made_without_its_boss_still_starts() {
+    let mut gs = get_new_game_state(1, Mode::Campaign);
+    let i = gs
+        .stages
+        .iter()
+        .position(|s| s.kind == StageKind::Boss)
+        .unwrap();
+    gs.stages[i].boss = None;
+    stage::start(&mut gs, i);
+    assert!(gs.boss.is_none());
+    // the built in stages and arenas load, they went through the same checks
+    assert!(!stage::load().is_empty());
+    assert!(!stage::load_arenas().is_empty());
+}
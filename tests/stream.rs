@@ -0,0 +1,70 @@
+use asteroids_rs::{
+    components::{get_new_game_state, Action, Mode},
+    step,
+    stream::{Publisher, Spectator},
+};
+use std::{
+    net::SocketAddr,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn spectator_sees_the_published_game() {
+    let mut publisher = Publisher::bind(0).unwrap();
+    let addr = SocketAddr::from(([127, 0, 0, 1], publisher.local_addr().unwrap().port()));
+    let mut spectator = Spectator::connect(addr).unwrap();
+
+    let mut gs = get_new_game_state(2, Mode::Versus);
+    // breaks up on the first tick
+    gs.asteroids[0].collision = true;
+    let actions = [
+        Action {
+            thrust: true,
+            fire: true,
+            ..Action::default()
+        },
+        Action {
+            left: true,
+            ..Action::default()
+        },
+    ];
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while gs.tick < 120 || spectator.game.as_ref().is_none_or(|s| s.tick != gs.tick) {
+        assert!(Instant::now() < deadline, "timed out");
+        if gs.tick < 120 {
+            step(&mut gs, &actions);
+            publisher.publish(&gs);
+        }
+        assert!(spectator.update());
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(publisher.viewers(), 1);
+
+    let seen = spectator.game.as_ref().unwrap();
+    assert!(seen.mode == Mode::Versus && seen.run_state == gs.run_state);
+    for (a, b) in seen.players.iter().zip(gs.players.iter()) {
+        assert_eq!((a.ship.pos, a.ship.angle), (b.ship.pos, b.ship.angle));
+        assert_eq!((a.score, a.lives), (b.score, b.lives));
+    }
+    assert_eq!(seen.asteroids.len(), gs.asteroids.len());
+    for (a, b) in seen.asteroids.iter().zip(gs.asteroids.iter()) {
+        assert_eq!(
+            (a.pos, a.w, a.material as u8),
+            (b.pos, b.w, b.material as u8)
+        );
+        // fragments too, they can't be rebuilt from a seed
+        assert_eq!((&a.points, &a.craters), (&b.points, &b.craters));
+    }
+    assert_eq!(seen.bullets.len(), gs.bullets.len());
+
+    drop(spectator);
+    for _ in 0..100 {
+        publisher.publish(&gs);
+        if publisher.viewers() == 0 {
+            return;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("the spectator is still counted after leaving");
+}
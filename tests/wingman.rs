@@ -0,0 +1,31 @@
+use asteroids_rs::{
+    components::{get_new_game_state, Action, Bullet, Mode, Owner},
+    step, wingman,
+};
+use glam::Vec2;
+
+#[test]
+fn wingman_kills_score_for_the_first_player_but_arent_their_hits() {
+    let mut gs = get_new_game_state(1, Mode::Campaign);
+    wingman::toggle(&mut gs);
+    gs.asteroids.truncate(1);
+    let a = &mut gs.asteroids[0];
+    a.hits = 1;
+    // on screen and out of the way, the bullet would wrap around otherwise
+    a.pos = gs.screen / 4.0;
+    a.vel = Vec2::ZERO;
+    let pos = a.pos;
+    gs.bullets.push(Bullet {
+        pos,
+        created_at: gs.time,
+        vel: Vec2::ZERO,
+        collision: false,
+        owner: Owner::Wingman,
+    });
+
+    step(&mut gs, &[Action::default()]);
+    let player = &gs.players[0];
+    assert_eq!(player.combo, 1);
+    assert!(player.score > 0);
+    assert_eq!(player.hits, 0);
+}